@@ -0,0 +1,18 @@
+//! Runtime API definition for the audit pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_audit::{AuditPunishment, AuditRecord};
+
+sp_api::decl_runtime_apis! {
+	pub trait AuditApi<AccountId, BlockNumber> where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// The recent challenge rounds of a miner, oldest first.
+		fn audit_history(miner: AccountId) -> Vec<AuditRecord<AccountId, BlockNumber>>;
+	}
+}
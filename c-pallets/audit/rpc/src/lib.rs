@@ -0,0 +1,83 @@
+//! RPC interface for the audit pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::error::{CallError, ErrorObject},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+pub use pallet_audit_runtime_api::AuditApi as AuditRuntimeApi;
+use pallet_audit_runtime_api::AuditRecord;
+
+#[rpc(client, server)]
+pub trait AuditApi<BlockHash, AccountId, BlockNumber> {
+	/// The recent challenge rounds of a miner, oldest first.
+	#[method(name = "audit_queryHistory")]
+	fn query_history(
+		&self,
+		miner: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<AuditRecord<AccountId, BlockNumber>>>;
+}
+
+/// Error type of this RPC api.
+pub enum Error {
+	/// The call to runtime failed.
+	RuntimeError,
+}
+
+impl From<Error> for i32 {
+	fn from(e: Error) -> i32 {
+		match e {
+			Error::RuntimeError => 1,
+		}
+	}
+}
+
+/// Provides RPC methods to query the audit pallet.
+pub struct Audit<C, P> {
+	client: Arc<C>,
+	_marker: PhantomData<P>,
+}
+
+impl<C, P> Audit<C, P> {
+	/// Creates a new instance of the Audit Rpc helper.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+impl<C, Block, AccountId, BlockNumber> AuditApiServer<<Block as BlockT>::Hash, AccountId, BlockNumber>
+	for Audit<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: AuditRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+	BlockNumber: Codec + Clone + Send + Sync + Serialize + DeserializeOwned + 'static,
+{
+	fn query_history(
+		&self,
+		miner: AccountId,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> RpcResult<Vec<AuditRecord<AccountId, BlockNumber>>> {
+		let api = self.client.runtime_api();
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		api.audit_history(&at, miner).map_err(|e| {
+			CallError::Custom(ErrorObject::owned(
+				Error::RuntimeError.into(),
+				"Unable to query audit history.",
+				Some(e.to_string()),
+			))
+			.into()
+		})
+	}
+}
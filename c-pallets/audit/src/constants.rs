@@ -1,3 +1,7 @@
 pub(super) const IDLE_FAULT_TOLERANT: u8 = 2;
 
-pub(super) const SERVICE_FAULT_TOLERANT: u8 = 2;
\ No newline at end of file
+pub(super) const SERVICE_FAULT_TOLERANT: u8 = 2;
+
+// Added to every credit score when weighing tee workers, so that workers without
+// a credit history still receive missions.
+pub(super) const BASE_CREDIT_WEIGHT: u32 = 100;
@@ -45,11 +45,12 @@ mod tests;
 
 mod types;
 use types::*;
+pub use types::{AuditRecord, AuditPunishment};
 
 mod constants;
 use constants::*;
 
-// pub mod migrations;
+pub mod migrations;
 
 pub use pallet::*;
 
@@ -57,7 +58,7 @@ pub use pallet::*;
 pub mod benchmarking;
 
 use sp_runtime::{
-	traits::{CheckedAdd, SaturatedConversion},
+	traits::{CheckedAdd, CheckedMul, SaturatedConversion},
 	RuntimeDebug, Permill,
 	offchain::storage::{StorageValueRef, StorageRetrievalError},
 };
@@ -82,11 +83,12 @@ use sp_core::{
 use sp_runtime::{Saturating, app_crypto::RuntimeAppPublic};
 use frame_system::offchain::{CreateSignedTransaction, SubmitTransaction};
 use pallet_file_bank::RandomFileList;
-use pallet_tee_worker::ScheduleFind;
-use pallet_sminer::MinerControl;
+use pallet_tee_worker::{ScheduleFind, VerifyMissionHandoff};
+use pallet_sminer::{MinerControl, MinerState};
 use pallet_storage_handler::StorageHandle;
 use scale_info::TypeInfo;
-use sp_core::H256;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use sp_std::{ 
 		convert:: { TryFrom, TryInto },
 		prelude::*,
@@ -102,7 +104,7 @@ type BlockNumberOf<T> = <T as frame_system::Config>::BlockNumber;
 pub const AUDIT: KeyTypeId = KeyTypeId(*b"cess");
 // type FailureRate = u32;
 
-const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
 
 pub mod sr25519 {
 	mod app_sr25519 {
@@ -191,7 +193,7 @@ pub mod pallet {
 		//one hours block
 		#[pallet::constant]
 		type OneHours: Get<BlockNumberOf<Self>>;
-		// randomness for seeds.
+		// VRF-backed randomness from rrsc, consumed through `VrfRandomness`.
 		type MyRandomness: Randomness<Option<Self::Hash>, Self::BlockNumber>;
 		//Find the consensus of the current block
 		type FindAuthor: FindAuthor<Self::AccountId>;
@@ -201,7 +203,7 @@ pub mod pallet {
 		type Scheduler: ScheduleFind<Self::AccountId>;
 		//It is used to increase or decrease the miners' computing power, space, and execute
 		// punishment
-		type MinerControl: MinerControl<Self::AccountId>;
+		type MinerControl: MinerControl<Self::AccountId, Self::BlockNumber>;
 
 		type StorageHandle: StorageHandle<Self::AccountId>;
 		//Configuration to be used for offchain worker
@@ -224,6 +226,24 @@ pub mod pallet {
 
 		#[pallet::constant]
 		type LockTime: Get<BlockNumberOf<Self>>;
+		// Number of blocks a signed challenge proposal digest is accepted after the block it names.
+		#[pallet::constant]
+		type ProposalValidity: Get<BlockNumberOf<Self>>;
+		// Number of challenge rounds kept in each miner's audit history.
+		#[pallet::constant]
+		type AuditHistoryLimit: Get<u32> + Clone + Eq + PartialEq;
+		// Maximum number of miners whose rewards are settled in one block.
+		#[pallet::constant]
+		type RewardSettleLimit: Get<u32>;
+		// Default proof window every challenged miner gets.
+		#[pallet::constant]
+		type ChallengeBaseLife: Get<BlockNumberOf<Self>>;
+		// Default extra proof window per TiB of challenged idle space.
+		#[pallet::constant]
+		type IdleLifePerTiB: Get<BlockNumberOf<Self>>;
+		// Default extra proof window per TiB of challenged service space.
+		#[pallet::constant]
+		type ServiceLifePerTiB: Get<BlockNumberOf<Self>>;
 	}
 
 	#[pallet::event]
@@ -235,6 +255,11 @@ pub mod pallet {
 
 		VerifyProof { tee_worker: AccountOf<T>, miner: AccountOf<T> },
 
+		UpdateChallengeLifeParams { params: ChallengeLifeParams<BlockNumberOf<T>> },
+
+		RoundRewardSettled { challenge_start: BlockNumberOf<T> },
+		//The pending proofs of an exiting tee worker were moved to other workers
+		MissionsHandedOff { tee_worker: AccountOf<T>, count: u32 },
 	}
 
 	/// Error for the audit pallet.
@@ -272,6 +297,14 @@ pub mod pallet {
 		NonExistentMission,
 
 		UnexpectedError,
+		//The proof window of this miner has ended
+		ProofExpired,
+		//This authority has already voted for the challenge proposal
+		DuplicateProposal,
+		//No other tee worker can take over the pending proofs
+		NoTeeForHandoff,
+		//Every tee worker's verify queue is full
+		NoTeeAvailable,
 	}
 
 	//Relevant time nodes for storage challenges
@@ -284,6 +317,21 @@ pub mod pallet {
 	#[pallet::getter(fn verify_duration)]
 	pub(super) type VerifyDuration<T: Config> = StorageValue<_, BlockNumberOf<T>, ValueQuery>;
 
+	#[pallet::type_value]
+	pub fn DefaultChallengeLifeParams<T: Config>() -> ChallengeLifeParams<BlockNumberOf<T>> {
+		ChallengeLifeParams {
+			base: T::ChallengeBaseLife::get(),
+			idle_per_tib: T::IdleLifePerTiB::get(),
+			service_per_tib: T::ServiceLifePerTiB::get(),
+		}
+	}
+
+	//Governance parameters used to calculate the proof window of each challenged miner
+	#[pallet::storage]
+	#[pallet::getter(fn challenge_life_params)]
+	pub(super) type LifeParams<T: Config> =
+		StorageValue<_, ChallengeLifeParams<BlockNumberOf<T>>, ValueQuery, DefaultChallengeLifeParams<T>>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn cur_authority_index)]
 	pub(super) type CurAuthorityIndex<T: Config> = StorageValue<_, u16, ValueQuery>;
@@ -296,10 +344,25 @@ pub mod pallet {
 	#[pallet::getter(fn challenge_proposal)]
 	pub(super) type ChallengeProposal<T: Config> = CountedStorageMap<_, Blake2_128Concat, [u8; 32], (u32, ChallengeInfo<T>)>;
 
+	//Authorities that have already voted for a challenge proposal
+	#[pallet::storage]
+	#[pallet::getter(fn proposal_voters)]
+	pub(super) type ProposalVoters<T: Config> = StorageDoubleMap<_, Blake2_128Concat, [u8; 32], Blake2_128Concat, T::AuthorityId, BlockNumberOf<T>>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn challenge_snap_shot)]
 	pub(super) type ChallengeSnapShot<T: Config> = StorageValue<_, ChallengeInfo<T>>;
 
+	//Reward pool of the current round and the total space of the miners that passed verification
+	#[pallet::storage]
+	#[pallet::getter(fn round_reward)]
+	pub(super) type RoundReward<T: Config> = StorageValue<_, RewardRound<BlockNumberOf<T>>>;
+
+	//Miners that passed verification in the current round, waiting for the reward settlement
+	#[pallet::storage]
+	#[pallet::getter(fn pending_reward)]
+	pub(super) type PendingReward<T: Config> = CountedStorageMap<_, Blake2_128Concat, AccountOf<T>, (u128, u128)>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn unverify_proof)]
 	pub(super) type UnverifyProof<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, BoundedVec<ProveInfo<T>, T::VerifyMissionMax>, ValueQuery>;
@@ -316,6 +379,17 @@ pub mod pallet {
 	#[pallet::getter(fn counted_clear)]
 	pub(super) type CountedClear<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, u8, ValueQuery>;
 
+	// The most recent challenge rounds of each miner, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn audit_history)]
+	pub(super) type AuditHistory<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AccountOf<T>,
+		BoundedVec<AuditRecord<AccountOf<T>, BlockNumberOf<T>>, T::AuditHistoryLimit>,
+		ValueQuery,
+	>;
+
 	// For TEST
 	#[pallet::storage]
 	#[pallet::getter(fn controller_button)]
@@ -342,12 +416,14 @@ pub mod pallet {
 			weight
 				.saturating_add(Self::clear_challenge(now))
 				.saturating_add(Self::clear_verify_mission(now))
+				.saturating_add(Self::settle_round_reward(now))
 		}
 
 		fn offchain_worker(now: T::BlockNumber) {
 			let deadline = Self::verify_duration();
 			if sp_io::offchain::is_validator() {
-				if now > deadline {
+				// The next round only starts after the rewards of the previous one are settled.
+				if now > deadline && !<RoundReward<T>>::exists() {
 					// For TEST
 					let permission = <ControllerButton<T>>::get();
 					if permission == now {
@@ -377,7 +453,7 @@ pub mod pallet {
 		pub fn save_challenge_info(
 			origin: OriginFor<T>,
 			challenge_info: ChallengeInfo<T>,
-			_key: T::AuthorityId,
+			key: T::AuthorityId,
 			_seg_digest: SegDigest<BlockNumberOf<T>>,
 			_signature: <T::AuthorityId as RuntimeAppPublic>::Signature,
 		) -> DispatchResult {
@@ -386,6 +462,9 @@ pub mod pallet {
 			let encode_info: Vec<u8> = challenge_info.encode();
 
 			let hash = sp_io::hashing::sha2_256(&encode_info);
+			let now = <frame_system::Pallet<T>>::block_number();
+
+			ensure!(!<ProposalVoters<T>>::contains_key(&hash, &key), Error::<T>::DuplicateProposal);
 
 			let count: u32 = Keys::<T>::get().len() as u32;
 			let limit = count
@@ -393,18 +472,28 @@ pub mod pallet {
 				.checked_div(3).ok_or(Error::<T>::Overflow)?;
 
 			if ChallengeProposal::<T>::contains_key(&hash) {
-				let proposal = ChallengeProposal::<T>::get(&hash).unwrap();
+				let proposal = ChallengeProposal::<T>::get(&hash).ok_or(Error::<T>::UnexpectedError)?;
+				<ProposalVoters<T>>::insert(&hash, &key, now);
+				ChallengeProposal::<T>::insert(&hash, (proposal.0.saturating_add(1), proposal.1.clone()));
 				if proposal.0 + 1 >= limit {
 					let cur_blcok = <ChallengeDuration<T>>::get();
-					let now = <frame_system::Pallet<T>>::block_number();
-					if now > cur_blcok {
-						let duration = now.checked_add(&proposal.1.net_snap_shot.life).ok_or(Error::<T>::Overflow)?;
-						<ChallengeSnapShot<T>>::put(proposal.1);
+					if now > cur_blcok && !<RoundReward<T>>::exists() {
+						let mut challenge_info = proposal.1;
+						// Proof windows are relative to the block in which the challenge takes effect.
+						challenge_info.net_snap_shot.start = now;
+						<RoundReward<T>>::put(RewardRound {
+							challenge_start: now,
+							total_reward: challenge_info.net_snap_shot.total_reward,
+							total_idle_space: u128::MIN,
+							total_service_space: u128::MIN,
+						});
+						let duration = now.checked_add(&challenge_info.net_snap_shot.life).ok_or(Error::<T>::Overflow)?;
+						<ChallengeSnapShot<T>>::put(challenge_info);
 						<ChallengeDuration<T>>::put(duration);
 						let one_hour = T::OneHours::get();
 						let v_duration = duration.checked_add(&one_hour).ok_or(Error::<T>::Overflow)?;
 						<VerifyDuration<T>>::put(v_duration);
-						let _ = ChallengeProposal::<T>::clear(ChallengeProposal::<T>::count(), None);
+						Self::clear_challenge_proposal();
 					}
 
 					Self::deposit_event(Event::<T>::GenerateChallenge);
@@ -412,8 +501,9 @@ pub mod pallet {
 			} else {
 				if ChallengeProposal::<T>::count() > count {
 					// Proposal Generally Less
-					let _ = ChallengeProposal::<T>::clear(ChallengeProposal::<T>::count(), None);
+					Self::clear_challenge_proposal();
 				} else {
+					<ProposalVoters<T>>::insert(&hash, &key, now);
 					ChallengeProposal::<T>::insert(
 						&hash,
 						(1, challenge_info),
@@ -432,9 +522,10 @@ pub mod pallet {
 			idle_prove: BoundedVec<u8, T::SigmaMax>,
 			service_prove: BoundedVec<u8, T::SigmaMax>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 
-			let miner_snapshot = <ChallengeSnapShot<T>>::try_mutate(|challenge_opt| -> Result<MinerSnapShot<AccountOf<T>>, DispatchError> {
+			let (miner_snapshot, challenge_start) = <ChallengeSnapShot<T>>::try_mutate(|challenge_opt| -> Result<(MinerSnapShot<AccountOf<T>, BlockNumberOf<T>>, BlockNumberOf<T>), DispatchError> {
 				let challenge_info = challenge_opt.as_mut().ok_or(Error::<T>::NoChallenge)?;
 
 				for (index, miner_snapshot) in challenge_info.miner_snapshot_list.clone().iter().enumerate() {
@@ -442,9 +533,12 @@ pub mod pallet {
 						let now = <frame_system::Pallet<T>>::block_number();
 						let duration = <ChallengeDuration<T>>::get();
 						ensure!(now < duration, Error::<T>::NoChallenge);
+						let deadline = challenge_info.net_snap_shot.start
+							.checked_add(&miner_snapshot.life).ok_or(Error::<T>::Overflow)?;
+						ensure!(now < deadline, Error::<T>::ProofExpired);
 						
 						challenge_info.miner_snapshot_list.remove(index);
-						return Ok(miner_snapshot.clone());
+						return Ok((miner_snapshot.clone(), challenge_info.net_snap_shot.start));
 					}
 				}
 
@@ -454,10 +548,10 @@ pub mod pallet {
 			let tee_list = T::Scheduler::get_controller_list();
 			ensure!(tee_list.len() > 0, Error::<T>::SystemError);
 
-			let seed: u32 = <frame_system::Pallet<T>>::block_number().saturated_into();
-			let index = Self::random_number(seed) as u32;
-			let index: u32 = index % (tee_list.len() as u32);
-			let tee_acc = &tee_list[index as usize];
+			// Bind the choice to this challenge and miner so that it cannot be predicted from the block number alone.
+			let candidates = Self::tee_candidates(&tee_list, &[]);
+			let tee_acc = &Self::select_tee(&candidates, None, (b"tee", challenge_start, &sender))
+				.ok_or(Error::<T>::NoTeeAvailable)?;
 
 			let prove_info = ProveInfo::<T> {
 				snap_shot: miner_snapshot,
@@ -473,6 +567,12 @@ pub mod pallet {
 				Ok(())
 			})?;
 
+			let now = <frame_system::Pallet<T>>::block_number();
+			Self::record_audit_history(&sender, challenge_start, |record| {
+				record.submit_block = Some(now);
+				record.tee_worker = Some(tee_acc.clone());
+			});
+
 			Self::deposit_event(Event::<T>::SubmitProof { miner: sender });
 
 			Ok(())
@@ -480,7 +580,8 @@ pub mod pallet {
 
 		#[pallet::call_index(2)]
 		#[transactional]
-		#[pallet::weight(100_000_000)]
+		// both the idle and the service proof can fail and punish the miner
+		#[pallet::weight(Weight::from_ref_time(100_000_000).saturating_add(T::MinerControl::punish_weight(miner).saturating_mul(2)))]
 		pub fn submit_verify_result(
 			origin: OriginFor<T>,
 			miner: AccountOf<T>,
@@ -498,15 +599,19 @@ pub mod pallet {
 					if miner_info.snap_shot.miner == miner {
 						let snap_shot = <ChallengeSnapShot<T>>::try_get().map_err(|_| Error::<T>::UnexpectedError)?;
 
+						let mut punishment: Vec<AuditPunishment> = Default::default();
+
+						// Rewards are settled for all successful miners together once the verification window ends.
 						if idle_result && service_result {
-							T::MinerControl::calculate_miner_reward(
-								&miner,
-								snap_shot.net_snap_shot.total_reward,
-								snap_shot.net_snap_shot.total_idle_space,
-								snap_shot.net_snap_shot.total_service_space,
-								miner_info.snap_shot.idle_space,
-								miner_info.snap_shot.service_space,
-							)?;
+							<RoundReward<T>>::try_mutate(|round_opt| -> DispatchResult {
+								let round = round_opt.as_mut().ok_or(Error::<T>::UnexpectedError)?;
+								round.total_idle_space = round.total_idle_space
+									.checked_add(miner_info.snap_shot.idle_space).ok_or(Error::<T>::Overflow)?;
+								round.total_service_space = round.total_service_space
+									.checked_add(miner_info.snap_shot.service_space).ok_or(Error::<T>::Overflow)?;
+								Ok(())
+							})?;
+							<PendingReward<T>>::insert(&miner, (miner_info.snap_shot.idle_space, miner_info.snap_shot.service_space));
 						}
 
 						if idle_result {
@@ -514,7 +619,8 @@ pub mod pallet {
 						} else {
 							let count = <CountedIdleFailed<T>>::get(&miner) + 1;
 							if count >= IDLE_FAULT_TOLERANT as u32 {
-								T::MinerControl::idle_punish(&miner, miner_info.snap_shot.idle_space, miner_info.snap_shot.service_space)?;
+								let amount = T::MinerControl::idle_punish(&miner, miner_info.snap_shot.idle_space, miner_info.snap_shot.service_space, snap_shot.net_snap_shot.start)?;
+								punishment.push(AuditPunishment::IdleFailed { amount });
 							}
 							<CountedIdleFailed<T>>::insert(&miner, count);
 						}
@@ -524,11 +630,21 @@ pub mod pallet {
 						} else {
 							let count = <CountedServiceFailed<T>>::get(&miner) + 1;
 							if count >= SERVICE_FAULT_TOLERANT as u32 {
-								T::MinerControl::service_punish(&miner, miner_info.snap_shot.idle_space, miner_info.snap_shot.service_space)?;
+								let amount = T::MinerControl::service_punish(&miner, miner_info.snap_shot.idle_space, miner_info.snap_shot.service_space, snap_shot.net_snap_shot.start)?;
+								punishment.push(AuditPunishment::ServiceFailed { amount });
 							}
 							<CountedServiceFailed<T>>::insert(&miner, count);
 						}
 
+						Self::record_audit_history(&miner, snap_shot.net_snap_shot.start, |record| {
+							record.tee_worker = Some(sender.clone());
+							record.idle_result = Some(idle_result);
+							record.service_result = Some(service_result);
+							for item in punishment {
+								let _ = record.punishment.try_push(item);
+							}
+						});
+
 						unverify_list.remove(index);
 
 						return Ok(())
@@ -586,6 +702,22 @@ pub mod pallet {
 			Ok(())
 		}
 
+		#[pallet::call_index(6)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn update_challenge_life_params(
+			origin: OriginFor<T>,
+			params: ChallengeLifeParams<BlockNumberOf<T>>,
+		) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+
+			<LifeParams<T>>::put(params.clone());
+
+			Self::deposit_event(Event::<T>::UpdateChallengeLifeParams { params });
+
+			Ok(())
+		}
+
 		
 		
 	}
@@ -598,12 +730,12 @@ pub mod pallet {
 
 		fn validate_unsigned(_source: TransactionSource, call: &Self::Call) -> TransactionValidity {
 			if let Call::save_challenge_info {
-				challenge_info: _,
+				challenge_info,
 				key,
 				seg_digest,
 				signature,			
 			} = call {
-				Self::check_unsign(key.clone(), &seg_digest, &signature)
+				Self::check_unsign(key.clone(), &challenge_info, &seg_digest, &signature)
 			} else {
 				InvalidTransaction::Call.into()
 			}
@@ -626,12 +758,20 @@ pub mod pallet {
 					let count = <CountedClear<T>>::get(&miner_snapshot.miner) + 1;
 					weight = weight.saturating_add(T::DbWeight::get().reads(1));
 
-					let _ = T::MinerControl::clear_punish(
+					weight = weight.saturating_add(T::MinerControl::punish_weight(&miner_snapshot.miner));
+					let result = T::MinerControl::clear_punish(
 						&miner_snapshot.miner, 
 						count, 
 						miner_snapshot.idle_space, 
-						miner_snapshot.service_space
+						miner_snapshot.service_space,
+						snap_shot.net_snap_shot.start,
 					);
+
+					Self::record_audit_history(&miner_snapshot.miner, snap_shot.net_snap_shot.start, |record| {
+						if let Ok(amount) = result {
+							let _ = record.punishment.try_push(AuditPunishment::NoProof { count, amount });
+						}
+					});
 					weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
 
 					if count >= 3 {
@@ -662,46 +802,43 @@ pub mod pallet {
 				// Used to calculate the new validation period.
 				let mut mission_count: u32 = 0;
 				let tee_list = T::Scheduler::get_controller_list();
-				let mut reassign_list: BTreeMap<AccountOf<T>, BoundedVec<ProveInfo<T>, T::VerifyMissionMax>> = Default::default();
-
-				for (acc, unverify_list) in UnverifyProof::<T>::iter() {
-					seed += 1;
-					weight = weight.saturating_add(T::DbWeight::get().reads(1));
-					if unverify_list.len() > 0 {
-						match T::Scheduler::punish_scheduler(acc.clone()) {
-							Ok(()) => log::info!("punish scheduler success"),
-							Err(e) => log::error!("punish scheduler failed: {:?}", e),
-						};
-						// Count the number of verification tasks that need to be performed.
-						mission_count = mission_count.saturating_add(unverify_list.len() as u32);
-
-						let index = Self::random_number(seed) as u32;
-						let mut index: u32 = index % (tee_list.len() as u32);
-						let mut tee_acc = &tee_list[index as usize];
-	
-						if &acc == tee_acc {
-							index += 1;
-							index = index % (tee_list.len() as u32);
-							tee_acc = &tee_list[index as usize];
-						}
-
-						if let Some(value) = reassign_list.get_mut(tee_acc) {
-							let result = value.try_append(&mut unverify_list.to_vec());
-
-							if result.is_err() {
-								let new_block: BlockNumberOf<T> = now.saturating_add(5u32.saturated_into());
-								<VerifyDuration<T>>::put(new_block);
-								weight = weight.saturating_add(T::DbWeight::get().writes(1));
-								return weight;
-							}
-						} else {
-							reassign_list.insert(tee_acc.clone(), unverify_list);
+				let stale: Vec<(AccountOf<T>, BoundedVec<ProveInfo<T>, T::VerifyMissionMax>)> = UnverifyProof::<T>::iter()
+					.filter(|(_, unverify_list)| unverify_list.len() > 0)
+					.collect();
+				weight = weight.saturating_add(T::DbWeight::get().reads(stale.len() as u64));
+				// The queues of stale workers are emptied below, so they count as free.
+				let stale_acc: Vec<AccountOf<T>> = stale.iter().map(|(acc, _)| acc.clone()).collect();
+				let mut candidates = Self::tee_candidates(&tee_list, &stale_acc);
+				let mut reassign_list: BTreeMap<AccountOf<T>, Vec<ProveInfo<T>>> = Default::default();
+
+				for (acc, unverify_list) in stale {
+					match T::Scheduler::punish_scheduler(acc.clone()) {
+						Ok(()) => log::info!("punish scheduler success"),
+						Err(e) => log::error!("punish scheduler failed: {:?}", e),
+					};
+					// Count the number of verification tasks that need to be performed.
+					mission_count = mission_count.saturating_add(unverify_list.len() as u32);
+
+					// Proofs no worker has room for stay queued and are retried in the next window.
+					let mut leftover: Vec<ProveInfo<T>> = Default::default();
+					for prove_info in unverify_list {
+						seed += 1;
+						match Self::select_tee(&candidates, Some(&acc), (b"reassign", now, &acc, seed)) {
+							Some(tee_acc) => {
+								if let Some((load, _)) = candidates.get_mut(&tee_acc) {
+									*load += 1;
+								}
+								reassign_list.entry(tee_acc).or_default().push(prove_info);
+							},
+							None => leftover.push(prove_info),
 						}
-						
-	
-						weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+					}
 
-						UnverifyProof::<T>::remove(acc);
+					weight = weight.saturating_add(T::DbWeight::get().writes(1));
+					if leftover.is_empty() {
+						UnverifyProof::<T>::remove(&acc);
+					} else {
+						UnverifyProof::<T>::insert(&acc, BoundedVec::truncate_from(leftover));
 					}
 				}
 
@@ -709,18 +846,24 @@ pub mod pallet {
 				if mission_count == 0 {
 					<ChallengeSnapShot<T>>::kill();
 				} else {
-					for (acc, unverify_list) in reassign_list {
-						let result = UnverifyProof::<T>::mutate(acc, |tar_unverify_list| -> DispatchResult {
-							tar_unverify_list.try_append(&mut unverify_list.to_vec()).map_err(|_| Error::<T>::Overflow)?;
-							// tar_unverify_list.try_push(mission)
+					let challenge_start = <ChallengeSnapShot<T>>::get().map(|snap_shot| snap_shot.net_snap_shot.start);
+					for (acc, mut unverify_list) in reassign_list {
+						if let Some(challenge_start) = challenge_start {
+							for prove_info in unverify_list.iter() {
+								Self::record_audit_history(&prove_info.snap_shot.miner, challenge_start, |record| {
+									record.tee_worker = Some(acc.clone());
+								});
+								weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
+							}
+						}
+
+						// Loads were tracked above, so the queue always has room.
+						let result = UnverifyProof::<T>::mutate(&acc, |tar_unverify_list| -> DispatchResult {
+							tar_unverify_list.try_append(&mut unverify_list).map_err(|_| Error::<T>::Overflow)?;
 							Ok(())
 						});
-
-						if result.is_err() {
-							let new_block: BlockNumberOf<T> = now.saturating_add(5u32.saturated_into());
-							<VerifyDuration<T>>::put(new_block);
-							weight = weight.saturating_add(T::DbWeight::get().writes(1));
-							return weight;
+						if let Err(e) = result {
+							log::error!("reassign verify mission failed: {:?}", e);
 						}
 
 						weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 1));
@@ -736,8 +879,107 @@ pub mod pallet {
 			weight
 		}
 
+		// Current queue length and credit score of each tee worker. Workers in `emptied`
+		// are about to lose their queue and start from zero.
+		pub(super) fn tee_candidates(
+			tee_list: &[AccountOf<T>],
+			emptied: &[AccountOf<T>],
+		) -> BTreeMap<AccountOf<T>, (u32, u32)> {
+			tee_list
+				.iter()
+				.map(|acc| {
+					let load = if emptied.contains(acc) {
+						0
+					} else {
+						UnverifyProof::<T>::decode_len(acc).unwrap_or(0) as u32
+					};
+					(acc.clone(), (load, T::Scheduler::get_credit_score(acc.clone())))
+				})
+				.collect()
+		}
+
+		// Picks a tee worker with room in its verify queue. The chance of each worker grows
+		// with its free queue capacity and its scheduler credit score.
+		pub(super) fn select_tee<S: Encode>(
+			candidates: &BTreeMap<AccountOf<T>, (u32, u32)>,
+			exclude: Option<&AccountOf<T>>,
+			subject: S,
+		) -> Option<AccountOf<T>> {
+			let max = T::VerifyMissionMax::get();
+			let weights: Vec<(&AccountOf<T>, u64)> = candidates
+				.iter()
+				.filter(|(acc, (load, _))| Some(*acc) != exclude && *load < max)
+				.map(|(acc, (load, credit))| {
+					let credit = credit.saturating_add(BASE_CREDIT_WEIGHT) as u64;
+					(acc, credit.saturating_mul((max - load) as u64))
+				})
+				.collect();
+			let total = weights.iter().fold(0u64, |total, (_, weight)| total.saturating_add(*weight));
+			if total == 0 {
+				return None;
+			}
+
+			let mut point = Self::random_number(subject) % total;
+			for (acc, weight) in weights {
+				if point < weight {
+					return Some(acc.clone());
+				}
+				point -= weight;
+			}
+
+			None
+		}
+
+		// Once the verification window has ended, pay the successful miners of the round
+		// in batches of `RewardSettleLimit`, splitting the pool over their total power.
+		pub(super) fn settle_round_reward(now: BlockNumberOf<T>) -> Weight {
+			let mut weight: Weight = Weight::from_ref_time(0);
+			let duration = <VerifyDuration<T>>::get();
+			weight = weight.saturating_add(T::DbWeight::get().reads(1));
+			if now <= duration {
+				return weight;
+			}
+
+			let round = match <RoundReward<T>>::get() {
+				Some(round) => round,
+				None => return weight,
+			};
+			weight = weight.saturating_add(T::DbWeight::get().reads(1));
+
+			let limit = T::RewardSettleLimit::get() as usize;
+			let batch: Vec<(AccountOf<T>, (u128, u128))> = <PendingReward<T>>::iter().take(limit).collect();
+			for (miner, (idle_space, service_space)) in batch {
+				<PendingReward<T>>::remove(&miner);
+				weight = weight.saturating_add(T::MinerControl::reward_weight(&miner));
+				let result = T::MinerControl::calculate_miner_reward(
+					&miner,
+					round.total_reward,
+					round.total_idle_space,
+					round.total_service_space,
+					idle_space,
+					service_space,
+				);
+				match result {
+					Ok(reward) => Self::record_audit_history(&miner, round.challenge_start, |record| {
+						record.reward = reward;
+					}),
+					Err(e) => log::error!("settle miner reward failed: {:?}", e),
+				};
+				weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 2));
+			}
+
+			if <PendingReward<T>>::count() == 0 {
+				<RoundReward<T>>::kill();
+				weight = weight.saturating_add(T::DbWeight::get().writes(1));
+				Self::deposit_event(Event::<T>::RoundRewardSettled { challenge_start: round.challenge_start });
+			}
+
+			weight
+		}
+
 		fn check_unsign(
 			key: T::AuthorityId,
+			challenge_info: &ChallengeInfo<T>,
 			seg_digest: &SegDigest<BlockNumberOf<T>>,
 			signature: &<T::AuthorityId as RuntimeAppPublic>::Signature,
 		) -> TransactionValidity {
@@ -748,6 +990,29 @@ pub mod pallet {
 				return InvalidTransaction::Stale.into();
 			} 
 
+			// The digest must belong to the current session and validator set.
+			if seg_digest.session_index != current_session || seg_digest.validators_len != keys.len() as u32 {
+				return InvalidTransaction::Stale.into();
+			}
+
+			// The digest is only valid for a short window after the block it was signed in.
+			let now = <frame_system::Pallet<T>>::block_number();
+			if seg_digest.block_num > now {
+				return InvalidTransaction::Future.into();
+			}
+			if seg_digest.block_num.saturating_add(T::ProposalValidity::get()) < now {
+				return InvalidTransaction::Stale.into();
+			}
+
+			let challenge_hash = sp_io::hashing::sha2_256(&challenge_info.encode());
+			if seg_digest.challenge_hash != challenge_hash {
+				return InvalidTransaction::BadProof.into();
+			}
+
+			if <ProposalVoters<T>>::contains_key(&challenge_hash, &key) {
+				return InvalidTransaction::Stale.into();
+			}
+
 			let signature_valid = seg_digest.using_encoded(|encoded_seg_digest| {
 				key.verify(&encoded_seg_digest, &signature)
 			});
@@ -760,7 +1025,7 @@ pub mod pallet {
 			log::info!("build valid transaction");
 			ValidTransaction::with_tag_prefix("Audit")
 				.priority(T::UnsignedPriority::get())
-				.and_provides((current_session, key, signature))
+				.and_provides((current_session, key, challenge_hash))
 				.longevity(
 					TryInto::<u64>::try_into(
 						T::NextSessionRotation::average_session_length() / 2u32.into(),
@@ -794,7 +1059,7 @@ pub mod pallet {
 		fn _trigger_challenge(now: BlockNumberOf<T>) -> bool {
 			const START_FINAL_PERIOD: Permill = Permill::from_percent(80);
 
-			let time_point = Self::random_number(20220509);
+			let time_point = Self::random_number((b"trigger", now));
 			//The chance to trigger a challenge is once a day
 			let probability: u32 = T::OneDay::get().saturated_into();
 			let range = LIMIT / probability as u64;
@@ -910,7 +1175,9 @@ pub mod pallet {
 			let need_miner_count = miner_count;
 			// let need_miner_count = miner_count / 10 + 1;
 
-			let mut miner_list: BoundedVec<MinerSnapShot<AccountOf<T>>, T::ChallengeMinerMax> = Default::default();
+			let mut miner_list: BoundedVec<MinerSnapShot<AccountOf<T>, BlockNumberOf<T>>, T::ChallengeMinerMax> = Default::default();
+			let life_params = <LifeParams<T>>::get();
+			let mut max_life: BlockNumberOf<T> = life_params.base;
 
 			let mut valid_index_list: Vec<u32> = Default::default();
 
@@ -920,15 +1187,13 @@ pub mod pallet {
 			let mut seed: u32 = 20230601;
 			while (miner_list.len() as u32 != need_miner_count) && (valid_index_list.len() as u32 != miner_count) {
 				seed = seed.saturating_add(1); 
-				let index_list = Self::random_select_miner(need_miner_count, miner_count, &valid_index_list, seed);
-
-				let allminer = T::MinerControl::get_all_miner().map_err(|_| OffchainErr::GenerateInfoError)?;
+				let index_list = Self::random_select_miner(now, need_miner_count, miner_count, &valid_index_list, seed);
 
 				for index in index_list {
 					valid_index_list.push(index);
-					let miner = allminer[index as usize].clone();
+					let miner = T::MinerControl::get_miner_by_index(index).ok_or(OffchainErr::GenerateInfoError)?;
 					let state = T::MinerControl::get_miner_state(&miner).map_err(|_| OffchainErr::GenerateInfoError)?;
-					if state == "lock".as_bytes().to_vec() {
+					if state == MinerState::Lock {
 						continue;
 					}
 	
@@ -940,10 +1205,15 @@ pub mod pallet {
 	
 					total_idle_space = total_idle_space.checked_add(idle_space).ok_or(OffchainErr::Overflow)?;
 					total_service_space = total_service_space.checked_add(service_space).ok_or(OffchainErr::Overflow)?;
-					let miner_snapshot = MinerSnapShot::<AccountOf<T>> {
+					let life = Self::calculate_miner_life(&life_params, idle_space, service_space)?;
+					if life > max_life {
+						max_life = life;
+					}
+					let miner_snapshot = MinerSnapShot::<AccountOf<T>, BlockNumberOf<T>> {
 						miner,
 						idle_space,
 						service_space,
+						life,
 					};
 					let result = miner_list.try_push(miner_snapshot);
 					if let Err(_e) = result {
@@ -957,17 +1227,17 @@ pub mod pallet {
 			let mut seed: u32 = u32::MIN;
 			while random_index_list.len() < need_count as usize {
 				seed = seed + 1;
-				let random_index = (Self::random_number(seed) % CHUNK_COUNT as u64) as u32;
+				let random_index = Self::random_index((b"chunk", now, seed), CHUNK_COUNT);
 				if !random_index_list.contains(&random_index) {
 					random_index_list.push(random_index);
 				}
 			}
 
 			let mut random_list: Vec<[u8; 20]> = Default::default();
-			let mut seed: u32 = now.saturated_into();
+			let mut seed: u32 = u32::MIN;
 			while random_list.len() < need_count as usize {
 				seed = seed + 1;
-				let random_number = Self::generate_challenge_random(seed);
+				let random_number = Self::generate_challenge_random(now, seed);
 				if !random_list.contains(&random_number) {
 					random_list.push(random_number);
 				}
@@ -976,7 +1246,7 @@ pub mod pallet {
 			let total_reward: u128 = T::MinerControl::get_reward();
 			let snap_shot = NetSnapShot::<BlockNumberOf<T>>{
 				start: now,
-				life: now,
+				life: max_life,
 				total_reward,
 				total_idle_space,
 				total_service_space,
@@ -987,14 +1257,34 @@ pub mod pallet {
 			Ok( ChallengeInfo::<T>{ net_snap_shot: snap_shot, miner_snapshot_list: miner_list } )
 		}
 
+		// The proof window grows with every started TiB of challenged idle and service space.
+		pub(super) fn calculate_miner_life(
+			params: &ChallengeLifeParams<BlockNumberOf<T>>,
+			idle_space: u128,
+			service_space: u128,
+		) -> Result<BlockNumberOf<T>, OffchainErr> {
+			let idle_tib: u32 = idle_space.saturating_add(T_BYTE - 1)
+				.checked_div(T_BYTE).ok_or(OffchainErr::Overflow)?
+				.saturated_into();
+			let service_tib: u32 = service_space.saturating_add(T_BYTE - 1)
+				.checked_div(T_BYTE).ok_or(OffchainErr::Overflow)?
+				.saturated_into();
+
+			let idle_life = params.idle_per_tib.checked_mul(&idle_tib.saturated_into()).ok_or(OffchainErr::Overflow)?;
+			let service_life = params.service_per_tib.checked_mul(&service_tib.saturated_into()).ok_or(OffchainErr::Overflow)?;
+
+			params.base
+				.checked_add(&idle_life).ok_or(OffchainErr::Overflow)?
+				.checked_add(&service_life).ok_or(OffchainErr::Overflow)
+		}
+
 		// Ensure that the length is not 0
-		fn random_select_miner(need: u32, length: u32, valid_index_list: &Vec<u32>, seed: u32) -> Vec<u32> {
+		fn random_select_miner(now: BlockNumberOf<T>, need: u32, length: u32, valid_index_list: &Vec<u32>, seed: u32) -> Vec<u32> {
 			let mut miner_index_list: Vec<u32> = Default::default();
 			let mut seed: u32 = seed.saturating_mul(1000);
 			while (miner_index_list.len() as u32) < need && ((valid_index_list.len() + miner_index_list.len()) as u32 != length) {
 				seed += 1;
-				let index = Self::random_number(seed);
-				let index: u32 = (index % length as u64) as u32;
+				let index = Self::random_index((b"miner", now, seed), length);
 
 				if valid_index_list.contains(&index) {
 					continue;
@@ -1014,7 +1304,7 @@ pub mod pallet {
 			challenge_info: ChallengeInfo<T>,
 		) -> Result<(), OffchainErr> {
 
-			let (signature, digest) = Self::offchain_sign_digest(now, &authority_id)?;
+			let (signature, digest) = Self::offchain_sign_digest(now, &authority_id, &challenge_info)?;
 
 			let call = Call::save_challenge_info {
 							challenge_info,
@@ -1036,6 +1326,7 @@ pub mod pallet {
 		fn offchain_sign_digest(
 			now: BlockNumberOf<T>,
 			authority_id: &T::AuthorityId,
+			challenge_info: &ChallengeInfo<T>,
 		) -> Result< (<<T as pallet::Config>::AuthorityId as sp_runtime::RuntimeAppPublic>::Signature, SegDigest::<BlockNumberOf<T>>), OffchainErr> {
 
 			let network_state =
@@ -1046,6 +1337,8 @@ pub mod pallet {
 			let digest = SegDigest::<BlockNumberOf<T>>{
 				validators_len: author_len as u32,
 				block_num: now,
+				session_index: T::ValidatorSet::session_index(),
+				challenge_hash: sp_io::hashing::sha2_256(&challenge_info.encode()),
 				network_state,
 			};
 
@@ -1054,6 +1347,44 @@ pub mod pallet {
 			Ok((signature, digest))
 		}
 
+		// Remove all pending challenge proposals together with their votes.
+		fn clear_challenge_proposal() {
+			// Each authority votes at most once per proposal, and `Keys` holds at most `StringLimit` authorities.
+			let max_voters = T::StringLimit::get();
+			for hash in ChallengeProposal::<T>::iter_keys() {
+				let _ = <ProposalVoters<T>>::clear_prefix(&hash, max_voters, None);
+			}
+			let _ = ChallengeProposal::<T>::clear(ChallengeProposal::<T>::count(), None);
+		}
+
+		// Update the record of the given challenge round in the miner's audit history.
+		// If the round has no record yet, a new one is appended and the oldest is dropped when full.
+		pub(super) fn record_audit_history(
+			miner: &AccountOf<T>,
+			challenge_start: BlockNumberOf<T>,
+			f: impl FnOnce(&mut AuditRecord<AccountOf<T>, BlockNumberOf<T>>),
+		) {
+			<AuditHistory<T>>::mutate(miner, |history| {
+				if let Some(record) = history.iter_mut().rev().find(|record| record.challenge_start == challenge_start) {
+					f(record);
+					return;
+				}
+
+				let mut record = AuditRecord::new(challenge_start);
+				f(&mut record);
+
+				if history.len() as u32 >= T::AuditHistoryLimit::get() && history.len() > 0 {
+					history.remove(0);
+				}
+				let _ = history.try_push(record);
+			});
+		}
+
+		// The recent challenge rounds of a miner, oldest first. Backs the `AuditApi` runtime API.
+		pub fn miner_audit_history(miner: AccountOf<T>) -> Vec<AuditRecord<AccountOf<T>, BlockNumberOf<T>>> {
+			<AuditHistory<T>>::get(miner).into_inner()
+		}
+
 		pub fn initialize_keys(keys: &[T::AuthorityId]) {
 			if !keys.is_empty() {
 				assert!(Keys::<T>::get().is_empty(), "Keys are already initialized!");
@@ -1063,37 +1394,77 @@ pub mod pallet {
 			}
 		}
 
-		// Generate a random number from a given seed.
-		pub fn random_number(seed: u32) -> u64 {
-			let (random_seed, _) = T::MyRandomness::random(&(T::MyPalletId::get(), seed).encode());
-			let random_seed = match random_seed {
-				Some(v) => v,
-				None => Default::default(),
-			};
-			let random_number = <u64>::decode(&mut random_seed.as_ref())
-				.expect("secure hashes should always be bigger than u32; qed");
-			random_number
+		// Generate a random number bound to the given subject from the rrsc VRF randomness.
+		pub fn random_number<S: Encode>(subject: S) -> u64 {
+			T::MyRandomness::random_u64(&(T::MyPalletId::get(), subject).encode())
+		}
+
+		// Generate a random index in `0..len` bound to the given subject.
+		pub fn random_index<S: Encode>(subject: S, len: u32) -> u32 {
+			T::MyRandomness::random_index(&(T::MyPalletId::get(), subject).encode(), len)
 		}
 
 		//The number of pieces generated is vec
-		fn generate_challenge_random(seed: u32) -> [u8; 20] {
-			let mut increase = seed;
-			loop {
-				increase += 1;
-				let (r_seed, _) =
-					T::MyRandomness::random(&(T::MyPalletId::get(), increase).encode());
-				let r_seed = match r_seed {
-					Some(v) => v,
-					None => Default::default(),
-				};
-				let random_seed = <H256>::decode(&mut r_seed.as_ref())
-					.expect("secure hashes should always be bigger than u32; qed");
-				let random_vec = random_seed.as_bytes().to_vec();
-				if random_vec.len() >= 20 {
-					return random_vec[0..20].try_into().unwrap();
-				}
+		fn generate_challenge_random(now: BlockNumberOf<T>, seed: u32) -> [u8; 20] {
+			let random = T::MyRandomness::random_bytes(&(T::MyPalletId::get(), b"challenge", now, seed).encode());
+			let mut result = [0u8; 20];
+			result.copy_from_slice(&random[..20]);
+			result
+		}
+	}
+}
+
+impl<T: Config> VerifyMissionHandoff<AccountOf<T>> for Pallet<T> {
+	fn pending_missions(acc: &AccountOf<T>) -> u32 {
+		<UnverifyProof<T>>::decode_len(acc).unwrap_or(0) as u32
+	}
+
+	// Spreads the proofs over the other active workers by load and credit, skipping full queues.
+	// Everything is assigned in memory first, so a failure leaves storage untouched.
+	fn hand_off_missions(acc: &AccountOf<T>) -> DispatchResult {
+		let missions = <UnverifyProof<T>>::get(acc);
+		if missions.is_empty() {
+			return Ok(());
+		}
+
+		let tee_list: Vec<AccountOf<T>> = T::Scheduler::get_controller_list()
+			.into_iter()
+			.filter(|tee_acc| tee_acc != acc)
+			.collect();
+		ensure!(tee_list.len() > 0, Error::<T>::NoTeeForHandoff);
+
+		let now = <frame_system::Pallet<T>>::block_number();
+		let mut candidates = Self::tee_candidates(&tee_list, &[]);
+		let mut targets: BTreeMap<AccountOf<T>, BoundedVec<ProveInfo<T>, T::VerifyMissionMax>> = Default::default();
+		let mut assignment: Vec<(AccountOf<T>, AccountOf<T>)> = Default::default();
+
+		for (seed, prove_info) in missions.iter().enumerate() {
+			let tee_acc = Self::select_tee(&candidates, None, (b"handoff", now, acc, seed as u32))
+				.ok_or(Error::<T>::NoTeeAvailable)?;
+			if let Some((load, _)) = candidates.get_mut(&tee_acc) {
+				*load += 1;
 			}
+			let queue = targets.entry(tee_acc.clone()).or_insert_with(|| <UnverifyProof<T>>::get(&tee_acc));
+			queue.try_push(prove_info.clone()).map_err(|_| Error::<T>::Overflow)?;
+			assignment.push((prove_info.snap_shot.miner.clone(), tee_acc));
 		}
+
+		for (tee_acc, queue) in targets {
+			<UnverifyProof<T>>::insert(&tee_acc, queue);
+		}
+		<UnverifyProof<T>>::remove(acc);
+
+		if let Some(snap_shot) = <ChallengeSnapShot<T>>::get() {
+			for (miner, tee_acc) in assignment {
+				Self::record_audit_history(&miner, snap_shot.net_snap_shot.start, |record| {
+					record.tee_worker = Some(tee_acc);
+				});
+			}
+		}
+
+		Self::deposit_event(Event::<T>::MissionsHandedOff { tee_worker: acc.clone(), count: missions.len() as u32 });
+
+		Ok(())
 	}
 }
 
@@ -1,75 +1,105 @@
-use crate::*;
-use codec::{Decode, Encode};
-use frame_support::{
-	codec, generate_storage_alias,
-	traits::{Get},
-};
-use frame_support::traits::OnRuntimeUpgrade;
+//! Storage migrations for the Audit pallet.
 
-pub struct MigrationSegmentBook<T: crate::Config>(sp_std::marker::PhantomData<T>);
-impl<T: crate::Config> OnRuntimeUpgrade for MigrationSegmentBook<T> {
-	fn on_runtime_upgrade() -> Weight {
-        log::info!("Audit migrate start!");
-		migrate::<T>()
-	}
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
 
-	#[cfg(feature = "try-runtime")]
-	fn pre_upgrade() -> Result<(), &'static str> {
-		log::info!("audit check access");
-		return Ok(())
-	}
+pub mod v2 {
+	use super::*;
+	use sp_runtime::traits::Zero;
 
-	#[cfg(feature = "try-runtime")]
-	fn post_upgrade() -> Result<(), &'static str> {
-		let weights = migrate::<T>();
-		return Ok(())
+	#[derive(Decode, Encode)]
+	struct OldMinerSnapShot<AccountId> {
+		miner: AccountId,
+		idle_space: u128,
+		service_space: u128,
 	}
-}
 
-pub fn migrate<T: Config>() -> Weight {
-	let version = StorageVersion::get::<Pallet<T>>();
-	let mut weight: Weight = 0;
+	#[derive(Decode, Encode)]
+	struct OldChallengeInfo<AccountId, Block> {
+		net_snap_shot: NetSnapShot<Block>,
+		miner_snapshot_list: Vec<OldMinerSnapShot<AccountId>>,
+	}
 
-	if version < 1 {
-        log::info!("Audit version 1 -> 2 migrations start!");
-        weight = weight.saturating_add(v2::migrate::<T>());
-        StorageVersion::new(2).put::<Pallet<T>>();
+	#[derive(Decode, Encode)]
+	struct OldProveInfo<AccountId, Proof> {
+		snap_shot: OldMinerSnapShot<AccountId>,
+		idle_prove: Proof,
+		service_prove: Proof,
 	}
 
-	weight
-}
+	fn snap_shot_from<AccountId, Block>(old: OldMinerSnapShot<AccountId>, life: Block) -> MinerSnapShot<AccountId, Block> {
+		MinerSnapShot { miner: old.miner, idle_space: old.idle_space, service_space: old.service_space, life }
+	}
 
-mod v2 {
-    use super::*;
+	/// Gives every challenged miner its own proof window.
+	///
+	/// Before v2 all miners shared the window of the network snapshot, which held the absolute
+	/// block the challenge ended at. The remaining window, relative to the challenge start, is
+	/// given to the miners of the running challenge and to the proofs waiting for verification.
+	/// The running challenge also gets a reward round, so its proofs can still be verified.
+	/// Pending challenge proposals are dropped, the validators propose them again.
+	pub struct MigrateToV2<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				log::warn!("Skipping audit v2 migration, should be removed");
+				return T::DbWeight::get().reads(1);
+			}
 
-    #[derive(Decode, Encode)]
-    struct OldProveInfo<T: Config> {
-	    file_id: Hash,
-	    miner_acc: AccountOf<T>,
-	    challenge_info: ChallengeInfo<T>,
-	    mu: BoundedList<T>,
-	    sigma: BoundedVec<u8, T::StringLimit>,
-	    name: BoundedVec<u8, T::StringLimit>,
-	    u: BoundedList<T>,
-    }
+			let mut life: BlockNumberOf<T> = Zero::zero();
+			let duration = <ChallengeDuration<T>>::get();
+			let _ = <ChallengeSnapShot<T>>::translate::<OldChallengeInfo<AccountOf<T>, BlockNumberOf<T>>, _>(|old| {
+				let mut old = old?;
+				life = duration.saturating_sub(old.net_snap_shot.start);
+				old.net_snap_shot.life = life;
+				if !<RoundReward<T>>::exists() {
+					<RoundReward<T>>::put(RewardRound {
+						challenge_start: old.net_snap_shot.start,
+						total_reward: old.net_snap_shot.total_reward,
+						total_idle_space: 0,
+						total_service_space: 0,
+					});
+				}
+				let miner_snapshot_list: Vec<MinerSnapShot<AccountOf<T>, BlockNumberOf<T>>> = old
+					.miner_snapshot_list
+					.into_iter()
+					.map(|snap_shot| snap_shot_from(snap_shot, life))
+					.collect();
+				Some(ChallengeInfo::<T> {
+					net_snap_shot: old.net_snap_shot,
+					miner_snapshot_list: BoundedVec::truncate_from(miner_snapshot_list),
+				})
+			});
 
-    generate_storage_alias!(
-		Audit,
-		UnVerifyProof<T: Config> => Map<
-            (Blake2_128Concat, T::AccountId),
-            BoundedVec<OldProveInfo<T>, T::ChallengeMaximum>
-        >
-	);
+			let mut translated: u64 = 0;
+			<UnverifyProof<T>>::translate::<Vec<OldProveInfo<AccountOf<T>, BoundedVec<u8, T::SigmaMax>>>, _>(
+				|_acc, old| {
+					translated += 1;
+					let proofs: Vec<ProveInfo<T>> = old
+						.into_iter()
+						.map(|prove| ProveInfo::<T> {
+							snap_shot: snap_shot_from(prove.snap_shot, life),
+							idle_prove: prove.idle_prove,
+							service_prove: prove.service_prove,
+						})
+						.collect();
+					Some(BoundedVec::truncate_from(proofs))
+				},
+			);
 
-    pub fn migrate<T: Config>() -> Weight {
-        let mut weight: Weight = 0;
+			let proposals = ChallengeProposal::<T>::count();
+			let _ = ChallengeProposal::<T>::clear(proposals, None);
+			StorageVersion::new(2).put::<Pallet<T>>();
 
-        for (acc, prove_list) in <UnVerifyProof<T>>::iter() {
-            log::info!("prove_list len is: {:?}", prove_list.len());
-            <UnVerifyProof<T>>::remove(&acc);
-            weight = weight.saturating_add(T::DbWeight::get().writes(1 as Weight));
-        }
+			log::info!("audit v2 applied, {} verify queues migrated, {} proposals dropped", translated, proposals);
+			let proposals = proposals as u64;
+			T::DbWeight::get().reads_writes(translated + proposals + 5, translated + proposals + 4)
+		}
 
-        weight
-    }
-}
\ No newline at end of file
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 2, "audit v2 not applied");
+			Ok(())
+		}
+	}
+}
@@ -53,14 +53,21 @@ frame_support::construct_runtime!(
 		Timestamp: pallet_timestamp,
 		SchedulerCredit: pallet_scheduler_credit,
 		Oss: pallet_oss,
-        PreImage: pallet_preimage,
+		StorageHandler: pallet_storage_handler,
+		PreImage: pallet_preimage,
 	}
 );
 
+parameter_types! {
+	pub const P2PLength: u32 = 200;
+}
+
 impl pallet_oss::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 
 	type WeightInfo = ();
+
+	type P2PLength = P2PLength;
 }
 
 parameter_types! {
@@ -104,6 +111,16 @@ parameter_types! {
 		pub const SchedulerMaximum: u32 = 10000;
 		#[derive(Clone, PartialEq, Eq)]
 		pub const ParamsLimit: u32 = 359;
+		#[derive(Clone, PartialEq, Eq)]
+		pub const MaxWhitelist: u32 = 4;
+		pub const MaxReportAge: u64 = 3600;
+		#[derive(Clone, PartialEq, Eq)]
+		pub const MaxCrls: u32 = 4;
+		pub const AttestationValidity: u64 = 100;
+		pub const AttestationGracePeriod: u64 = 20;
+		pub const Podr2KeyThreshold: Perbill = Perbill::from_percent(67);
+		pub const Podr2KeyActivationDelay: u64 = 10;
+		pub const ExitTimeout: u64 = 30;
 }
 
 impl pallet_tee_worker::Config for Test {
@@ -115,6 +132,15 @@ impl pallet_tee_worker::Config for Test {
 		type CreditCounter = SchedulerCredit;
 		type SchedulerMaximum = SchedulerMaximum;
 		type ParamsLimit = ParamsLimit;
+		type MaxWhitelist = MaxWhitelist;
+		type MaxReportAge = MaxReportAge;
+		type MaxCrls = MaxCrls;
+		type AttestationValidity = AttestationValidity;
+		type AttestationGracePeriod = AttestationGracePeriod;
+		type Podr2KeyThreshold = Podr2KeyThreshold;
+		type Podr2KeyActivationDelay = Podr2KeyActivationDelay;
+		type VerifyMission = Audit;
+		type ExitTimeout = ExitTimeout;
 }
 
 parameter_types! {
@@ -128,24 +154,36 @@ parameter_types! {
 	#[derive(Clone, Eq, PartialEq)]
 	pub const BucketLimit: u32 = 1000;
 	#[derive(Clone, Eq, PartialEq)]
-	pub const NameStrLimit: u32 = 40;
+	pub const NameStrLimit: u32 = 63;
 	#[derive(Clone, Eq, PartialEq)]
-	pub const MinLength: u32 = 3;
+	pub const NameMinLength: u32 = 3;
 	#[derive(Clone, Eq, PartialEq)]
 	pub const FileListLimit: u32 = 500000;
 	#[derive(Clone, Eq, PartialEq)]
-	pub const FrozenDays: BlockNumber = 60 * 10 * 24 * 7;
+	pub const SegmentCount: u32 = 100;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const FragmentCount: u32 = 3;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const OwnerLimit: u32 = 50000;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const RestoralOrderLife: u32 = 250;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const MissionCount: u32 = 300;
 }
 
 impl pallet_file_bank::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
-	type Currency = Balances;
 	type WeightInfo = ();
 	type RuntimeCall = RuntimeCall;
+	type FScheduler = Scheduler;
+	type AScheduler = Scheduler;
+	type SPalletsOrigin = OriginCaller;
+	type SProposal = RuntimeCall;
 	type FindAuthor = ();
 	type CreditCounter = SchedulerCredit;
 	type Scheduler = pallet_tee_worker::Pallet::<Test>;
 	type MinerControl = pallet_sminer::Pallet::<Test>;
+	type StorageHandle = StorageHandler;
 	type MyRandomness = TestRandomness<Self>;
 	type FilbakPalletId = FilbakPalletId;
 	type StringLimit = StringLimit;
@@ -154,11 +192,34 @@ impl pallet_file_bank::Config for Test {
 	type NameStrLimit = NameStrLimit;
 	type BucketLimit = BucketLimit;
 	type OssFindAuthor = Oss;
-	type FrozenDays = FrozenDays;
 	type RecoverLimit = RecoverLimit;
 	type InvalidLimit = InvalidLimit;
 	type UploadFillerLimit = UploadFillerLimit;
-	type MinLength = MinLength;
+	type NameMinLength = NameMinLength;
+	type SegmentCount = SegmentCount;
+	type FragmentCount = FragmentCount;
+	type OwnerLimit = OwnerLimit;
+	type RestoralOrderLife = RestoralOrderLife;
+	type MissionCount = MissionCount;
+}
+
+parameter_types! {
+	pub const TreasuryPalletId: PalletId = PalletId(*b"py/trsry");
+	#[derive(Clone, Eq, PartialEq)]
+	pub const StateStringMax: u32 = 20;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const FrozenDays: BlockNumber = 60 * 10 * 24 * 7;
+}
+
+impl pallet_storage_handler::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type WeightInfo = ();
+	type OneDay = OneDay;
+	type FilbakPalletId = FilbakPalletId;
+	type TreasuryPalletId = TreasuryPalletId;
+	type StateStringMax = StateStringMax;
+	type FrozenDays = FrozenDays;
 }
 
 pub struct OtherSessionHandler;
@@ -272,6 +333,7 @@ impl pallet_cess_staking::Config for Test {
     const FIRST_YEAR_VALIDATOR_REWARDS: BalanceOf<Test> = 618_000_000;
     const FIRST_YEAR_SMINER_REWARDS: BalanceOf<Test> = 309_000_000;
     const REWARD_DECREASE_RATIO: Perbill = Perbill::from_perthousand(794);
+    const REWARD_DECREASE_YEARS: u64 = 30;
     type SminerRewardPool = ();
     type Currency = Balances;
     type CurrencyBalance = <Self as pallet_balances::Config>::Balance;
@@ -360,8 +422,19 @@ parameter_types! {
     pub const MultipleFines: u8 = 7;
     pub const DepositBufferPeriod: u32 = 3;
     pub const ItemLimit: u32 = 1024;
+		pub const ChallengeMinerMax: u32 = 8000;
 		pub const MaxAward: u128 = 1_306_849_000_000_000_000;
 		pub const LockInPeriod: u8 = 2;
+		pub const PayoutBatchSize: u32 = 10;
+		pub const RewardImmediateShare: Perbill = Perbill::from_percent(20);
+		pub const RewardReleaseNumber: u32 = 2;
+		pub const RewardReleaseInterval: u64 = 10;
+		pub const MaxRewardOrders: u32 = 4;
+		pub const MinerUnbondingPeriod: u64 = 10;
+		pub const MinerMaxUnlockingChunks: u32 = 4;
+		pub const MaxDelegators: u32 = 4;
+		pub const MaxCommission: Perbill = Perbill::from_percent(50);
+		pub const MaxMultiaddrs: u32 = 4;
 }
 
 impl pallet_sminer::Config for Test {
@@ -379,23 +452,41 @@ impl pallet_sminer::Config for Test {
       type DepositBufferPeriod = DepositBufferPeriod;
       type OneDayBlock = OneDay;
 			type MaxAward = MaxAward;
+			type ChallengeMinerMax = ChallengeMinerMax;
 			type LockInPeriod = LockInPeriod;
+			type PayoutBatchSize = PayoutBatchSize;
+			type RewardImmediateShare = RewardImmediateShare;
+			type RewardReleaseNumber = RewardReleaseNumber;
+			type RewardReleaseInterval = RewardReleaseInterval;
+			type MaxRewardOrders = MaxRewardOrders;
+			type UnbondingPeriod = MinerUnbondingPeriod;
+			type MaxUnlockingChunks = MinerMaxUnlockingChunks;
+			type MaxDelegators = MaxDelegators;
+			type MaxCommission = MaxCommission;
+			type MaxMultiaddrs = MaxMultiaddrs;
 }
 
 parameter_types! {
 	pub const SegmentBookPalletId: PalletId = PalletId(*b"SegmentB");
 	#[derive(Clone, PartialEq, Eq)]
 	pub const StringLimit: u32 = 100;
-	pub const OneHours: u32 = 60 * 20;
-	pub const OneDay: u32 = 60 * 20 * 24;
-	pub const LockTime: u32 = 1;
+	pub const OneHours: BlockNumber = 60 * 20;
+	pub const OneDay: BlockNumber = 60 * 20 * 24;
+	pub const LockTime: BlockNumber = 1;
+	pub const ProposalValidity: BlockNumber = 20;
 	pub const SegUnsignedPriority: TransactionPriority = TransactionPriority::max_value();
 	#[derive(Clone, PartialEq, Eq)]
-	pub const SubmitProofLimit: u32 = 100;
+	pub const SigmaMax: u32 = 2048;
 	#[derive(Clone, PartialEq, Eq)]
 	pub const SubmitValidationLimit: u32 = 50;
 	#[derive(Clone, PartialEq, Eq)]
-	pub const ChallengeMaximum: u32 = 8000;
+	pub const AuditHistoryLimit: u32 = 5;
+	pub const RewardSettleLimit: u32 = 2;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const VerifyMissionMax: u32 = 10;
+	pub const ChallengeBaseLife: u64 = 10;
+	pub const IdleLifePerTiB: u64 = 2;
+	pub const ServiceLifePerTiB: u64 = 4;
 }
 
 impl Config for Test {
@@ -405,21 +496,28 @@ impl Config for Test {
     type WeightInfo = ();
     type MyRandomness = TestRandomness<Self>;
     type StringLimit = StringLimit;
-    type RandomLimit = StringLimit;
+    type ChallengeMinerMax = ChallengeMinerMax;
+    type SigmaMax = SigmaMax;
     type OneDay = OneDay;
     type OneHours = OneHours;
     type FindAuthor = ();
     type File = pallet_file_bank::Pallet::<Test>;
     type Scheduler = pallet_tee_worker::Pallet::<Test>;
     type MinerControl = Sminer;
+    type StorageHandle = StorageHandler;
     type AuthorityId = audit::sr25519::AuthorityId;
 		type ValidatorSet = Historical;
 		type NextSessionRotation = ();
 		type UnsignedPriority = SegUnsignedPriority;
 		type LockTime = LockTime;
+		type ProposalValidity = ProposalValidity;
 		type SubmitValidationLimit = SubmitValidationLimit;
-		type SubmitProofLimit = SubmitProofLimit;
-		type ChallengeMaximum = ChallengeMaximum;
+		type AuditHistoryLimit = AuditHistoryLimit;
+		type RewardSettleLimit = RewardSettleLimit;
+		type VerifyMissionMax = VerifyMissionMax;
+		type ChallengeBaseLife = ChallengeBaseLife;
+		type IdleLifePerTiB = IdleLifePerTiB;
+		type ServiceLifePerTiB = ServiceLifePerTiB;
 }
 
 pub fn account1() -> AccountId {
@@ -455,7 +553,7 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     }
         .assimilate_storage(&mut t)
         .unwrap();
-		pallet_file_bank::GenesisConfig::<Test> {
+		pallet_storage_handler::GenesisConfig::<Test> {
 			price: 30
 		}
 			.assimilate_storage(&mut t)
@@ -1,321 +1,373 @@
 //! Tests for the module.
 
 use super::*;
-use frame_support::{assert_ok, assert_noop, bounded_vec};
+use frame_support::{assert_ok, assert_noop};
 use frame_benchmarking::account;
-use crate::{mock::*, Event, Error, mock::System as Sys};
-use pallet_file_bank::{SliceInfo, FillerInfo, UserBrief};
-use cp_cess_common::{Hash, IpAddress, DataType};
-
-// fn to_bounded_vec<T>(v: Vec<T>) -> BoundedVec<T, StringLimit> {
-//     let bv: BoundedVec<T, StringLimit> = v.try_into().unwrap();
-//     bv
-// }
-
-// fn to_bounded_vec2<T: Clone>(vv: Vec<Vec<T>>) -> BoundedVec<BoundedVec<T, StringLimit>, StringLimit> {
-//     let mut r: BoundedVec<BoundedVec<T, StringLimit>, StringLimit> = Vec::new().try_into().unwrap();
-//     // let mut t = &r;
-//     // vv.iter().map(|v| {
-//     //     t.push(to_bounded_vec::<T>(v.to_vec()))
-//     // });
-//     for v in vv {
-//         r.try_push(v.try_into().unwrap()).unwrap();
-//     }
-//     r
-// }
-
-#[derive(Debug, Clone)]
-pub struct MockingFileInfo {
-    file_hash: Hash,
-    file_size: u64,
-		slice_info: Vec<SliceInfo<Test>>,
+use crate::{mock::*, Error};
+use sp_core::Pair;
+
+fn challenge_info(start: u64) -> ChallengeInfo<Test> {
+    ChallengeInfo::<Test> {
+        net_snap_shot: NetSnapShot {
+            start,
+            life: 10,
+            total_reward: 0,
+            total_idle_space: 0,
+            total_service_space: 0,
+            random_index_list: Default::default(),
+            random_list: Default::default(),
+        },
+        miner_snapshot_list: Default::default(),
+    }
 }
 
-impl Default for MockingFileInfo {
-	fn default() -> Self {
-		let file_hash = Hash([5u8; 64]);
-		let mut file_hash1: Vec<u8> = file_hash.0.to_vec();
-		file_hash1.append("-001".as_bytes().to_vec().as_mut());
-		let file_hash1: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-
-		let mut file_hash2: Vec<u8> = file_hash.0.to_vec();
-		file_hash2.append("-002".as_bytes().to_vec().as_mut());
-		let file_hash2: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-
-		let mut file_hash3: Vec<u8> = file_hash.0.to_vec();
-		file_hash3.append("-002".as_bytes().to_vec().as_mut());
-		let file_hash3: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-		MockingFileInfo {
-			file_hash: file_hash,
-			file_size: 12,
-			slice_info: vec![SliceInfo::<Test>{
-				miner_id: 1,
-				shard_size: 111,
-				block_num: 8,
-				shard_id: file_hash1,
-				miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-				miner_acc: mock::miner1(),
-			},
-			SliceInfo::<Test>{
-				miner_id: 1,
-				shard_size: 111,
-				block_num: 8,
-				shard_id: file_hash2,
-				miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-				miner_acc: mock::miner1(),
-			},
-			SliceInfo::<Test>{
-				miner_id: 1,
-				shard_size: 111,
-				block_num: 8,
-				shard_id: file_hash3,
-				miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-				miner_acc: mock::miner1(),
-			},
-			]
-		}
-	}
+fn authority(seed: u8) -> crate::sr25519::AuthorityPair {
+    crate::sr25519::AuthorityPair::from_seed(&[seed; 32])
 }
 
-fn add_power_for_miner(controller: AccountId, miner: AccountId) -> DispatchResult {
-	let max: u8 = 10;
-	let mut filler_list: Vec<FillerInfo<Test>> = Vec::new();
-	for i in 0 .. max {
-		let filler_hash: Hash = Hash([i; 64]);
-		filler_list.push(
-			FillerInfo::<Test> {
-				index: 1,
-				filler_size: 8 * 1_048_576,
-				block_num: 8,
-				segment_size: 1_048_576,
-				scan_size: 1_048_576,
-				miner_address: miner.clone(),
-				filler_hash: filler_hash.clone(),
-			}
-		)
-	}
-	FileBank::upload_filler(RuntimeOrigin::signed(controller), miner, filler_list)?;
-
-	Ok(())
+// Makes the given authorities the validator set of the current session.
+fn set_keys(pairs: &[&crate::sr25519::AuthorityPair]) {
+    let keys: Vec<crate::sr25519::AuthorityId> = pairs.iter().map(|pair| pair.public()).collect();
+    Keys::<Test>::put(WeakBoundedVec::force_from(keys, None));
 }
 
-fn upload_declaration_alias(account: AccountId, file_name: Vec<u8>, file_hash: Hash, bucket_name: Vec<u8>) -> DispatchResult {
-	let user_brief = UserBrief::<Test>{
-		user: account.clone(),
-		file_name: file_name.try_into().unwrap(),
-		bucket_name: bucket_name.try_into().unwrap(),
-	};
-	FileBank::upload_declaration(
-		RuntimeOrigin::signed(account.clone()),
-		file_hash,
-		user_brief,
-	)
+// A digest over `challenge_info` signed in the current block and session.
+fn seg_digest(challenge_info: &ChallengeInfo<Test>) -> SegDigest<u64> {
+    SegDigest {
+        validators_len: Keys::<Test>::get().len() as u32,
+        block_num: System::block_number(),
+        session_index: Session::current_index(),
+        challenge_hash: sp_io::hashing::sha2_256(&challenge_info.encode()),
+        network_state: Default::default(),
+    }
 }
 
-fn upload_file_alias(_account: AccountId, controller: AccountId, file_info: &MockingFileInfo) -> DispatchResult {
-	let MockingFileInfo { file_hash, file_size, slice_info} = file_info.clone();
-	FileBank::upload(
-		RuntimeOrigin::signed(controller),
-		file_hash,
-		file_size,
-		slice_info,
-	)
+fn proposal_call(
+    pair: &crate::sr25519::AuthorityPair,
+    challenge_info: ChallengeInfo<Test>,
+    seg_digest: SegDigest<u64>,
+) -> Call<Test> {
+    let signature = pair.sign(&seg_digest.encode());
+    Call::save_challenge_info { challenge_info, key: pair.public(), seg_digest, signature }
 }
 
-fn create_new_bucket(acc: AccountId, bucket_name: Vec<u8>) -> DispatchResult {
-	FileBank::create_bucket(
-		RuntimeOrigin::signed(acc.clone()),
-		acc,
-		bucket_name.try_into().unwrap(),
-	)
+fn validate(call: &Call<Test>) -> TransactionValidity {
+    <Audit as ValidateUnsigned>::validate_unsigned(TransactionSource::External, call)
 }
 
-fn register_scheduler(stash: AccountId, controller: AccountId) -> DispatchResult {
-	pallet_cess_staking::Bonded::<Test>::insert(&stash, controller.clone());
-	TeeWorker::registration_scheduler(
-		RuntimeOrigin::signed(controller),
-		stash,
-		IpAddress::IPV4([127,0,0,1], 15000),
-	)
+#[test]
+fn audit_history_keeps_latest_rounds() {
+    new_test_ext().execute_with(|| {
+        let miner_acc = miner1();
+        let controller1 = controller1();
+        let limit = AuditHistoryLimit::get() as u64;
+
+        for start in 1 ..= limit + 2 {
+            Audit::record_audit_history(&miner_acc, start, |record| {
+                record.submit_block = Some(start + 1);
+            });
+        }
+        Audit::record_audit_history(&miner_acc, limit + 2, |record| {
+            record.tee_worker = Some(controller1.clone());
+            record.idle_result = Some(true);
+            record.service_result = Some(false);
+            let _ = record.punishment.try_push(AuditPunishment::ServiceFailed { amount: 100 });
+        });
+
+        let history = AuditHistory::<Test>::get(&miner_acc);
+        assert_eq!(limit as usize, history.len());
+        assert_eq!(3, history[0].challenge_start);
+
+        let last = history.last().unwrap();
+        assert_eq!(limit + 2, last.challenge_start);
+        assert_eq!(Some(limit + 3), last.submit_block);
+        assert_eq!(Some(controller1), last.tee_worker);
+        assert_eq!(Some(false), last.service_result);
+        assert_eq!(1, last.punishment.len());
+    });
+}
 
+#[test]
+fn challenge_life_scales_with_space() {
+    new_test_ext().execute_with(|| {
+        let params = Audit::challenge_life_params();
+        assert_eq!(ChallengeBaseLife::get(), params.base);
+
+        let empty = Audit::calculate_miner_life(&params, 0, 0).ok().unwrap();
+        assert_eq!(ChallengeBaseLife::get(), empty);
+
+        let life = Audit::calculate_miner_life(&params, T_BYTE + 1, T_BYTE).ok().unwrap();
+        assert_eq!(ChallengeBaseLife::get() + 2 * IdleLifePerTiB::get() + ServiceLifePerTiB::get(), life);
+
+        let new_params = ChallengeLifeParams { base: 20, idle_per_tib: 1, service_per_tib: 1 };
+        assert_noop!(
+            Audit::update_challenge_life_params(RuntimeOrigin::signed(miner1()), new_params.clone()),
+            sp_runtime::DispatchError::BadOrigin
+        );
+        assert_ok!(Audit::update_challenge_life_params(RuntimeOrigin::root(), new_params.clone()));
+        assert_eq!(new_params, Audit::challenge_life_params());
+    });
 }
 
 #[test]
-fn submit_challenge_prove_works() {
+fn challenge_proposal_counts_each_authority_once() {
     new_test_ext().execute_with(|| {
-        let miner_acc = miner1();
-        let file_id = Hash([5u8; 64]);
-        let mu = bounded_vec![2_u8];
-        let sigma = bounded_vec![1_u8];
-				let name = bounded_vec![5_u8];
-        let u = vec![3_u8, 3u8]
-        let controller1 = controller1();
-        let stash1 = stash1();
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-
-        let mut prove_list: Vec<ProveInfo<Test>> = Vec::new();
-        let challenge_info = ChallengeInfo::<Test>{
-					file_size: 1024,
-					file_type: DataType::Filler,
-					block_list: bounded_vec![1,2,3],
-					file_id: file_id.clone(),
-					shard_id: [0u8; 68],
-					random: bounded_vec![bounded_vec![1,3],bounded_vec![4,5]],
+        let authority = crate::sr25519::AuthorityId::from(sp_core::sr25519::Public::from_raw([1u8; 32]));
+        let challenge_info = ChallengeInfo::<Test> {
+            net_snap_shot: NetSnapShot {
+                start: 1,
+                life: 10,
+                total_reward: 0,
+                total_idle_space: 0,
+                total_service_space: 0,
+                random_index_list: Default::default(),
+                random_list: Default::default(),
+            },
+            miner_snapshot_list: Default::default(),
         };
-        let prove_info = ProveInfo::<Test> {
-            file_id: file_id.clone(),
-            miner_acc: miner_acc.clone(),
-            challenge_info: challenge_info.clone(),
-            mu,
-            sigma,
-						u,
+        let hash = sp_io::hashing::sha2_256(&challenge_info.encode());
+        let digest = SegDigest {
+            validators_len: 0,
+            block_num: 1,
+            session_index: 0,
+            challenge_hash: hash,
+            network_state: Default::default(),
         };
-        prove_list.push(prove_info);
-        assert_noop!(Audit::submit_challenge_prove(RuntimeOrigin::signed(miner_acc.clone()), prove_list.clone()), Error::<Test>::NoChallenge);
-        assert_ok!(ChallengeMap::<Test>::try_mutate(miner_acc, |value| -> DispatchResult {
-            value.try_push(challenge_info).map_err(|_| Error::<Test>::BoundedVecError)?;
-            Ok(())
-        }));
-
-        assert_ok!(Audit::submit_challenge_prove(RuntimeOrigin::signed(miner_acc.clone()), prove_list));
-
-        assert!(UnVerifyProof::<Test>::contains_key(&controller1));
-        let prove_info = UnVerifyProof::<Test>::try_get(&controller1).unwrap().pop().unwrap();
-        assert_eq!(miner_acc, prove_info.miner_acc);
-        assert_eq!(0, ChallengeMap::<Test>::try_get(miner_acc).unwrap().len());
-
-        let event = Sys::events().pop().expect("Expected at least one ChallengeProof to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::ChallengeProof { miner : miner_acc, file_id: file_id }), event);
+        let signature = crate::sr25519::AuthoritySignature::from(sp_core::sr25519::Signature::from_raw([0u8; 64]));
+
+        assert_ok!(Audit::save_challenge_info(
+            RuntimeOrigin::none(), challenge_info.clone(), authority.clone(), digest.clone(), signature.clone()
+        ));
+        assert_eq!(Some(1), ChallengeProposal::<Test>::get(&hash).map(|p| p.0));
+        assert!(ProposalVoters::<Test>::contains_key(&hash, &authority));
+
+        assert_noop!(
+            Audit::save_challenge_info(RuntimeOrigin::none(), challenge_info, authority, digest, signature),
+            Error::<Test>::DuplicateProposal
+        );
+        assert_eq!(Some(1), ChallengeProposal::<Test>::get(&hash).map(|p| p.0));
     });
 }
 
 #[test]
-fn verify_proof_works() {
+fn round_reward_settles_in_batches() {
     new_test_ext().execute_with(|| {
-        let miner_acc = miner1();
-				let file_id = Hash([5u8; 64]);
-        let controller1 = controller1();
-        let stash1 = stash1();
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-
-				let challenge_info = ChallengeInfo::<Test>{
-					file_size: 1024,
-					file_type: DataType::Filler,
-					block_list: bounded_vec![1,2,3],
-					file_id: file_id.clone(),
-					shard_id: [0u8; 68],
-					random: bounded_vec![bounded_vec![1,3],bounded_vec![4,5]],
-				};
-
-        let mut verify_result_list: Vec<VerifyResult<Test>> = Vec::new();
-        let verify_result = VerifyResult::<Test> {
-            miner_acc: miner_acc.clone(),
-            file_id: file_id.clone(),
-						shard_id: [0u8; 68],
-            result: true,
-        };
-        verify_result_list.push(verify_result);
-
-        assert_ok!(UnVerifyProof::<Test>::try_mutate(controller1, |value| -> DispatchResult {
-            value.try_push(ProveInfo {
-                file_id: file_id.clone(),
-                miner_acc,
-                challenge_info,
-                mu: bounded_vec![bounded_vec![1, 3], bounded_vec![4, 5]],
-                sigma: bounded_vec![1],
-								name: bounded_vec![1],
-								u: bounded_vec![bounded_vec![1, 3], bounded_vec![4, 5]],
-            }).map_err(|_| Error::<Test>::BoundedVecError)?;
-            Ok(())
-        }));
-
-        assert_ok!(Audit::verify_proof(RuntimeOrigin::signed(controller1.clone()), verify_result_list.clone()));
-        assert_eq!(0, UnVerifyProof::<Test>::try_get(controller1.clone()).unwrap().len());
-
-        let event = Sys::events().pop().expect("Expected at least one VerifyProof to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::VerifyProof { miner: miner_acc, file_id: file_id }), event);
+        RoundReward::<Test>::put(RewardRound {
+            challenge_start: 1,
+            total_reward: 1_000,
+            total_idle_space: 300,
+            total_service_space: 0,
+        });
+        for i in 0 .. 3u32 {
+            PendingReward::<Test>::insert(account("miner", i, 0), (100u128, 0u128));
+        }
+        VerifyDuration::<Test>::put(5);
+
+        Audit::settle_round_reward(5);
+        assert_eq!(3, PendingReward::<Test>::count());
+
+        Audit::settle_round_reward(6);
+        assert_eq!(3 - RewardSettleLimit::get(), PendingReward::<Test>::count());
+        assert!(RoundReward::<Test>::exists());
+
+        Audit::settle_round_reward(7);
+        assert_eq!(0, PendingReward::<Test>::count());
+        assert!(!RoundReward::<Test>::exists());
     });
 }
 
 #[test]
-fn verify_proof_on_punish() {
+fn hand_off_keeps_missions_without_other_tee() {
     new_test_ext().execute_with(|| {
-        let beneficiary = account::<mock::AccountId>("beneficiary", 0, 0);
-        let stake_amount: u64 = 2_000_000;
-        let ip = IpAddress::IPV4([127,0,0,1], 15000);
-        assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(miner1()), beneficiary, ip.clone(), stake_amount));
-        let miner_acc = miner1();
-        assert_ok!(Sminer::add_power(&miner_acc.clone(), 10_000));
-        let mu = bounded_vec![bounded_vec![2_u8]];
-        let sigma = bounded_vec![1_u8];
-				let name = bounded_vec![5_u8];
-				let u = bounded_vec![bounded_vec![3_u8, 3u8]];
-        let controller1 = controller1();
-        let stash1 = stash1();
-        let mfi = MockingFileInfo::default();
-        let acc1 = account1();
-				let bucket_name = "cess-bucket".as_bytes().to_vec();
-
-				assert_ok!(create_new_bucket(acc1, bucket_name.clone()));
-        assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash, bucket_name.clone()));
-        assert_ok!(Sminer::add_power(&miner_acc, 1_048_576 * 1024 * 50));
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 1));
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-        assert_ok!(add_power_for_miner(controller1.clone(), miner_acc.clone()));
-        assert_ok!(upload_file_alias(acc1.clone(), controller1.clone(), &mfi));
-
-				let mut shard_id1: Vec<u8> = mfi.file_hash.0.to_vec();
-				shard_id1.append("-001".as_bytes().to_vec().as_mut());
-				let shard_id1: [u8; 68] = shard_id1.as_slice().try_into().unwrap();
-
-        let mut prove_list: Vec<ProveInfo<Test>> = Vec::new();
-        let challenge_info = ChallengeInfo::<Test>{
-            file_type: DataType::File,
-            file_id: mfi.file_hash,
-						shard_id: shard_id1,
-            file_size: 12,
-            block_list: bounded_vec![1,2,3],
-            random: bounded_vec![bounded_vec![1,3],bounded_vec![4,5]],
-        };
+        let tee_acc = controller1();
         let prove_info = ProveInfo::<Test> {
-            file_id: mfi.file_hash,
-            miner_acc: miner_acc.clone(),
-            challenge_info: challenge_info.clone(),
-            mu,
-            sigma,
-						name,
-						u,
-        };
-        prove_list.push(prove_info);
-
-        let mut verify_result_list: Vec<VerifyResult<Test>> = Vec::new();
-        let verify_result = VerifyResult::<Test> {
-            miner_acc: miner_acc.clone(),
-            file_id: mfi.file_hash,
-						shard_id: shard_id1,
-            result: false,
+            snap_shot: MinerSnapShot { miner: miner1(), idle_space: 100, service_space: 0, life: 10 },
+            idle_prove: Default::default(),
+            service_prove: Default::default(),
         };
-        verify_result_list.push(verify_result);
-
-        assert_ok!(ChallengeMap::<Test>::try_mutate(&miner_acc, |value| -> DispatchResult {
-            value.try_push(challenge_info.clone()).map_err(|_| Error::<Test>::BoundedVecError)?;
-            Ok(())
-        }));
-
-        assert_ok!(Audit::submit_challenge_prove(RuntimeOrigin::signed(miner_acc.clone()), prove_list));
-        assert_ok!(Sminer::add_reward_order1(&miner_acc, 1000000));
-        //FIXME! the punish action is hard to test now, as it's depend concrete pallet: sminer. Suggest doing this use Trait instead.
-        assert_ok!(Audit::verify_proof(RuntimeOrigin::signed(controller1.clone()), verify_result_list));  // the last parameter indicate whether punish
-        assert_eq!(0, UnVerifyProof::<Test>::try_get(controller1.clone()).unwrap().len());
-
-        let event = Sys::events().pop().expect("Expected at least one VerifyProof to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::VerifyProof { miner: miner_acc.clone(), file_id: mfi.file_hash }), event);
-        <MinerTotalProof<Test>>::insert(&miner_acc, 1);
-        <VerifyDuration<Test>>::put(10);
-        <Audit as Hooks<u64>>::on_initialize(10);
-        Sys::set_block_number(11);
-
-        let state = Sminer::get_miner_state(miner_acc.clone()).unwrap();
-        assert_eq!(state, "frozen".as_bytes().to_vec());
+        UnverifyProof::<Test>::insert(&tee_acc, BoundedVec::truncate_from(vec![prove_info]));
+        assert_eq!(1, Audit::pending_missions(&tee_acc));
+
+        assert_noop!(Audit::hand_off_missions(&tee_acc), Error::<Test>::NoTeeForHandoff);
+        assert_eq!(1, Audit::pending_missions(&tee_acc));
+        assert_eq!(0, Audit::pending_missions(&miner1()));
+    });
+}
+
+#[test]
+fn select_tee_skips_full_queues() {
+    new_test_ext().execute_with(|| {
+        let full = controller1();
+        let free = miner1();
+        let mut candidates: BTreeMap<AccountId, (u32, u32)> = Default::default();
+        candidates.insert(full.clone(), (VerifyMissionMax::get(), 1000));
+        candidates.insert(free.clone(), (0, 0));
+
+        for seed in 0 .. 10u32 {
+            assert_eq!(Some(free.clone()), Audit::select_tee(&candidates, None, seed));
+        }
+        assert_eq!(None, Audit::select_tee(&candidates, Some(&free), 0u32));
+    });
+}
+
+#[test]
+fn signed_challenge_proposal_is_accepted() {
+    new_test_ext().execute_with(|| {
+        let (alice, bob) = (authority(1), authority(2));
+        set_keys(&[&alice, &bob]);
+        let info = challenge_info(1);
+        let call = proposal_call(&alice, info.clone(), seg_digest(&info));
+        assert!(validate(&call).is_ok());
+
+        let digest = seg_digest(&info);
+        let signature = alice.sign(&digest.encode());
+        assert_ok!(Audit::save_challenge_info(RuntimeOrigin::none(), info.clone(), alice.public(), digest, signature));
+
+        // The same authority cannot vote twice, the others still can.
+        assert_eq!(validate(&call), InvalidTransaction::Stale.into());
+        assert!(validate(&proposal_call(&bob, info.clone(), seg_digest(&info))).is_ok());
+    });
+}
+
+#[test]
+fn challenge_proposal_from_unknown_authority_is_stale() {
+    new_test_ext().execute_with(|| {
+        let (alice, mallory) = (authority(1), authority(9));
+        set_keys(&[&alice]);
+        let info = challenge_info(1);
+        let call = proposal_call(&mallory, info.clone(), seg_digest(&info));
+        assert_eq!(validate(&call), InvalidTransaction::Stale.into());
+    });
+}
+
+#[test]
+fn challenge_proposal_must_match_the_session() {
+    new_test_ext().execute_with(|| {
+        let (alice, bob) = (authority(1), authority(2));
+        set_keys(&[&alice, &bob]);
+        let info = challenge_info(1);
+
+        let mut digest = seg_digest(&info);
+        digest.session_index += 1;
+        assert_eq!(validate(&proposal_call(&alice, info.clone(), digest)), InvalidTransaction::Stale.into());
+
+        let mut digest = seg_digest(&info);
+        digest.validators_len = 1;
+        assert_eq!(validate(&proposal_call(&alice, info.clone(), digest)), InvalidTransaction::Stale.into());
+    });
+}
+
+#[test]
+fn challenge_proposal_must_be_recent() {
+    new_test_ext().execute_with(|| {
+        let alice = authority(1);
+        set_keys(&[&alice]);
+        let info = challenge_info(1);
+
+        let mut digest = seg_digest(&info);
+        digest.block_num = System::block_number() + 1;
+        assert_eq!(validate(&proposal_call(&alice, info.clone(), digest)), InvalidTransaction::Future.into());
+
+        let signed_at = System::block_number();
+        let digest = seg_digest(&info);
+        System::set_block_number(signed_at + ProposalValidity::get());
+        assert!(validate(&proposal_call(&alice, info.clone(), digest.clone())).is_ok());
+
+        System::set_block_number(signed_at + ProposalValidity::get() + 1);
+        assert_eq!(validate(&proposal_call(&alice, info.clone(), digest)), InvalidTransaction::Stale.into());
+    });
+}
+
+#[test]
+fn challenge_proposal_signature_binds_the_challenge() {
+    new_test_ext().execute_with(|| {
+        let alice = authority(1);
+        set_keys(&[&alice]);
+        let info = challenge_info(1);
+        let other = challenge_info(2);
+
+        // A digest signed for one challenge cannot carry another.
+        let call = proposal_call(&alice, other, seg_digest(&info));
+        assert_eq!(validate(&call), InvalidTransaction::BadProof.into());
+
+        // Nor can the digest be altered after signing.
+        let digest = seg_digest(&info);
+        let signature = alice.sign(&digest.encode());
+        let mut forged = digest.clone();
+        forged.network_state.peer_id = sp_core::OpaquePeerId(vec![1u8]);
+        let call = Call::save_challenge_info { challenge_info: info, key: alice.public(), seg_digest: forged, signature };
+        assert_eq!(validate(&call), InvalidTransaction::BadProof.into());
+    });
+}
+
+#[test]
+fn migration_v2_gives_miners_the_network_life() {
+    use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
+
+    new_test_ext().execute_with(|| {
+        let tee_acc = controller1();
+        let mut net_snap_shot = challenge_info(5).net_snap_shot;
+        // Before v2 the snapshot held the absolute block the challenge ended at.
+        net_snap_shot.life = 25;
+        net_snap_shot.total_reward = 1_000;
+        ChallengeDuration::<Test>::put(25);
+        let old_snap_shot = (miner1(), 100u128, 0u128);
+        // The v1 layouts, where miners had no proof window of their own.
+        frame_support::storage::unhashed::put_raw(
+            &ChallengeSnapShot::<Test>::hashed_key(),
+            &(net_snap_shot.clone(), vec![old_snap_shot.clone()]).encode(),
+        );
+        frame_support::storage::unhashed::put_raw(
+            &UnverifyProof::<Test>::hashed_key_for(&tee_acc),
+            &vec![(old_snap_shot, Vec::<u8>::new(), Vec::<u8>::new())].encode(),
+        );
+        ChallengeProposal::<Test>::insert([1u8; 32], (1, challenge_info(2)));
+        StorageVersion::new(1).put::<Audit>();
+
+        crate::migrations::v2::MigrateToV2::<Test>::on_runtime_upgrade();
+
+        assert_eq!(2, Audit::on_chain_storage_version());
+        // The window left from the start of the challenge to its end.
+        let life = 20;
+        let snap_shot = ChallengeSnapShot::<Test>::get().unwrap();
+        assert_eq!(NetSnapShot { life, ..net_snap_shot }, snap_shot.net_snap_shot);
+        assert_eq!(
+            vec![MinerSnapShot { miner: miner1(), idle_space: 100, service_space: 0, life }],
+            snap_shot.miner_snapshot_list.to_vec()
+        );
+        let proofs = UnverifyProof::<Test>::get(&tee_acc);
+        assert_eq!(1, proofs.len());
+        assert_eq!(life, proofs[0].snap_shot.life);
+        assert_eq!(0, ChallengeProposal::<Test>::count());
+
+        // Proofs of the running challenge can still be verified and rewarded.
+        assert_ok!(Audit::submit_verify_result(RuntimeOrigin::signed(tee_acc.clone()), miner1(), true, true, [0u8; 64]));
+        assert!(UnverifyProof::<Test>::get(&tee_acc).is_empty());
+        assert_eq!(Some((100, 0)), PendingReward::<Test>::get(&miner1()));
+        let round = RoundReward::<Test>::get().unwrap();
+        assert_eq!(5, round.challenge_start);
+        assert_eq!(1_000, round.total_reward);
+        assert_eq!(100, round.total_idle_space);
+    });
+}
+
+#[test]
+fn audit_api_returns_the_history_oldest_first() {
+    new_test_ext().execute_with(|| {
+        let miner_acc = miner1();
+        assert!(Audit::miner_audit_history(miner_acc.clone()).is_empty());
+
+        let limit = AuditHistoryLimit::get() as u64;
+        for start in 1 ..= limit + 1 {
+            Audit::record_audit_history(&miner_acc, start, |record| {
+                record.idle_result = Some(start % 2 == 0);
+            });
+        }
+
+        let history = Audit::miner_audit_history(miner_acc.clone());
+        let starts: Vec<u64> = history.iter().map(|record| record.challenge_start).collect();
+        assert_eq!((2 ..= limit + 1).collect::<Vec<u64>>(), starts);
+        assert_eq!(Some(true), history[0].idle_result);
+        assert!(Audit::miner_audit_history(controller1()).is_empty());
     });
 }
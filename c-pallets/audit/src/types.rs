@@ -8,12 +8,13 @@ use super::*;
 #[codec(mel_bound())]
 pub struct ChallengeInfo<T: pallet::Config> {
 	pub(super) net_snap_shot: NetSnapShot<BlockNumberOf<T>>,
-	pub(super) miner_snapshot_list: BoundedVec<MinerSnapShot<AccountOf<T>>, T::ChallengeMinerMax>,
+	pub(super) miner_snapshot_list: BoundedVec<MinerSnapShot<AccountOf<T>, BlockNumberOf<T>>, T::ChallengeMinerMax>,
 }
 
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
 pub struct NetSnapShot<Block> {
 	pub(super) start: Block,
+	// The longest proof window among the challenged miners, relative to `start`.
 	pub(super) life: Block,
 	pub(super) total_reward: u128,
 	pub(super) total_idle_space: u128,
@@ -23,10 +24,12 @@ pub struct NetSnapShot<Block> {
 }
 
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
-pub struct MinerSnapShot<AccountId> {
+pub struct MinerSnapShot<AccountId, Block> {
 	pub(super) miner: AccountId,
 	pub(super) idle_space: u128,
 	pub(super) service_space: u128,
+	// Proof window of this miner, relative to the start of the challenge.
+	pub(super) life: Block,
 }
 
 // Structure for storing miner certificates
@@ -34,7 +37,7 @@ pub struct MinerSnapShot<AccountId> {
 #[scale_info(skip_type_params(T))]
 #[codec(mel_bound())]
 pub struct ProveInfo<T: pallet::Config> {
-	pub(super) snap_shot: MinerSnapShot<AccountOf<T>>,
+	pub(super) snap_shot: MinerSnapShot<AccountOf<T>, BlockNumberOf<T>>,
 	pub(super) idle_prove: BoundedVec<u8, T::SigmaMax>,
 	pub(super) service_prove: BoundedVec<u8, T::SigmaMax>,
 }
@@ -49,9 +52,70 @@ pub struct VerifyResult<T: pallet::Config> {
 	pub(super) result: bool,
 }
 
+// Reward pool of a challenge round and the space of the miners that passed verification.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct RewardRound<Block> {
+	pub(super) challenge_start: Block,
+	pub(super) total_reward: u128,
+	pub(super) total_idle_space: u128,
+	pub(super) total_service_space: u128,
+}
+
+// Parameters used to derive the proof window of each challenged miner.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct ChallengeLifeParams<Block> {
+	// Blocks every challenged miner gets regardless of its space.
+	pub base: Block,
+	// Extra blocks for each started TiB of challenged idle space.
+	pub idle_per_tib: Block,
+	// Extra blocks for each started TiB of challenged service space.
+	pub service_per_tib: Block,
+}
+
 #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug, TypeInfo)]
 pub struct SegDigest<BlockNumber> {
 	pub(super) validators_len: u32,
 	pub(super) block_num: BlockNumber,
+	pub(super) session_index: u32,
+	// sha2_256 of the encoded challenge info this signature vouches for.
+	pub(super) challenge_hash: [u8; 32],
 	pub(super) network_state: OpaqueNetworkState,
 }
+
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum AuditPunishment {
+	// The idle proof failed verification.
+	IdleFailed { amount: u128 },
+	// The service proof failed verification.
+	ServiceFailed { amount: u128 },
+	// No proof was submitted before the challenge ended.
+	NoProof { count: u8, amount: u128 },
+}
+
+// One challenge round of a miner, kept in the audit history ring buffer.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AuditRecord<AccountId, BlockNumber> {
+	pub challenge_start: BlockNumber,
+	pub submit_block: Option<BlockNumber>,
+	pub tee_worker: Option<AccountId>,
+	pub idle_result: Option<bool>,
+	pub service_result: Option<bool>,
+	pub reward: u128,
+	pub punishment: BoundedVec<AuditPunishment, ConstU32<3>>,
+}
+
+impl<AccountId, BlockNumber> AuditRecord<AccountId, BlockNumber> {
+	pub(super) fn new(challenge_start: BlockNumber) -> Self {
+		AuditRecord {
+			challenge_start,
+			submit_block: None,
+			tee_worker: None,
+			idle_result: None,
+			service_result: None,
+			reward: u128::MIN,
+			punishment: Default::default(),
+		}
+	}
+}
@@ -131,7 +131,7 @@ impl<T: Config> Pallet<T> {
         user_brief: UserBrief<T>,
         share_info: Vec<SegmentInfo<T>>,
     ) -> DispatchResult {
-        let miner_task_list = Self::random_assign_miner(&needed_list)?;
+        let miner_task_list = Self::random_assign_miner(&file_hash, &needed_list)?;
 
         Self::start_first_task(file_hash.0.to_vec(), file_hash, 1)?;
 
@@ -185,6 +185,7 @@ impl<T: Config> Pallet<T> {
     }
 
     pub(super) fn random_assign_miner(
+        deal_hash: &Hash,
         needed_list: &BoundedVec<SegmentList<T>, T::SegmentCount>
     ) -> Result<BoundedVec<MinerTaskList<T>, T::StringLimit>, DispatchError> {
         let mut miner_task_list: BoundedVec<MinerTaskList<T>, T::StringLimit> = Default::default();
@@ -192,10 +193,16 @@ impl<T: Config> Pallet<T> {
         // The optimal number of miners required for storage.
         // segment_size * 1.5 / fragment_size.
         let miner_count: u32 = (SEGMENT_SIZE * 15 / 10 / FRAGMENT_SIZE) as u32;
-        let mut seed = <frame_system::Pallet<T>>::block_number().saturated_into();
+        let now = <frame_system::Pallet<T>>::block_number();
+        let mut seed: u32 = 0;
 
-        let mut all_miner = T::MinerControl::get_all_miner()?;
-        let mut total = all_miner.len() as u32;
+        let mut total = T::MinerControl::get_miner_count();
+        // Registry positions swapped out by earlier draws, so no miner is drawn twice.
+        let mut swapped: BTreeMap<u32, u32> = BTreeMap::new();
+        // Replicas are spread across regions first, eligible miners from regions
+        // already used are kept aside and only taken if there are not enough others.
+        let mut used_regions: BTreeSet<RegionCode> = BTreeSet::new();
+        let mut deferred: Vec<(AccountOf<T>, u128)> = Default::default();
 
         // ensure!(total > miner_count, Error::<T>::NodesInsufficient);
         let max_count = miner_count * 5;
@@ -209,7 +216,7 @@ impl<T: Config> Pallet<T> {
                 break;
             }
 
-            let index = Self::generate_random_number(seed)? as u32 % total;
+            let index = Self::generate_random_number((deal_hash, now, seed)) % total;
             // seed + 1
             seed = seed.checked_add(1).ok_or(Error::<T>::Overflow)?;
 
@@ -222,9 +229,12 @@ impl<T: Config> Pallet<T> {
             cur_count += 1;
 
             // Judge whether the idle space of the miners is sufficient.
-            let miner = all_miner[index as usize].clone();
-            all_miner.remove(index as usize);
-            total = total - 1;
+            let last = total - 1;
+            let position = swapped.get(&index).copied().unwrap_or(index);
+            let last_position = swapped.get(&last).copied().unwrap_or(last);
+            swapped.insert(index, last_position);
+            total = last;
+            let miner = T::MinerControl::get_miner_by_index(position).ok_or(Error::<T>::BugInvalid)?;
             let result = T::MinerControl::is_positive(&miner)?;
             if !result {
                 continue;
@@ -233,6 +243,12 @@ impl<T: Config> Pallet<T> {
             let cur_space: u128 = T::MinerControl::get_miner_idle_space(&miner)?;
             // If sufficient, the miner is selected.
             if cur_space > needed_list.len() as u128 * FRAGMENT_SIZE {
+                if let Some(region) = T::MinerControl::get_miner_region(&miner) {
+                    if !used_regions.insert(region) {
+                        deferred.push((miner, cur_space));
+                        continue;
+                    }
+                }
                 // Accumulate all idle space of currently selected miners
                 total_idle_space = total_idle_space.checked_add(&cur_space).ok_or(Error::<T>::Overflow)?;
                 let miner_task = MinerTaskList::<T>{
@@ -247,6 +263,20 @@ impl<T: Config> Pallet<T> {
                 break;
             }
         }
+
+        // Fill up with miners from regions that already hold a replica.
+        for (miner, cur_space) in deferred {
+            if miner_task_list.len() as u32 == miner_count {
+                break;
+            }
+            total_idle_space = total_idle_space.checked_add(&cur_space).ok_or(Error::<T>::Overflow)?;
+            let miner_task = MinerTaskList::<T>{
+                miner: miner,
+                fragment_list: Default::default(),
+            };
+            miner_task_list.try_push(miner_task).map_err(|_e| Error::<T>::BoundedVecError)?;
+            miner_idle_space_list.push(cur_space);
+        }
         
         ensure!(miner_task_list.len() != 0, Error::<T>::BugInvalid);
         ensure!(total_idle_space > SEGMENT_SIZE * 15 / 10, Error::<T>::NodesInsufficient);
@@ -420,27 +450,14 @@ impl<T: Config> Pallet<T> {
     }
     /// helper: generate random number.
     ///
-    /// Get a random number.
+    /// Get a random number from the rrsc VRF randomness, bound to the given subject.
     ///
     /// Parameters:
-    /// - `seed`: random seed.
+    /// - `subject`: data the random number is bound to, e.g. the deal hash.
     /// Result:
     /// - `u32`: random number.
-    pub fn generate_random_number(seed: u32) -> Result<u32, DispatchError> {
-        let mut counter = 0;
-        loop {
-            let (random_seed, _) =
-                T::MyRandomness::random(&(T::FilbakPalletId::get(), seed + counter).encode());
-            let random_seed = match random_seed {
-                Some(v) => v,
-                None => Default::default(),
-            };
-            let random_number = <u32>::decode(&mut random_seed.as_ref()).unwrap_or(0);
-            if random_number != 0 {
-                return Ok(random_number)
-            }
-            counter = counter.checked_add(1).ok_or(Error::<T>::Overflow)?;
-        }
+    pub fn generate_random_number<S: Encode>(subject: S) -> u32 {
+        T::MyRandomness::random_u64(&(T::FilbakPalletId::get(), subject).encode()) as u32
     }
     /// helper: add user hold fileslice.
     ///
@@ -66,9 +66,9 @@ use sp_std::{
 	convert::TryInto, 
 	prelude::*, 
 	str, 
-	collections::btree_map::BTreeMap
+	collections::{btree_map::BTreeMap, btree_set::BTreeSet},
 };
-use pallet_sminer::MinerControl;
+use pallet_sminer::{MinerControl, MinerState};
 use pallet_tee_worker::ScheduleFind;
 use pallet_oss::OssFindAuthor;
 
@@ -111,8 +111,8 @@ pub mod pallet {
 		//Used to find out whether the schedule exists
 		type Scheduler: ScheduleFind<Self::AccountId>;
 		//It is used to control the computing power and space of miners
-		type MinerControl: MinerControl<Self::AccountId>;
-		//Interface that can generate random seeds
+		type MinerControl: MinerControl<Self::AccountId, Self::BlockNumber>;
+		//VRF-backed randomness from rrsc, consumed through `VrfRandomness`
 		type MyRandomness: Randomness<Option<Self::Hash>, Self::BlockNumber>;
 
 		type StorageHandle: StorageHandle<Self::AccountId>;
@@ -516,7 +516,7 @@ pub mod pallet {
 						let task_count = miner_task.fragment_list.len() as u128;
 						T::MinerControl::unlock_space(&miner_task.miner, FRAGMENT_SIZE * task_count)?;
 					}
-					let miner_task_list = Self::random_assign_miner(&deal_info.needed_list)?;
+					let miner_task_list = Self::random_assign_miner(&deal_hash, &deal_info.needed_list)?;
 					deal_info.assigned_miner = miner_task_list;
 					deal_info.complete_list = Default::default();
 					deal_info.count = count;
@@ -624,7 +624,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			deal_hash: Vec<Hash>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 			ensure!(deal_hash.len() < 5, Error::<T>::LengthExceedsLimit);
 			let mut failed_list: Vec<Hash> = Default::default();
 			for hash in deal_hash {
@@ -732,7 +733,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			filler: Vec<Hash>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 
 			ensure!(filler.len() < 30, Error::<T>::LengthExceedsLimit);
 			let pending_count = <PendingReplacements<T>>::get(&sender);
@@ -800,7 +802,8 @@ pub mod pallet {
 			tee_worker: AccountOf<T>,
 			filler_list: Vec<FillerInfo<T>>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 			let limit = T::UploadFillerLimit::get();
 			if filler_list.len() > limit as usize {
 				Err(Error::<T>::LengthExceedsLimit)?;
@@ -839,7 +842,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			filler_hash: Hash,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 
 			let is_positive = T::MinerControl::is_positive(&sender)?;
 			ensure!(is_positive, Error::<T>::NotQualified);
@@ -990,7 +994,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			restoral_fragment: Hash,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 			let is_positive = T::MinerControl::is_positive(&sender)?;
 			ensure!(is_positive, Error::<T>::MinerStateError);
 
@@ -1022,7 +1027,8 @@ pub mod pallet {
 			file_hash: Hash,
 			restoral_fragment: Hash,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 			let is_positive = T::MinerControl::is_positive(&sender)?;
 			ensure!(is_positive, Error::<T>::MinerStateError);
 
@@ -1076,7 +1082,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			fragment_hash: Hash,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let worker = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_worker(&worker).ok_or(Error::<T>::NotQualified)?;
 			let is_positive = T::MinerControl::is_positive(&sender)?;
 			ensure!(is_positive, Error::<T>::MinerStateError);
 
@@ -1128,7 +1135,8 @@ pub mod pallet {
 		pub fn miner_exit_prep(
 			origin: OriginFor<T>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
+			let controller = ensure_signed(origin)?;
+			let sender = T::MinerControl::stash_of_controller(&controller).ok_or(Error::<T>::NotQualified)?;
 
 			if let Ok(lock_time) = <MinerLock<T>>::try_get(&sender) {
 				let now = <frame_system::Pallet<T>>::block_number();
@@ -1137,7 +1145,7 @@ pub mod pallet {
 
 			let result = T::MinerControl::is_positive(&sender)?;
 			ensure!(result, Error::<T>::MinerStateError);
-			T::MinerControl::update_miner_state(&sender, "lock")?;
+			T::MinerControl::update_miner_state(&sender, MinerState::Lock)?;
 
 			let now = <frame_system::Pallet<T>>::block_number();
 			let lock_time = T::OneDay::get().checked_add(&now).ok_or(Error::<T>::Overflow)?;
@@ -71,22 +71,28 @@ frame_support::construct_runtime!(
 		TeeWorker: pallet_tee_worker,
 		SchedulerCredit: pallet_scheduler_credit,
 		Oss: pallet_oss,
+		StorageHandler: pallet_storage_handler,
 		Preimage: pallet_preimage
 	}
 );
 
+parameter_types! {
+	pub const P2PLength: u32 = 200;
+}
+
 impl pallet_oss::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
 	type WeightInfo = ();
+	type P2PLength = P2PLength;
 }
 
 parameter_types! {
 	#[derive(Clone, PartialEq, Eq)]
 	pub const StringLimit: u32 = 100;
 	#[derive(Clone, PartialEq, Eq)]
-	pub const OneHours: u32 = 60 * 10;
+	pub const OneHours: BlockNumber = 60 * 10;
 	#[derive(Clone, PartialEq, Eq)]
-	pub const OneDay: u32 = 60 * 10 * 24;
+	pub const OneDay: BlockNumber = 60 * 10 * 24;
 }
 
 parameter_types! {
@@ -124,6 +130,17 @@ parameter_types! {
 	pub const ItemLimit: u32 = 1024;
 	pub const MaxAward: u128 = 1_306_849_000_000_000_000;
 	pub const LockInPeriod: u8 = 2;
+	pub const ChallengeMinerMax: u32 = 8000;
+	pub const PayoutBatchSize: u32 = 10;
+	pub const RewardImmediateShare: Perbill = Perbill::from_percent(20);
+	pub const RewardReleaseNumber: u32 = 2;
+	pub const RewardReleaseInterval: u64 = 10;
+	pub const MaxRewardOrders: u32 = 4;
+	pub const MinerUnbondingPeriod: u64 = 10;
+	pub const MinerMaxUnlockingChunks: u32 = 4;
+	pub const MaxDelegators: u32 = 4;
+	pub const MaxCommission: Perbill = Perbill::from_percent(50);
+	pub const MaxMultiaddrs: u32 = 4;
 }
 
 impl pallet_sminer::Config for Test {
@@ -141,7 +158,18 @@ impl pallet_sminer::Config for Test {
 	type DepositBufferPeriod = DepositBufferPeriod;
 	type OneDayBlock = OneDay;
 	type MaxAward = MaxAward;
+	type ChallengeMinerMax = ChallengeMinerMax;
 	type LockInPeriod = LockInPeriod;
+	type PayoutBatchSize = PayoutBatchSize;
+	type RewardImmediateShare = RewardImmediateShare;
+	type RewardReleaseNumber = RewardReleaseNumber;
+	type RewardReleaseInterval = RewardReleaseInterval;
+	type MaxRewardOrders = MaxRewardOrders;
+	type UnbondingPeriod = MinerUnbondingPeriod;
+	type MaxUnlockingChunks = MinerMaxUnlockingChunks;
+	type MaxDelegators = MaxDelegators;
+	type MaxCommission = MaxCommission;
+	type MaxMultiaddrs = MaxMultiaddrs;
 }
 
 parameter_types! {
@@ -150,6 +178,16 @@ parameter_types! {
 	pub const SchedulerMaximum: u32 = 10000;
 	#[derive(Clone, PartialEq, Eq)]
 	pub const ParamsLimit: u32 = 359;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const MaxWhitelist: u32 = 4;
+	pub const MaxReportAge: u64 = 3600;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const MaxCrls: u32 = 4;
+	pub const AttestationValidity: u64 = 100;
+	pub const AttestationGracePeriod: u64 = 20;
+	pub const Podr2KeyThreshold: Perbill = Perbill::from_percent(67);
+	pub const Podr2KeyActivationDelay: u64 = 10;
+	pub const ExitTimeout: u64 = 30;
 }
 
 impl pallet_tee_worker::Config for Test {
@@ -161,6 +199,15 @@ impl pallet_tee_worker::Config for Test {
 	type CreditCounter = SchedulerCredit;
 	type SchedulerMaximum = SchedulerMaximum;
 	type ParamsLimit = ParamsLimit;
+	type MaxWhitelist = MaxWhitelist;
+	type MaxReportAge = MaxReportAge;
+	type MaxCrls = MaxCrls;
+	type AttestationValidity = AttestationValidity;
+	type AttestationGracePeriod = AttestationGracePeriod;
+	type Podr2KeyThreshold = Podr2KeyThreshold;
+	type Podr2KeyActivationDelay = Podr2KeyActivationDelay;
+	type VerifyMission = ();
+	type ExitTimeout = ExitTimeout;
 }
 
 const THRESHOLDS: [sp_npos_elections::VoteWeight; 9] =
@@ -229,6 +276,7 @@ impl pallet_cess_staking::Config for Test {
     const FIRST_YEAR_VALIDATOR_REWARDS: BalanceOf<Test> = 618_000_000;
     const FIRST_YEAR_SMINER_REWARDS: BalanceOf<Test> = 309_000_000;
     const REWARD_DECREASE_RATIO: Perbill = Perbill::from_perthousand(794);
+    const REWARD_DECREASE_YEARS: u64 = 30;
     type SminerRewardPool = ();
     type Currency = Balances;
 	type CurrencyBalance = <Self as pallet_balances::Config>::Balance;
@@ -422,22 +470,34 @@ parameter_types! {
 	#[derive(Clone, Eq, PartialEq)]
 	pub const NameStrLimit: u32 = 63;
 	#[derive(Clone, Eq, PartialEq)]
-	pub const MinLength: u32 = 3;
+	pub const NameMinLength: u32 = 3;
 	#[derive(Clone, Eq, PartialEq)]
 	pub const FileListLimit: u32 = 500000;
 	#[derive(Clone, Eq, PartialEq)]
-	pub const FrozenDays: BlockNumber = 60 * 10 * 24 * 7;
+	pub const SegmentCount: u32 = 100;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const FragmentCount: u32 = 3;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const OwnerLimit: u32 = 50000;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const RestoralOrderLife: u32 = 250;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const MissionCount: u32 = 300;
 }
 
 impl Config for Test {
 	type RuntimeEvent = RuntimeEvent;
-	type Currency = Balances;
 	type WeightInfo = ();
 	type RuntimeCall = RuntimeCall;
+	type FScheduler = Scheduler;
+	type AScheduler = Scheduler;
+	type SPalletsOrigin = OriginCaller;
+	type SProposal = RuntimeCall;
 	type FindAuthor = ();
 	type CreditCounter = SchedulerCredit;
 	type Scheduler = pallet_tee_worker::Pallet::<Test>;
 	type MinerControl = pallet_sminer::Pallet::<Test>;
+	type StorageHandle = StorageHandler;
 	type MyRandomness = TestRandomness<Self>;
 	type FilbakPalletId = FilbakPalletId;
 	type StringLimit = StringLimit;
@@ -446,11 +506,34 @@ impl Config for Test {
 	type NameStrLimit = NameStrLimit;
 	type BucketLimit = BucketLimit;
 	type OssFindAuthor = Oss;
-	type FrozenDays = FrozenDays;
 	type RecoverLimit = RecoverLimit;
 	type InvalidLimit = InvalidLimit;
 	type UploadFillerLimit = UploadFillerLimit;
-	type MinLength = MinLength;
+	type NameMinLength = NameMinLength;
+	type SegmentCount = SegmentCount;
+	type FragmentCount = FragmentCount;
+	type OwnerLimit = OwnerLimit;
+	type RestoralOrderLife = RestoralOrderLife;
+	type MissionCount = MissionCount;
+}
+
+parameter_types! {
+	pub const TreasuryPalletId: PalletId = PalletId(*b"py/trsry");
+	#[derive(Clone, Eq, PartialEq)]
+	pub const StateStringMax: u32 = 20;
+	#[derive(Clone, Eq, PartialEq)]
+	pub const FrozenDays: BlockNumber = 60 * 10 * 24 * 7;
+}
+
+impl pallet_storage_handler::Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type WeightInfo = ();
+	type OneDay = OneDay;
+	type FilbakPalletId = FilbakPalletId;
+	type TreasuryPalletId = TreasuryPalletId;
+	type StateStringMax = StateStringMax;
+	type FrozenDays = FrozenDays;
 }
 
 pub fn account1() -> AccountId {
@@ -473,6 +556,30 @@ pub fn controller1() -> AccountId {
     account("controller1", 0, 0)
 }
 
+// Registers `controller` as a tee worker. Registering for real needs an enclave and the
+// worker map is private to its pallet, so the entry is written straight into storage.
+pub fn register_tee_worker(controller: AccountId, stash: AccountId) {
+	let info = pallet_tee_worker::TeeWorkerInfo::<Test> {
+		controller_account: controller.clone(),
+		peer_id: [0u8; 38],
+		endpoint: IpAddress::IPV4([127, 0, 0, 1], 15001),
+		multiaddr: Default::default(),
+		capabilities: Default::default(),
+		node_key: NodePublicKey::from_raw([0u8; 32]),
+		stash_account: stash,
+		attested_at: System::block_number(),
+		mr_enclave: None,
+	};
+	let key = [
+		sp_io::hashing::twox_128(b"TeeWorker").to_vec(),
+		sp_io::hashing::twox_128(b"TeeWorkerMap").to_vec(),
+		controller.using_encoded(sp_io::hashing::blake2_128).to_vec(),
+		controller.encode(),
+	]
+	.concat();
+	frame_support::storage::unhashed::put(&key, &info);
+}
+
 pub fn new_test_ext() -> sp_io::TestExternalities {
     let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
     pallet_balances::GenesisConfig::<Test> {
@@ -486,7 +593,7 @@ pub fn new_test_ext() -> sp_io::TestExternalities {
     }
         .assimilate_storage(&mut t)
         .unwrap();
-		file_bank::GenesisConfig::<Test> {
+		pallet_storage_handler::GenesisConfig::<Test> {
 			price: 30
 		}
 			.assimilate_storage(&mut t)
@@ -5,407 +5,285 @@
 use super::*;
 use crate::{mock::*, Event};
 use mock::System as Sys;
-use frame_support::{assert_ok, assert_noop};
-use cp_cess_common::{IpAddress, Hash};
+use frame_benchmarking::account;
+use frame_support::{assert_noop, assert_ok, traits::Currency};
 use pallet_sminer::MinerControl;
 
-
-#[derive(Debug, Clone)]
-pub struct MockingFileBankInfo {
-    file_hash: Hash,
-    file_size: u64,
-	  slice_info: Vec<SliceInfo<Test>>,
+const MINER_STAKE: u64 = 2_000_000_000_000_000;
+const IDLE_SPACE: u128 = 1024 * 1024 * 1024;
+const BUCKET_NAME: &str = "cess-bucket";
+
+// Registers a positive miner with idle space, declared in `region`.
+fn register_miner_in(miner: &AccountId, region: RegionCode) {
+	let _ = Balances::make_free_balance_be(miner, MINER_STAKE * 2);
+	assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(miner.clone()), miner.clone(), [0u8; 38], MINER_STAKE));
+	assert_ok!(Sminer::test_update_miner_idle_space(miner, IDLE_SPACE));
+	assert_ok!(<StorageHandler as StorageHandle<_>>::add_total_idle_space(IDLE_SPACE));
+	assert_ok!(Sminer::update_metadata(RuntimeOrigin::signed(miner.clone()), region, Default::default(), IDLE_SPACE * 2));
 }
 
-type Balance = u64;
-const UNIT_PRICE: Balance = 30;
-
-impl Default for MockingFileBankInfo {
-    fn default() -> Self {
-			let file_hash = Hash([5u8; 64]);
-			let mut file_hash1: Vec<u8> = file_hash.0.to_vec();
-			file_hash1.append("-001".as_bytes().to_vec().as_mut());
-			let file_hash1: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-
-			let mut file_hash2: Vec<u8> = file_hash.0.to_vec();
-			file_hash2.append("-002".as_bytes().to_vec().as_mut());
-			let file_hash2: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-
-			let mut file_hash3: Vec<u8> = file_hash.0.to_vec();
-			file_hash3.append("-002".as_bytes().to_vec().as_mut());
-			let file_hash3: [u8; 68] = file_hash1.as_slice().try_into().unwrap();
-        MockingFileBankInfo {
-            file_hash: file_hash,
-            file_size: 12,
-            slice_info: vec![SliceInfo::<Test>{
-                miner_id: 1,
-                shard_size: 111,
-                block_num: 8,
-                shard_id: file_hash1,
-                miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-                miner_acc: mock::miner1(),
-            },
-            SliceInfo::<Test>{
-                miner_id: 1,
-                shard_size: 111,
-                block_num: 8,
-                shard_id: file_hash2,
-                miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-                miner_acc: mock::miner1(),
-            },
-            SliceInfo::<Test>{
-                miner_id: 1,
-                shard_size: 111,
-                block_num: 8,
-                shard_id: file_hash3,
-                miner_ip: IpAddress::IPV4([127,0,0,1], 15000),
-                miner_acc: mock::miner1(),
-            },
-            ]
-        }
-    }
+// Registers enough miners to take every fragment of a segment.
+fn register_miners() {
+	for i in 0 .. FragmentCount::get() {
+		register_miner_in(&account("miner", i, 0), *b"CN");
+	}
 }
 
-fn upload_declaration_alias(account: AccountId, file_name: Vec<u8>, file_hash: Hash, bucket_name: Vec<u8>) -> DispatchResult {
-		let user_brief = UserBrief::<Test>{
-			user: account.clone(),
-			file_name: file_name.try_into().unwrap(),
-			bucket_name: bucket_name.try_into().unwrap(),
-		};
-    FileBank::upload_declaration(
-        RuntimeOrigin::signed(account.clone()),
-        file_hash,
-        user_brief,
-    )
+fn user_brief(user: &AccountId, file_name: &str, bucket_name: &str) -> UserBrief<Test> {
+	UserBrief::<Test> {
+		user: user.clone(),
+		file_name: file_name.as_bytes().to_vec().try_into().unwrap(),
+		bucket_name: bucket_name.as_bytes().to_vec().try_into().unwrap(),
+	}
 }
 
-fn upload_file_alias(_account: AccountId, controller: AccountId, file_info: &MockingFileBankInfo) -> DispatchResult {
-    let MockingFileBankInfo { file_hash, file_size, slice_info} = file_info.clone();
-    FileBank::upload(
-        RuntimeOrigin::signed(controller),
-        file_hash,
-        file_size,
-        slice_info,
-    )
+// A file of `count` segments, each split into `fragments` fragments.
+fn deal_info(count: u8, fragments: u8) -> BoundedVec<SegmentList<Test>, SegmentCount> {
+	let segments: Vec<SegmentList<Test>> = (0 .. count)
+		.map(|i| SegmentList::<Test> {
+			hash: Hash([i; 64]),
+			fragment_list: (0 .. fragments)
+				.map(|j| {
+					let mut hash = [100 + j; 64];
+					hash[0] = i;
+					Hash(hash)
+				})
+				.collect::<Vec<Hash>>()
+				.try_into()
+				.unwrap(),
+		})
+		.collect();
+	segments.try_into().unwrap()
 }
 
-fn create_new_bucket(acc: AccountId, bucket_name: Vec<u8>) -> DispatchResult {
-	FileBank::create_bucket(
-		RuntimeOrigin::signed(acc.clone()),
-		acc,
-		bucket_name.try_into().unwrap(),
+fn upload_declaration_alias(user: &AccountId, file_hash: Hash, deal_info: BoundedVec<SegmentList<Test>, SegmentCount>) -> DispatchResult {
+	FileBank::upload_declaration(
+		RuntimeOrigin::signed(user.clone()),
+		file_hash,
+		deal_info,
+		user_brief(user, "cess-book", BUCKET_NAME),
 	)
 }
 
-fn register_scheduler(stash: AccountId, controller: AccountId) -> DispatchResult {
-    pallet_cess_staking::Bonded::<Test>::insert(&stash, controller.clone());
-    TeeWorker::registration_scheduler(
-        RuntimeOrigin::signed(controller),
-        stash,
-        IpAddress::IPV4([127,0,0,1], 15000),
-    )
-
-}
-
-fn register_miner(miner: AccountId) -> DispatchResult {
-    Sminer::regnstk(
-        RuntimeOrigin::signed(miner),
-        miner.clone(),
-				IpAddress::IPV4([127,0,0,1], 15000),
-        2_000u128.try_into().unwrap(),
-    )
+// Every miner assigned to the deal reports its fragments as stored.
+fn transfer_report_all(deal_hash: Hash) {
+	let deal = DealMap::<Test>::get(&deal_hash).unwrap();
+	for miner_task in deal.assigned_miner.iter() {
+		assert_ok!(FileBank::transfer_report(RuntimeOrigin::signed(miner_task.miner.clone()), vec![deal_hash]));
+	}
 }
 
-fn add_power_for_miner(controller: AccountId, miner: AccountId) -> DispatchResult {
-    let max: u8 = 10;
-    let mut filler_list: Vec<FillerInfo<Test>> = Vec::new();
-    for i in 0 .. max {
-        let filler_hash: Hash = Hash([i; 64]);
-        filler_list.push(
-            FillerInfo::<Test> {
-                index: 1,
-                filler_size: 8 * 1_048_576,
-                block_num: 8,
-                segment_size: 1_048_576,
-                scan_size: 1_048_576,
-                miner_address: miner.clone(),
-                filler_hash: filler_hash.clone(),
-            }
-        )
-    }
-    FileBank::upload_filler(RuntimeOrigin::signed(controller), miner, filler_list)?;
-
-    Ok(())
-}
-
-#[test]
-fn buy_space_works() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let _bal_before = Balances::free_balance(acc1);
-        let miner1 = mock::miner1();
-        let space_gb = 10_u128;
-        let bn = Sys::block_number();
-
-        assert_ok!(register_miner(miner1.clone()));
-        assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));  // GB => MB
-
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 10));
-
-        let uhsd = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-        assert_eq!(space_gb * 1024 * 1_048_576, uhsd.total_space); // MB unit
-        assert_eq!(14400 * 30 + bn as u128, uhsd.deadline as u128);
-
-        assert_eq!(uhsd.total_space, uhsd.remaining_space);
-				let price = UNIT_PRICE * space_gb as u64;
-
-        let event = Sys::events().pop().expect("Expected at least one BuySpace to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::BuySpace { acc: acc1, storage_capacity: space_gb * 1024 * 1_048_576, spend: price }), event);
-    });
-}
-
-#[test]
-fn expansion_space_work() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let miner1 = mock::miner1();
-        let space_gb = 1000_u128;
-        assert_ok!(register_miner(miner1.clone()));
-        assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));  // GB => MB
-
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 1));
-        let uhsd = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-        assert_eq!(1 * 1024 * 1_048_576, uhsd.total_space); // MB unit
-
-        assert_ok!(FileBank::expansion_space(RuntimeOrigin::signed(acc1), 1));
-				let price = UNIT_PRICE * 1;
-
-        let event = Sys::events().pop().expect("Expected at least one BuySpace to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::ExpansionSpace { acc: acc1, expansion_space: 1024 * 1024 * 1024, fee: price,}), event);
-
-        let uhsd = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-        assert_eq!(2 * 1024 * 1_048_576, uhsd.total_space); // MB unit
-    });
+// Uploads a one segment file for `user` and waits for the tags to be calculated.
+fn upload_file(user: &AccountId, file_hash: Hash) {
+	assert_ok!(upload_declaration_alias(user, file_hash, deal_info(1, FragmentCount::get() as u8)));
+	transfer_report_all(file_hash);
+	assert_ok!(FileBank::calculate_end(RuntimeOrigin::root(), file_hash));
 }
 
-#[test]
-fn renewal_space_work() {
-    new_test_ext().execute_with(|| {
-			let acc1 = mock::account1();
-			let miner1 = mock::miner1();
-			let space_gb = 1000_u128;
-			assert_ok!(register_miner(miner1.clone()));
-			assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));  // GB => MB
-
-			let gib_count = 2;
-			assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), gib_count));
-			let uhsd = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-			assert_eq!(2 * 1024 * 1_048_576, uhsd.total_space); // MB unit
-
-			let renewal_days = 32;
-			let price = UNIT_PRICE / 30 * (renewal_days as u64) * (gib_count as u64);
-
-			assert_ok!(FileBank::renewal_space(RuntimeOrigin::signed(acc1), renewal_days));
-			let event = Sys::events().pop().expect("Expected at least one BuySpace to be found").event;
-			assert_eq!(mock::RuntimeEvent::from(Event::RenewalSpace { acc: acc1, renewal_days, fee: price}), event);
-
-			let uhsd = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-			assert_eq!(2 * 1024 * 1_048_576, uhsd.total_space);
-			assert_eq!(1, uhsd.start);
-			assert_eq!(62 * 14400 + 1, uhsd.deadline);
-    });
+fn add_filler_for_miner(tee_worker: &AccountId, miner: &AccountId, count: u8) -> DispatchResult {
+	let filler_list: Vec<FillerInfo<Test>> = (0 .. count)
+		.map(|i| FillerInfo::<Test> {
+			block_num: 8,
+			miner_address: miner.clone(),
+			filler_hash: Hash([i; 64]),
+		})
+		.collect();
+	FileBank::upload_filler(RuntimeOrigin::signed(miner.clone()), tee_worker.clone(), filler_list)
 }
 
 #[test]
 fn upload_declaration() {
-    new_test_ext().execute_with(|| {
-			let acc1 = mock::account1();
-			let file_name = "cess-book".as_bytes().to_vec();
-			let file_hash = Hash([5u8; 64]);
-			let bucket_name: Vec<u8> = "cess-bucket".as_bytes().to_vec();
-
-			assert_noop!(upload_declaration_alias(acc1.clone(), file_name.clone(), file_hash.clone(), bucket_name.clone()), Error::<Test>::NonExistent);
-			assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-
-			assert_ok!(upload_declaration_alias(acc1, file_name, file_hash, bucket_name));
-    });
+	new_test_ext().execute_with(|| {
+		let acc1 = account1();
+		let file_hash = Hash([5u8; 64]);
+		register_miners();
+
+		assert_noop!(
+			upload_declaration_alias(&acc1, file_hash, deal_info(1, 3)),
+			pallet_storage_handler::Error::<Test>::NotPurchasedSpace
+		);
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+		assert_noop!(upload_declaration_alias(&acc1, file_hash, deal_info(1, 2)), Error::<Test>::SpecError);
+
+		let avail_before = StorageHandler::get_user_avail_space(&acc1).unwrap();
+		assert_ok!(upload_declaration_alias(&acc1, file_hash, deal_info(1, 3)));
+
+		let deal = DealMap::<Test>::get(&file_hash).unwrap();
+		assert_eq!(1, deal.stage);
+		assert_eq!(3, deal.assigned_miner.len());
+		assert_eq!(avail_before - FileBank::cal_file_size(1), StorageHandler::get_user_avail_space(&acc1).unwrap());
+
+		let event = Sys::events().pop().expect("Expected at least one UploadDeclaration to be found").event;
+		assert_eq!(
+			mock::RuntimeEvent::from(Event::UploadDeclaration { operator: acc1.clone(), owner: acc1, deal_hash: file_hash }),
+			event
+		);
+	});
 }
 
 #[test]
 fn upload_works() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let miner1 = mock::miner1();
-        let stash1 = mock::stash1();
-        let controller1 = mock::controller1();
-        let mfi = MockingFileBankInfo::default();
-				let bucket_name = "cess-bucket".as_bytes().to_vec();
-				let file_name = "cess-book".as_bytes().to_vec();
-
-				assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-        assert_ok!(upload_declaration_alias(acc1, file_name.clone(), mfi.file_hash.clone(), bucket_name.clone()));
-        assert_noop!(upload_file_alias(acc1, controller1, &mfi), Error::<Test>::ScheduleNonExistent);
-        assert_ok!(register_miner(miner1));
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-        //upload() file not work have not buy space
-
-
-        let space_gb = 20_u128;
-        assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-        assert_ok!(add_power_for_miner(controller1, miner1));
-
-        assert_ok!(upload_file_alias(acc1, controller1, &mfi));
-
-        let file_hash = mfi.file_hash;
-        let file_size = mfi.file_size;
-        let file_slice_info = UserFileSliceInfo{
-            file_hash: file_hash.clone(),
-            file_size: file_size,
-        };
-        assert!(File::<Test>::contains_key(&file_hash));
-        let t = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-        assert_eq!(mfi.file_size as u128, t.used_space);
-        assert_eq!(t.total_space - mfi.file_size as u128, t.remaining_space);
-        assert!(UserHoldFileList::<Test>::try_get(acc1).unwrap().contains(&file_slice_info));
-
-        let event = Sys::events().pop().expect("Expected at least one FileUpload to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::FileUpload { acc: controller1 }), event);
-    });
+	new_test_ext().execute_with(|| {
+		let acc1 = account1();
+		let file_hash = Hash([5u8; 64]);
+		register_miners();
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+		assert_ok!(upload_declaration_alias(&acc1, file_hash, deal_info(1, 3)));
+
+		// Only miners assigned to the deal can report it.
+		assert_noop!(
+			FileBank::transfer_report(RuntimeOrigin::signed(acc1.clone()), vec![file_hash]),
+			Error::<Test>::NotQualified
+		);
+		let outsider = account("miner", 99, 0);
+		register_miner_in(&outsider, *b"US");
+		assert_ok!(FileBank::transfer_report(RuntimeOrigin::signed(outsider.clone()), vec![file_hash]));
+		let event = Sys::events().pop().expect("Expected at least one TransferReport to be found").event;
+		assert_eq!(mock::RuntimeEvent::from(Event::TransferReport { acc: outsider, failed_list: vec![file_hash] }), event);
+
+		transfer_report_all(file_hash);
+		let file = File::<Test>::get(&file_hash).unwrap();
+		assert_eq!(FileState::Calculate, file.stat);
+		assert_eq!(acc1, file.owner[0].user);
+		let bucket_name: BoundedVec<u8, NameStrLimit> = BUCKET_NAME.as_bytes().to_vec().try_into().unwrap();
+		assert!(Bucket::<Test>::get(&acc1, &bucket_name).unwrap().object_list.contains(&file_hash));
+		let file_slice_info = UserFileSliceInfo { file_hash, file_size: FileBank::cal_file_size(1) };
+		assert!(UserHoldFileList::<Test>::get(&acc1).contains(&file_slice_info));
+
+		assert_ok!(FileBank::calculate_end(RuntimeOrigin::root(), file_hash));
+		assert_eq!(FileState::Active, File::<Test>::get(&file_hash).unwrap().stat);
+		assert!(!DealMap::<Test>::contains_key(&file_hash));
+	});
 }
 
 #[test]
 fn upload_should_not_work_when_insufficient_storage() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let stash1 = mock::stash1();
-        let miner1 = mock::miner1();
-        let controller1 = mock::controller1();
-        let mut mfi = MockingFileBankInfo::default();
-        let space_gb = 20_u128;
-				let bucket_name = "cess-bucket".as_bytes().to_vec();
-        assert_ok!(register_miner(miner1));
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-        assert_ok!(add_power_for_miner(controller1, miner1));
-        assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-        mfi.file_size = 3 * 1024 * 1024 * 1024;  // large file
-
-				assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-				assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash, bucket_name.clone()));
-        assert_noop!(upload_file_alias(acc1.clone(), controller1.clone(), &mfi), Error::<Test>::InsufficientStorage);
-
-        if let Err(e) = upload_file_alias(acc1, controller1, &mfi) {
-            if let DispatchError::Module(m) = e {
-                assert_eq!("InsufficientStorage", m.message.unwrap());
-            }
-        }
-    })
+	new_test_ext().execute_with(|| {
+		let acc1 = account1();
+		register_miners();
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+
+		// 1 GiB holds 42 segments at 1.5 times redundancy.
+		assert_noop!(
+			upload_declaration_alias(&acc1, Hash([5u8; 64]), deal_info(43, 3)),
+			Error::<Test>::InsufficientAvailableSpace
+		);
+		assert_ok!(upload_declaration_alias(&acc1, Hash([5u8; 64]), deal_info(42, 3)));
+	})
 }
 
 #[test]
 fn delete_file_works() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let stash1 = mock::stash1();
-        let miner1 = mock::miner1();
-        let controller1 = mock::controller1();
-        let mfi = MockingFileBankInfo::default();
-				let bucket_name = "cess-bucket".as_bytes().to_vec();
-        assert_noop!(FileBank::delete_file(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), mfi.file_hash.clone()), Error::<Test>::FileNonExistent);
-
-        let space_gb = 20_u128;
-        assert_ok!(register_miner(miner1.clone()));
-        assert_ok!(Sminer::add_power(&miner1, 1_048_576 * 1024 * space_gb));
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-        // acc1 upload file
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-				assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-        assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash, bucket_name.clone()));
-        assert_ok!(add_power_for_miner(controller1, miner1));
-        assert_ok!(upload_file_alias(acc1, controller1, &mfi));
-        assert_noop!(FileBank::delete_file(RuntimeOrigin::signed(mock::account2()), mock::account2(), mfi.file_hash.clone()), Error::<Test>::NotOwner);
-        let ss_before = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-
-        assert_ok!(FileBank::delete_file(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), mfi.file_hash.clone()));
-
-        let ss_after = UserOwnedSpace::<Test>::try_get(acc1).unwrap();
-        assert!(!File::<Test>::contains_key(&mfi.file_hash));
-        assert_ne!(ss_before.remaining_space, ss_after.remaining_space);
-
-        let event = Sys::events().pop().expect("Expected at least one DeleteFile to be found").event;
-        assert_eq!(mock::RuntimeEvent::from(Event::DeleteFile { operator: acc1.clone(), owner: acc1.clone(), file_hash: mfi.file_hash }), event);
-    });
+	new_test_ext().execute_with(|| {
+		let acc1 = account1();
+		let acc2 = account2();
+		let file_hash = Hash([5u8; 64]);
+		assert_noop!(
+			FileBank::delete_file(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), vec![file_hash]),
+			Error::<Test>::NonExistent
+		);
+
+		register_miners();
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+		let avail_before = StorageHandler::get_user_avail_space(&acc1).unwrap();
+		upload_file(&acc1, file_hash);
+		assert_noop!(
+			FileBank::delete_file(RuntimeOrigin::signed(acc2), acc1.clone(), vec![file_hash]),
+			Error::<Test>::NoPermission
+		);
+
+		assert_ok!(FileBank::delete_file(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), vec![file_hash]));
+
+		assert!(!File::<Test>::contains_key(&file_hash));
+		assert!(UserHoldFileList::<Test>::get(&acc1).is_empty());
+		assert_eq!(avail_before, StorageHandler::get_user_avail_space(&acc1).unwrap());
+
+		let event = Sys::events().pop().expect("Expected at least one DeleteFile to be found").event;
+		assert_eq!(
+			mock::RuntimeEvent::from(Event::DeleteFile { operator: acc1.clone(), owner: acc1, file_hash_list: vec![file_hash] }),
+			event
+		);
+	});
 }
 
 #[test]
 fn upload_filler_work() {
-    new_test_ext().execute_with(|| {
-        let stash1 = mock::stash1();
-        let miner1 = mock::miner1();
-        let controller1 = mock::controller1();
-        assert_noop!(add_power_for_miner(controller1.clone(), miner1.clone()), Error::<Test>::ScheduleNonExistent);
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-        assert_noop!(add_power_for_miner(controller1.clone(), miner1.clone()), pallet_sminer::Error::<Test>::NotMiner);
-        assert_ok!(register_miner(miner1));
-        assert_ok!(add_power_for_miner(controller1.clone(), miner1.clone()));
-
-        let (power, _) = Sminer::get_power_and_space(miner1.clone()).unwrap();
-        assert_eq!(1_048_576 * 8 * 10, power);
-    });
+	new_test_ext().execute_with(|| {
+		let stash1 = stash1();
+		let miner1 = miner1();
+		let controller1 = controller1();
+		assert_noop!(add_filler_for_miner(&controller1, &miner1, 10), Error::<Test>::NotQualified);
+		register_miner_in(&miner1, *b"CN");
+		assert_noop!(add_filler_for_miner(&controller1, &miner1, 10), Error::<Test>::ScheduleNonExistent);
+		register_tee_worker(controller1.clone(), stash1);
+		assert_noop!(add_filler_for_miner(&controller1, &miner1, 11), Error::<Test>::LengthExceedsLimit);
+
+		assert_ok!(add_filler_for_miner(&controller1, &miner1, 10));
+
+		let (idle_space, _) = <Sminer as MinerControl<_, _>>::get_power(&miner1).unwrap();
+		assert_eq!(IDLE_SPACE + 1_048_576 * 8 * 10, idle_space);
+		assert!(FillerMap::<Test>::contains_key(&miner1, Hash([9u8; 64])));
+		assert_noop!(add_filler_for_miner(&controller1, &miner1, 1), Error::<Test>::FileExistent);
+	});
 }
 
 #[test]
 fn clear_invalid_file_work() {
-    new_test_ext().execute_with(|| {
-        let acc1 = mock::account1();
-        let stash1 = mock::stash1();
-        let miner1 = mock::miner1();
-        let controller1 = mock::controller1();
-        let mfi = MockingFileBankInfo::default();
-				let bucket_name = "cess-bucket".as_bytes().to_vec();
-
-        assert_ok!(register_miner(miner1.clone()));
-        assert_ok!(Sminer::add_power(&miner1 ,1_048_576 * 1024 * 20));
-        assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-
-				assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-        assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash, bucket_name));
-        assert_ok!(add_power_for_miner(controller1, miner1));
-        assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-        assert_ok!(upload_file_alias(acc1, controller1, &mfi));
-
-        let mut file_hash_list = InvalidFile::<Test>::get(miner1.clone());
-        assert_ok!(FileBank::clear_invalid_file(RuntimeOrigin::signed(miner1.clone()), file_hash_list[0]));
-        file_hash_list.remove(0);
-        assert_eq!(file_hash_list, InvalidFile::<Test>::get(miner1.clone()));
-    });
+	new_test_ext().execute_with(|| {
+		let miner1 = miner1();
+		let file_hash_list = vec![Hash([1u8; 64]), Hash([2u8; 64]), Hash([3u8; 64])];
+		InvalidFile::<Test>::insert(&miner1, BoundedVec::truncate_from(file_hash_list.clone()));
+
+		assert_ok!(FileBank::clear_invalid_file(RuntimeOrigin::signed(miner1.clone()), file_hash_list[0]));
+
+		assert_eq!(file_hash_list[1..].to_vec(), InvalidFile::<Test>::get(&miner1).to_vec());
+		let event = Sys::events().pop().expect("Expected at least one ClearInvalidFile to be found").event;
+		assert_eq!(mock::RuntimeEvent::from(Event::ClearInvalidFile { acc: miner1, file_hash: file_hash_list[0] }), event);
+	});
 }
 
 #[test]
 fn create_bucket_works() {
 	new_test_ext().execute_with(|| {
-		let acc1 = mock::account1();
-		let bucket_name = "cess-bucket".as_bytes().to_vec();
-		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = bucket_name.try_into().unwrap();
+		let acc1 = account1();
+		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = BUCKET_NAME.as_bytes().to_vec().try_into().unwrap();
+		assert_noop!(
+			FileBank::create_bucket(RuntimeOrigin::signed(account2()), acc1.clone(), bound_bucket_name.clone()),
+			Error::<Test>::NoPermission
+		);
+		assert_noop!(
+			FileBank::create_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), b"cs".to_vec().try_into().unwrap()),
+			Error::<Test>::LessMinLength
+		);
+
 		assert_ok!(FileBank::create_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), bound_bucket_name.clone()));
-		let bucket = Bucket::<Test>::get(&acc1, bound_bucket_name).unwrap();
+		let bucket = Bucket::<Test>::get(&acc1, bound_bucket_name.clone()).unwrap();
 		assert_eq!(bucket.authority.len(), 1);
 		assert_eq!(bucket.authority[0], acc1);
+		assert_eq!(vec![bound_bucket_name.clone()], UserBucketList::<Test>::get(&acc1).to_vec());
+
+		assert_noop!(
+			FileBank::create_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), bound_bucket_name),
+			Error::<Test>::Existed
+		);
 	});
 }
 
 #[test]
 fn delete_bucket_works() {
 	new_test_ext().execute_with(|| {
-		let acc1 = mock::account1();
-		let bucket_name = "cess-bucket".as_bytes().to_vec();
-		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = bucket_name.try_into().unwrap();
+		let acc1 = account1();
+		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = BUCKET_NAME.as_bytes().to_vec().try_into().unwrap();
+		assert_noop!(
+			FileBank::delete_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), bound_bucket_name.clone()),
+			Error::<Test>::NonExistent
+		);
 		assert_ok!(FileBank::create_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), bound_bucket_name.clone()));
 		assert!(<Bucket<Test>>::contains_key(&acc1, bound_bucket_name.clone()));
 
 		assert_ok!(FileBank::delete_bucket(RuntimeOrigin::signed(acc1.clone()), acc1.clone(), bound_bucket_name.clone()));
 		assert!(!<Bucket<Test>>::contains_key(&acc1, bound_bucket_name.clone()));
+		assert!(UserBucketList::<Test>::get(&acc1).is_empty());
 	});
 }
 
@@ -414,41 +292,31 @@ fn transfer_ownership_works() {
 	new_test_ext().execute_with(|| {
 		let acc1 = account1();
 		let acc2 = account2();
-		let mfi = MockingFileBankInfo::default();
-		let bucket_name = "cess-bucket".as_bytes().to_vec();
-		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = bucket_name.clone().try_into().unwrap();
-
-		let stash1 = mock::stash1();
-		let miner1 = mock::miner1();
-		let controller1 = mock::controller1();
-
-		assert_ok!(register_miner(miner1.clone()));
-		assert_ok!(Sminer::add_power(&miner1 ,1_048_576 * 1024 * 20));
-		assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-		assert_ok!(add_power_for_miner(controller1, miner1));
-
-		assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-		assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc2), 2));
-
-		assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-		assert_ok!(create_new_bucket(acc2.clone(), bucket_name.clone()));
-		assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash.clone(), bucket_name.clone()));
-		assert_ok!(upload_file_alias(acc1, controller1, &mfi));
-
-		let file = <File<Test>>::get(&mfi.file_hash).unwrap();
-		assert_eq!(file.user_brief_list[0].user, acc1.clone());
-
-		let target_brief = UserBrief::<Test> {
-			user: acc2.clone(),
-			file_name: "test-file2".as_bytes().to_vec().try_into().unwrap(),
-			bucket_name: bound_bucket_name.clone(),
-		};
-
-		assert_ok!(FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), bound_bucket_name, target_brief, mfi.file_hash.clone()));
-
-		let file = <File<Test>>::get(&mfi.file_hash).unwrap();
-		assert_eq!(file.user_brief_list[0].user, acc2.clone());
-		assert!(!FileBank::check_is_file_owner(&acc1, &mfi.file_hash));
+		let file_hash = Hash([5u8; 64]);
+		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = BUCKET_NAME.as_bytes().to_vec().try_into().unwrap();
+
+		register_miners();
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc2.clone()), 1));
+		assert_ok!(FileBank::create_bucket(RuntimeOrigin::signed(acc2.clone()), acc2.clone(), bound_bucket_name.clone()));
+		upload_file(&acc1, file_hash);
+
+		let file = <File<Test>>::get(&file_hash).unwrap();
+		assert_eq!(file.owner[0].user, acc1.clone());
+
+		assert_ok!(FileBank::ownership_transfer(
+			RuntimeOrigin::signed(acc1.clone()),
+			user_brief(&acc2, "test-file2", BUCKET_NAME),
+			file_hash
+		));
+
+		let file = <File<Test>>::get(&file_hash).unwrap();
+		assert_eq!(1, file.owner.len());
+		assert_eq!(file.owner[0].user, acc2.clone());
+		assert!(!FileBank::check_is_file_owner(&acc1, &file_hash));
+		assert!(Bucket::<Test>::get(&acc2, &bound_bucket_name).unwrap().object_list.contains(&file_hash));
+		assert!(!Bucket::<Test>::get(&acc1, &bound_bucket_name).unwrap().object_list.contains(&file_hash));
+		assert!(UserHoldFileList::<Test>::get(&acc1).is_empty());
 	})
 }
 
@@ -457,45 +325,96 @@ fn transfer_ownership_exception() {
 	new_test_ext().execute_with(|| {
 		let acc1 = account1();
 		let acc2 = account2();
-		let mfi = MockingFileBankInfo::default();
-		let bucket_name = "cess-bucket".as_bytes().to_vec();
-		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = bucket_name.clone().try_into().unwrap();
-
-		let stash1 = mock::stash1();
-		let miner1 = mock::miner1();
-		let controller1 = mock::controller1();
-
-		assert_ok!(register_miner(miner1.clone()));
-		assert_ok!(Sminer::add_power(&miner1 ,1_048_576 * 1024 * 20));
-		assert_ok!(register_scheduler(stash1.clone(), controller1.clone()));
-		assert_ok!(add_power_for_miner(controller1, miner1));
-
-		assert_ok!(FileBank::buy_space(RuntimeOrigin::signed(acc1), 2));
-
-		assert_ok!(create_new_bucket(acc1.clone(), bucket_name.clone()));
-		assert_ok!(upload_declaration_alias(acc1, "cess-book".as_bytes().to_vec(), mfi.file_hash.clone(), bucket_name.clone()));
-		assert_ok!(upload_file_alias(acc1, controller1, &mfi));
-
-		let target_brief = UserBrief::<Test> {
-			user: acc2.clone(),
-			file_name: "test-file2".as_bytes().to_vec().try_into().unwrap(),
-			bucket_name: bound_bucket_name.clone(),
-		};
-
-		assert_noop!(FileBank::ownership_transfer(RuntimeOrigin::signed(acc2.clone()), bound_bucket_name.clone(), target_brief.clone(), mfi.file_hash.clone()), Error::<Test>::NotOwner);
-		let file_hash = Hash([8u8; 64]);
-		assert_noop!(FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), bound_bucket_name.clone(), target_brief.clone(), file_hash.clone()), Error::<Test>::FileNonExistent);
-		assert_noop!(FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), bound_bucket_name.clone(), target_brief.clone(), mfi.file_hash.clone()), Error::<Test>::NonExistent);
-
-		assert_ok!(create_new_bucket(acc2.clone(), bucket_name.clone()));
-		assert_noop!(FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), bound_bucket_name.clone(), target_brief.clone(), mfi.file_hash.clone()), Error::<Test>::NotPurchasedSpace);
+		let file_hash = Hash([5u8; 64]);
+		let target_brief = user_brief(&acc2, "test-file2", BUCKET_NAME);
+		let bound_bucket_name: BoundedVec<u8, NameStrLimit> = BUCKET_NAME.as_bytes().to_vec().try_into().unwrap();
+
+		register_miners();
+		assert_ok!(StorageHandler::buy_space(RuntimeOrigin::signed(acc1.clone()), 1));
+		assert_ok!(upload_declaration_alias(&acc1, file_hash, deal_info(1, 3)));
+		transfer_report_all(file_hash);
+
+		// The tags of the file are still being calculated.
+		assert_noop!(
+			FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), target_brief.clone(), file_hash),
+			Error::<Test>::Unprepared
+		);
+		assert_ok!(FileBank::calculate_end(RuntimeOrigin::root(), file_hash));
+
+		assert_noop!(
+			FileBank::ownership_transfer(RuntimeOrigin::signed(acc2.clone()), target_brief.clone(), file_hash),
+			Error::<Test>::NotOwner
+		);
+		assert_noop!(
+			FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), target_brief.clone(), Hash([8u8; 64])),
+			Error::<Test>::FileNonExistent
+		);
+		assert_noop!(
+			FileBank::ownership_transfer(
+				RuntimeOrigin::signed(acc1.clone()),
+				user_brief(&acc1, "test-file2", BUCKET_NAME),
+				file_hash
+			),
+			Error::<Test>::IsOwned
+		);
+		assert_noop!(
+			FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), target_brief.clone(), file_hash),
+			Error::<Test>::NonExistent
+		);
+
+		assert_ok!(FileBank::create_bucket(RuntimeOrigin::signed(acc2.clone()), acc2.clone(), bound_bucket_name));
+		assert_noop!(
+			FileBank::ownership_transfer(RuntimeOrigin::signed(acc1.clone()), target_brief, file_hash),
+			pallet_storage_handler::Error::<Test>::NotPurchasedSpace
+		);
 	})
 }
-// #[test]
-// fn update_price_works() {
-//     new_test_ext().execute_with(|| {
-//         let acc1 = mock::account1();
-//         assert_ok!(FileBank::update_price(Origin::signed(acc1), Vec::from("1000")));
-//         assert_eq!(1000 / 3, UnitPrice::<Test>::try_get().unwrap());
-//     });
-// }
+
+#[test]
+fn random_assign_miner_spreads_replicas_across_regions() {
+	new_test_ext().execute_with(|| {
+		let cn: Vec<AccountId> = (0 .. 2u32).map(|i| account("cn", i, 0)).collect();
+		let us: Vec<AccountId> = (0 .. 2u32).map(|i| account("us", i, 0)).collect();
+		for miner in cn.iter() {
+			register_miner_in(miner, *b"CN");
+		}
+		for miner in us.iter() {
+			register_miner_in(miner, *b"US");
+		}
+
+		let deal_hash = Hash([1u8; 64]);
+		let task_list = FileBank::random_assign_miner(&deal_hash, &Default::default()).unwrap();
+		let regions: Vec<RegionCode> = task_list
+			.iter()
+			.map(|task| Sminer::get_miner_region(&task.miner).unwrap())
+			.collect();
+
+		// One replica in each region first, the third can only come from a region already used.
+		assert_eq!(3, regions.len());
+		assert_ne!(regions[0], regions[1]);
+		assert!(regions[2] == regions[0] || regions[2] == regions[1]);
+
+		let mut miners: Vec<AccountId> = task_list.iter().map(|task| task.miner.clone()).collect();
+		miners.sort();
+		miners.dedup();
+		assert_eq!(3, miners.len());
+	});
+}
+
+#[test]
+fn random_assign_miner_falls_back_to_a_single_region() {
+	new_test_ext().execute_with(|| {
+		let miners: Vec<AccountId> = (0 .. 3u32).map(|i| account("cn", i, 0)).collect();
+		for miner in miners.iter() {
+			register_miner_in(miner, *b"CN");
+		}
+
+		let deal_hash = Hash([2u8; 64]);
+		let task_list = FileBank::random_assign_miner(&deal_hash, &Default::default()).unwrap();
+		let mut assigned: Vec<AccountId> = task_list.iter().map(|task| task.miner.clone()).collect();
+		assigned.sort();
+		let mut expected = miners.clone();
+		expected.sort();
+		assert_eq!(expected, assigned);
+	});
+}
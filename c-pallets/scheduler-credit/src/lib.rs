@@ -200,14 +200,7 @@ impl<T: Config> Pallet<T> {
 				if let Some(stash_account_id) =
 					T::StashAccountFinder::find_stash_account_id(&ctrl_account_id)
 				{
-					let mut credit_score = 0_u32;
-					for (index, weight) in PERIOD_WEIGHT.into_iter().enumerate() {
-						if last_period >= index as u32 {
-							let credit_value = HistoryCreditValues::<T>::try_get(&last_period.saturating_sub(index as u32), &ctrl_account_id)
-								.unwrap_or(0);
-							credit_score += weight * credit_value;
-						}
-					}
+					let credit_score = Self::figure_credit_score(&ctrl_account_id, last_period);
 					debug!(
 						target: LOG_TARGET,
 						"scheduler stash account: {:?}, credit value: {}",
@@ -225,6 +218,19 @@ impl<T: Config> Pallet<T> {
 		});
 		result
 	}
+
+	/// Weighted credit values of `scheduler_id` over the periods up to `last_period`.
+	pub fn figure_credit_score(scheduler_id: &T::AccountId, last_period: u32) -> CreditScore {
+		let mut credit_score = 0_u32;
+		for (index, weight) in PERIOD_WEIGHT.into_iter().enumerate() {
+			if last_period >= index as u32 {
+				let credit_value = HistoryCreditValues::<T>::try_get(&last_period.saturating_sub(index as u32), scheduler_id)
+					.unwrap_or(0);
+				credit_score += weight * credit_value;
+			}
+		}
+		credit_score
+	}
 }
 
 impl<T: Config> SchedulerCreditCounter<T::AccountId> for Pallet<T> {
@@ -237,6 +243,15 @@ impl<T: Config> SchedulerCreditCounter<T::AccountId> for Pallet<T> {
 		Pallet::<T>::record_punishment(scheduler_id)?;
 		Ok(())
 	}
+
+	fn credit_score(scheduler_id: &T::AccountId) -> u32 {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let period: u32 = (now / T::PeriodDuration::get()).saturated_into();
+		if period == 0 {
+			return 0;
+		}
+		Pallet::<T>::figure_credit_score(scheduler_id, period.saturating_sub(1))
+	}
 }
 
 impl<T: Config> ValidatorCredits<T::AccountId> for Pallet<T> {
@@ -0,0 +1,17 @@
+//! Runtime API definition for the sminer pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+sp_api::decl_runtime_apis! {
+	pub trait SminerApi<AccountId, Balance, BlockNumber> where
+		AccountId: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// Future reward unlocks of a miner as `(block, amount)` pairs in block order.
+		fn reward_unlocks(miner: AccountId) -> Vec<(BlockNumber, Balance)>;
+	}
+}
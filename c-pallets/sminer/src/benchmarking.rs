@@ -78,13 +78,11 @@ benchmarks! {
     exit_miner {
         let miner = add_miner::<T>("miner1");
         let miner_info = <MinerItems<T>>::get(&miner).unwrap();
-        let state = "positive".as_bytes().to_vec();
-        assert_eq!(state, miner_info.state.to_vec());
+        assert_eq!(MinerState::Positive, miner_info.state);
     }: _(RawOrigin::Signed(miner.clone()))
     verify {
         let miner_info = <MinerItems<T>>::get(&miner).unwrap();
-        let state = "exit".as_bytes().to_vec();
-        assert_eq!(state, miner_info.state.to_vec());
+        assert_eq!(MinerState::Exit, miner_info.state);
     }
 
     withdraw {
@@ -31,7 +31,7 @@ use frame_support::{
 		schedule::{Anon as ScheduleAnon, Named as ScheduleNamed},
 		Currency,
 		ExistenceRequirement::AllowDeath,
-		Get, Imbalance, OnUnbalanced, ReservableCurrency,
+		Get, Imbalance, OnUnbalanced, ReservableCurrency, StorageVersion,
 	},
 };
 use cp_cess_common::*;
@@ -43,6 +43,9 @@ mod benchmarking;
 
 mod types;
 use types::*;
+pub use types::MinerState;
+
+pub mod migrations;
 
 mod constants;
 use constants::*;
@@ -51,18 +54,19 @@ use codec::{Decode, Encode};
 use frame_support::{
 	ensure,
 	dispatch::{DispatchResult, Dispatchable},
-	pallet_prelude::DispatchError,
+	pallet_prelude::{DispatchError, Weight},
 	PalletId,
 };
 use frame_system::{self as system};
 pub use pallet::*;
 use scale_info::TypeInfo;
 use sp_runtime::{
-	traits::{AccountIdConversion, CheckedAdd, CheckedSub, SaturatedConversion},
+	traits::{
+		AccountIdConversion, AtLeast32BitUnsigned, CheckedAdd, CheckedSub, SaturatedConversion, Saturating,
+	},
 	RuntimeDebug, Perbill,
 };
-use sp_std::{convert::TryInto, prelude::*};
-use sp_core::ConstU32;
+use sp_std::{collections::btree_map::BTreeMap, convert::TryInto, prelude::*};
 
 pub mod weights;
 pub use weights::WeightInfo;
@@ -74,6 +78,14 @@ type NegativeImbalanceOf<T> = <<T as pallet::Config>::Currency as Currency<
 	<T as frame_system::Config>::AccountId,
 >>::NegativeImbalance;
 type BlockNumberOf<T> = <T as frame_system::Config>::BlockNumber;
+type UnlockChunksOf<T> =
+	BoundedVec<UnlockChunk<BalanceOf<T>, BlockNumberOf<T>>, <T as pallet::Config>::MaxUnlockingChunks>;
+type DelegatorUnlockChunksOf<T> = BoundedVec<
+	DelegatorUnlockChunk<AccountOf<T>, BalanceOf<T>, BlockNumberOf<T>>,
+	<T as pallet::Config>::MaxUnlockingChunks,
+>;
+
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -108,6 +120,36 @@ pub mod pallet {
 		type MaxAward: Get<u128>;
 		#[pallet::constant]
 		type ChallengeMinerMax: Get<u32>;
+		/// Maximum number of automatic reward payouts made in one block.
+		#[pallet::constant]
+		type PayoutBatchSize: Get<u32>;
+		/// Share of each round reward that is available right away.
+		#[pallet::constant]
+		type RewardImmediateShare: Get<Perbill>;
+		/// Number of tranches the rest of a round reward is released in.
+		#[pallet::constant]
+		type RewardReleaseNumber: Get<u32>;
+		/// Blocks between two tranches of a round reward.
+		#[pallet::constant]
+		type RewardReleaseInterval: Get<BlockNumberOf<Self>>;
+		/// Maximum number of vesting reward orders kept per miner, above `RewardReleaseNumber`.
+		#[pallet::constant]
+		type MaxRewardOrders: Get<u32>;
+		/// Blocks unbonded collateral stays reserved and slashable before it can be withdrawn.
+		#[pallet::constant]
+		type UnbondingPeriod: Get<BlockNumberOf<Self>>;
+		/// Maximum number of unbonding requests a miner can have in flight.
+		#[pallet::constant]
+		type MaxUnlockingChunks: Get<u32>;
+		/// Maximum number of delegators staking toward one miner.
+		#[pallet::constant]
+		type MaxDelegators: Get<u32>;
+		/// Highest share of the delegators' rewards a miner can keep.
+		#[pallet::constant]
+		type MaxCommission: Get<Perbill>;
+		/// Maximum number of network addresses a miner can publish.
+		#[pallet::constant]
+		type MaxMultiaddrs: Get<u32>;
 		/// The Scheduler.
 		type SScheduler: ScheduleNamed<Self::BlockNumber, Self::SProposal, Self::SPalletsOrigin>;
 
@@ -163,8 +205,75 @@ pub mod pallet {
 		},
 		Receive {
 			acc: AccountOf<T>,
+			beneficiary: AccountOf<T>,
 			reward: BalanceOf<T>,
-		}
+		},
+		AutoPayoutSet {
+			acc: AccountOf<T>,
+			enabled: bool,
+		},
+		MinerStateChanged {
+			acc: AccountOf<T>,
+			from: MinerState,
+			to: MinerState,
+		},
+		/// Part of a miner's reward went to repaying its debt.
+		DebtRepaid {
+			acc: AccountOf<T>,
+			amount: BalanceOf<T>,
+			remaining: BalanceOf<T>,
+		},
+		/// Part of a miner's reward went back into its collateral.
+		CollateralToppedUp {
+			acc: AccountOf<T>,
+			amount: BalanceOf<T>,
+			collaterals: BalanceOf<T>,
+		},
+		/// Collateral above the limit started unbonding.
+		CollateralUnbonding {
+			acc: AccountOf<T>,
+			amount: BalanceOf<T>,
+			unlock_at: BlockNumberOf<T>,
+		},
+		/// Unbonded collateral was released to the miner.
+		UnbondedWithdrawn {
+			acc: AccountOf<T>,
+			amount: BalanceOf<T>,
+		},
+		CommissionSet {
+			acc: AccountOf<T>,
+			commission: Perbill,
+		},
+		/// A third party staked collateral toward a miner.
+		Delegated {
+			delegator: AccountOf<T>,
+			miner: AccountOf<T>,
+			amount: BalanceOf<T>,
+		},
+		/// A delegator started unbonding collateral from a miner.
+		Undelegated {
+			delegator: AccountOf<T>,
+			miner: AccountOf<T>,
+			amount: BalanceOf<T>,
+			unlock_at: BlockNumberOf<T>,
+		},
+		DelegatorRewardClaimed {
+			delegator: AccountOf<T>,
+			reward: BalanceOf<T>,
+		},
+		MetadataUpdated {
+			acc: AccountOf<T>,
+			region: RegionCode,
+			declared_space: u128,
+		},
+		ControllerSet {
+			stash: AccountOf<T>,
+			controller: AccountOf<T>,
+		},
+		WorkerSet {
+			stash: AccountOf<T>,
+			worker: AccountOf<T>,
+		},
 	}
 
 	/// Error for the sminer pallet.
@@ -199,6 +308,36 @@ pub mod pallet {
 		Unexpected,
 
 		NoReward,
+		/// The miner's current state does not allow moving to the requested one.
+		InvalidStateTransition,
+		/// The collateral left after unbonding would fall below the limit for the miner's space.
+		CollateralBelowLimit,
+		/// Debt has to be repaid before collateral can be unbonded.
+		OutstandingDebt,
+		/// Too many unbonding requests are in flight.
+		TooManyUnlockChunks,
+		/// No unbonded collateral has finished its unbonding period.
+		NothingToWithdraw,
+		/// Delegated collateral can only be unbonded by its delegator.
+		NotEnoughOwnCollateral,
+		/// The account has not delegated that much to the miner.
+		InsufficientDelegation,
+		/// The miner already has the maximum number of delegators.
+		TooManyDelegators,
+		/// The commission is above `MaxCommission`.
+		CommissionTooHigh,
+		/// The miner has `MaxRewardOrders` orders vesting, which the configuration should rule out.
+		TooManyRewardOrders,
+		/// The region is not an upper case two letter country code.
+		InvalidRegion,
+		/// The declared space is less than the space the miner already holds.
+		DeclaredSpaceTooSmall,
+		/// The reserved collateral does not cover the power the miner would have.
+		InsufficientCollateral,
+		/// The space would exceed the capacity the miner declared.
+		DeclaredSpaceExceeded,
+		/// The account is already a miner, controller or worker key.
+		KeyInUse,
 	}
 
 	#[pallet::storage]
@@ -213,14 +352,23 @@ pub mod pallet {
 		_,
 		Blake2_128Concat,
 		T::AccountId,
-		MinerInfo<T::AccountId, BalanceOf<T>, BoundedVec<u8, T::ItemLimit>>,
+		MinerInfo<T::AccountId, BalanceOf<T>>,
 	>;
 
-	/// Store all miner information
+	/// Position of each registered miner in the registry.
 	#[pallet::storage]
-	#[pallet::getter(fn miner_info)]
-	pub(super) type AllMiner<T: Config> =
-		StorageValue<_, BoundedVec<AccountOf<T>, T::ItemLimit>, ValueQuery>;
+	#[pallet::getter(fn miner_index)]
+	pub(super) type MinerIndex<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, u32>;
+
+	/// Registered miners by position, dense over `0..MinerRegistryLen`.
+	#[pallet::storage]
+	#[pallet::getter(fn miner_by_index)]
+	pub(super) type MinerByIndex<T: Config> = StorageMap<_, Twox64Concat, u32, AccountOf<T>>;
+
+	/// Number of miners in the registry, at most `ItemLimit`.
+	#[pallet::storage]
+	#[pallet::getter(fn miner_registry_len)]
+	pub(super) type MinerRegistryLen<T: Config> = StorageValue<_, u32, ValueQuery>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn reward_map)]
@@ -241,10 +389,116 @@ pub mod pallet {
 	#[pallet::getter(fn currency_reward)]
 	pub(super) type CurrencyReward<T: Config> = StorageValue<_, BalanceOf<T>, ValueQuery>;
 
+	/// Miners that opted in to have their rewards pushed to the beneficiary.
+	#[pallet::storage]
+	#[pallet::getter(fn auto_payout)]
+	pub(super) type AutoPayout<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, bool, ValueQuery>;
+
+	/// Opted-in miners that were rewarded in the last challenge round and are waiting to be paid.
+	#[pallet::storage]
+	#[pallet::getter(fn pending_payout)]
+	pub(super) type PendingPayout<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, ()>;
+
+	/// Collateral delegated to each miner. It is included in `MinerInfo.collaterals`.
+	#[pallet::storage]
+	#[pallet::getter(fn delegators)]
+	pub(super) type Delegators<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AccountOf<T>,
+		BoundedVec<Delegation<AccountOf<T>, BalanceOf<T>>, T::MaxDelegators>,
+		ValueQuery,
+	>;
+
+	/// Region, addresses and committed capacity declared by each miner.
+	#[pallet::storage]
+	#[pallet::getter(fn miner_metadata)]
+	pub(super) type MinerMeta<T: Config> =
+		StorageMap<_, Blake2_128Concat, AccountOf<T>, MinerMetadata<T::MaxMultiaddrs>>;
+
+	/// Controller key of each miner stash, when it differs from the stash.
+	#[pallet::storage]
+	#[pallet::getter(fn controller)]
+	pub(super) type Controller<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, AccountOf<T>>;
+
+	/// Miner stash of each controller key.
+	#[pallet::storage]
+	#[pallet::getter(fn controller_stash)]
+	pub(super) type ControllerStash<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, AccountOf<T>>;
+
+	/// Worker key of each miner stash, when it differs from the stash.
+	#[pallet::storage]
+	#[pallet::getter(fn worker)]
+	pub(super) type Worker<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, AccountOf<T>>;
+
+	/// Miner stash of each worker key.
+	#[pallet::storage]
+	#[pallet::getter(fn worker_stash)]
+	pub(super) type WorkerStash<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, AccountOf<T>>;
+
+	/// Share of the delegators' rewards a miner keeps.
+	#[pallet::storage]
+	#[pallet::getter(fn commission)]
+	pub(super) type Commission<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, Perbill, ValueQuery>;
+
+	/// Rewards delegators can claim.
+	#[pallet::storage]
+	#[pallet::getter(fn delegator_reward)]
+	pub(super) type DelegatorReward<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, BalanceOf<T>, ValueQuery>;
+
+	/// Delegated collateral being unbonded, per delegator.
+	#[pallet::storage]
+	#[pallet::getter(fn delegator_unbonding)]
+	pub(super) type DelegatorUnbonding<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AccountOf<T>,
+		DelegatorUnlockChunksOf<T>,
+		ValueQuery,
+	>;
+
+	/// Delegators with collateral unbonding from each miner, so that it can be slashed for earlier faults.
+	#[pallet::storage]
+	#[pallet::getter(fn unbonding_delegators)]
+	pub(super) type UnbondingDelegators<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AccountOf<T>,
+		BoundedVec<AccountOf<T>, T::MaxDelegators>,
+		ValueQuery,
+	>;
+
+	/// Collateral each miner is unbonding, oldest request first.
+	#[pallet::storage]
+	#[pallet::getter(fn unbonding)]
+	pub(super) type Unbonding<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		AccountOf<T>,
+		UnlockChunksOf<T>,
+		ValueQuery,
+	>;
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberOf<T>> for Pallet<T> {
+		fn on_initialize(_now: BlockNumberOf<T>) -> Weight {
+			Self::auto_payout_batch()
+		}
+
+		fn integrity_test() {
+			// one order per release interval, plus the one of the current interval
+			assert!(
+				T::MaxRewardOrders::get() > T::RewardReleaseNumber::get(),
+				"MaxRewardOrders must exceed RewardReleaseNumber",
+			);
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		/// Staking and register for storage miner.
@@ -266,28 +520,25 @@ pub mod pallet {
 		) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
 			ensure!(!(<MinerItems<T>>::contains_key(&sender)), Error::<T>::AlreadyRegistered);
+			ensure!(!Self::is_bound_key(&sender), Error::<T>::KeyInUse);
+			ensure!(staking_val >= Self::check_collateral_limit(0)?, Error::<T>::InsufficientCollateral);
 			T::Currency::reserve(&sender, staking_val.clone())?;
 
 			<MinerItems<T>>::insert(
 				&sender,
-				MinerInfo::<T::AccountId, BalanceOf<T>, BoundedVec<u8, T::ItemLimit>> {
+				MinerInfo::<T::AccountId, BalanceOf<T>> {
 					beneficiary: beneficiary.clone(),
 					peer_id: peer_id,
 					collaterals: staking_val.clone(),
 					debt: BalanceOf::<T>::zero(),
-					state: Self::vec_to_bound::<u8>(STATE_POSITIVE.as_bytes().to_vec())?,
+					state: MinerState::Positive,
 					idle_space: u128::MIN,
 					service_space: u128::MIN,
 					lock_space: u128::MIN,
 				},
 			);
 
-			AllMiner::<T>::try_mutate(|all_miner| -> DispatchResult {
-				all_miner
-					.try_push(sender.clone())
-					.map_err(|_e| Error::<T>::StorageLimitReached)?;
-				Ok(())
-			})?;
+			Self::registry_insert(&sender)?;
 
 			RewardMap::<T>::insert(
 				&sender,
@@ -340,11 +591,11 @@ pub mod pallet {
 
 				balance = miner_info.collaterals;
 
-				if miner_info.state == STATE_FROZEN.as_bytes().to_vec() {
+				if miner_info.state == MinerState::Frozen {
 					let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
 					let limit = Self::check_collateral_limit(power)?;
 					if miner_info.collaterals >= limit {
-						miner_info.state = Self::vec_to_bound(STATE_POSITIVE.as_bytes().to_vec())?;
+						Self::transition_state(&sender, miner_info, MinerState::Positive)?;
 					}
 				}
 
@@ -368,8 +619,8 @@ pub mod pallet {
 			origin: OriginFor<T>,
 			beneficiary: AccountOf<T>,
 		) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
-			ensure!(MinerItems::<T>::contains_key(&sender), Error::<T>::NotMiner);
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
 
 			<MinerItems<T>>::try_mutate(&sender, |miner_info_opt| -> DispatchResult {
 				let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::ConversionError)?;
@@ -389,8 +640,8 @@ pub mod pallet {
 		#[transactional]
 		#[pallet::weight(<T as pallet::Config>::WeightInfo::update_ip())]
 		pub fn update_peer_id(origin: OriginFor<T>, peer_id: PeerId) -> DispatchResult {
-			let sender = ensure_signed(origin)?;
-			ensure!(MinerItems::<T>::contains_key(&sender), Error::<T>::NotMiner);
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
 
 			let old = <MinerItems<T>>::try_mutate(&sender, |miner_info_opt| -> Result<PeerId, DispatchError> {
 				let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::ConversionError)?;
@@ -409,35 +660,329 @@ pub mod pallet {
 		pub fn receive_reward(
 			origin: OriginFor<T>,
 		) -> DispatchResult {
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
+
+			Self::pay_reward(&sender)?;
+
+			Ok(())
+		}
+
+		/// Opt in or out of having rewards paid to the beneficiary automatically
+		/// at the end of each challenge round.
+		///
+		/// Parameters:
+		/// - `enabled`: Whether rewards are paid out automatically.
+		#[pallet::call_index(16)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_auto_payout())]
+		pub fn set_auto_payout(origin: OriginFor<T>, enabled: bool) -> DispatchResult {
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
+
+			if enabled {
+				<AutoPayout<T>>::insert(&sender, true);
+			} else {
+				<AutoPayout<T>>::remove(&sender);
+				<PendingPayout<T>>::remove(&sender);
+			}
+
+			Self::deposit_event(Event::<T>::AutoPayoutSet { acc: sender, enabled });
+			Ok(())
+		}
+
+		/// Start unbonding collateral above the limit for the miner's space.
+		///
+		/// The amount stays reserved for `UnbondingPeriod` blocks and can still be slashed
+		/// for faults committed before this call.
+		///
+		/// Parameters:
+		/// - `amount`: Collateral to unbond.
+		#[pallet::call_index(17)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::unbond_collateral(T::MaxDelegators::get(), T::MaxUnlockingChunks::get()))]
+		pub fn unbond_collateral(
+			origin: OriginFor<T>,
+			#[pallet::compact] amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			let unlock_at = now.saturating_add(T::UnbondingPeriod::get());
+
+			<MinerItems<T>>::try_mutate(&sender, |miner_info_opt| -> DispatchResult {
+				let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
+				ensure!(miner_info.state == MinerState::Positive, Error::<T>::NotpositiveState);
+				ensure!(miner_info.debt.is_zero(), Error::<T>::OutstandingDebt);
+
+				let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
+				let limit = Self::check_collateral_limit(power)?;
+				let remaining = miner_info.collaterals.checked_sub(&amount).ok_or(Error::<T>::CollateralBelowLimit)?;
+				ensure!(remaining >= limit, Error::<T>::CollateralBelowLimit);
+				ensure!(remaining >= Self::delegated_total(&sender), Error::<T>::NotEnoughOwnCollateral);
+				miner_info.collaterals = remaining;
+
+				<Unbonding<T>>::try_mutate(&sender, |chunks| {
+					chunks
+						.try_push(UnlockChunk { value: amount, requested_at: now, unlock_at })
+						.map_err(|_| Error::<T>::TooManyUnlockChunks)
+				})?;
+
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::<T>::CollateralUnbonding { acc: sender, amount, unlock_at });
+			Ok(())
+		}
+
+		/// Release all unbonded collateral whose unbonding period has passed.
+		///
+		/// Covers both miner and delegator unbonding, and is also usable after the miner has exited.
+		#[pallet::call_index(18)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::withdraw_unbonded(T::MaxUnlockingChunks::get()))]
+		pub fn withdraw_unbonded(origin: OriginFor<T>) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+
+			let amount = Self::drain_unlocked(&sender, now)
+				.saturating_add(Self::drain_delegator_unlocked(&sender, now));
+			ensure!(!amount.is_zero(), Error::<T>::NothingToWithdraw);
+
+			T::Currency::unreserve(&sender, amount);
+
+			Self::deposit_event(Event::<T>::UnbondedWithdrawn { acc: sender, amount });
+			Ok(())
+		}
 
-			if let Ok(miner) = <MinerItems<T>>::try_get(&sender) {
+		/// Set the share of the delegators' rewards the miner keeps.
+		///
+		/// Parameters:
+		/// - `commission`: The commission rate, at most `MaxCommission`.
+		#[pallet::call_index(19)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_commission())]
+		pub fn set_commission(origin: OriginFor<T>, commission: Perbill) -> DispatchResult {
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
+			ensure!(commission <= T::MaxCommission::get(), Error::<T>::CommissionTooHigh);
+
+			<Commission<T>>::insert(&sender, commission);
+
+			Self::deposit_event(Event::<T>::CommissionSet { acc: sender, commission });
+			Ok(())
+		}
+
+		/// Stake collateral toward a miner.
+		///
+		/// Parameters:
+		/// - `miner`: The miner to delegate to.
+		/// - `amount`: Collateral to reserve from the signer.
+		#[pallet::call_index(20)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::delegate(T::MaxDelegators::get()))]
+		pub fn delegate(
+			origin: OriginFor<T>,
+			miner: AccountOf<T>,
+			#[pallet::compact] amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+
+			<MinerItems<T>>::try_mutate(&miner, |miner_info_opt| -> DispatchResult {
+				let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
 				ensure!(
-					miner.state == STATE_POSITIVE.as_bytes().to_vec(),
+					matches!(miner_info.state, MinerState::Positive | MinerState::Frozen),
 					Error::<T>::NotpositiveState
 				);
 
-				<RewardMap<T>>::try_mutate(&sender, |opt_reward| -> DispatchResult {
-					let reward = opt_reward.as_mut().ok_or(Error::<T>::Unexpected)?;
-					ensure!(reward.currently_available_reward != 0u32.saturated_into(), Error::<T>::NoReward);
+				T::Currency::reserve(&sender, amount)?;
+				<Delegators<T>>::try_mutate(&miner, |delegators| -> DispatchResult {
+					match delegators.iter_mut().find(|d| d.delegator == sender) {
+						Some(delegation) => {
+							delegation.amount = delegation.amount.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+						},
+						None => {
+							delegators
+								.try_push(Delegation { delegator: sender.clone(), amount })
+								.map_err(|_| Error::<T>::TooManyDelegators)?;
+						},
+					}
+					Ok(())
+				})?;
+				miner_info.collaterals = miner_info.collaterals.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
 
-					let reward_pot = T::PalletId::get().into_account_truncating();
-					<T as pallet::Config>::Currency::transfer(&reward_pot, &sender, reward.currently_available_reward.clone(), AllowDeath)?;
+				if miner_info.state == MinerState::Frozen && miner_info.debt.is_zero() {
+					let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
+					if miner_info.collaterals >= Self::check_collateral_limit(power)? {
+						Self::transition_state(&miner, miner_info, MinerState::Positive)?;
+					}
+				}
 
-					reward.reward_issued = reward.reward_issued
-						.checked_add(&reward.currently_available_reward).ok_or(Error::<T>::Overflow)?;
+				Ok(())
+			})?;
 
-					Self::deposit_event(Event::<T>::Receive {
-						acc: sender.clone(),
-						reward: reward.currently_available_reward,
-					});
+			Self::deposit_event(Event::<T>::Delegated { delegator: sender, miner, amount });
+			Ok(())
+		}
 
-					reward.currently_available_reward = 0u32.saturated_into();
+		/// Start unbonding collateral delegated to a miner.
+		///
+		/// The miner is frozen if its collateral falls below the limit for its space.
+		///
+		/// Parameters:
+		/// - `miner`: The miner delegated to.
+		/// - `amount`: Collateral to unbond.
+		#[pallet::call_index(21)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::undelegate(T::MaxDelegators::get(), T::MaxUnlockingChunks::get()))]
+		pub fn undelegate(
+			origin: OriginFor<T>,
+			miner: AccountOf<T>,
+			#[pallet::compact] amount: BalanceOf<T>,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			let unlock_at = now.saturating_add(T::UnbondingPeriod::get());
+
+			<Delegators<T>>::try_mutate(&miner, |delegators| -> DispatchResult {
+				let delegation = delegators
+					.iter_mut()
+					.find(|d| d.delegator == sender)
+					.ok_or(Error::<T>::InsufficientDelegation)?;
+				delegation.amount = delegation.amount.checked_sub(&amount).ok_or(Error::<T>::InsufficientDelegation)?;
+				delegators.retain(|d| !d.amount.is_zero());
+				Ok(())
+			})?;
 
-					Ok(())
-				})?;
+			<MinerItems<T>>::try_mutate(&miner, |miner_info_opt| -> DispatchResult {
+				let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
+				miner_info.collaterals = miner_info.collaterals.checked_sub(&amount).ok_or(Error::<T>::Overflow)?;
+
+				let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
+				if miner_info.collaterals < Self::check_collateral_limit(power)? && miner_info.state == MinerState::Positive {
+					Self::transition_state(&miner, miner_info, MinerState::Frozen)?;
+				}
+				Ok(())
+			})?;
+
+			<DelegatorUnbonding<T>>::try_mutate(&sender, |chunks| {
+				chunks
+					.try_push(DelegatorUnlockChunk { miner: miner.clone(), value: amount, requested_at: now, unlock_at })
+					.map_err(|_| Error::<T>::TooManyUnlockChunks)
+			})?;
+			<UnbondingDelegators<T>>::try_mutate(&miner, |delegators| -> DispatchResult {
+				if !delegators.contains(&sender) {
+					delegators.try_push(sender.clone()).map_err(|_| Error::<T>::TooManyDelegators)?;
+				}
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::<T>::Undelegated { delegator: sender, miner, amount, unlock_at });
+			Ok(())
+		}
+
+		/// Declare the miner's region, network addresses and committed capacity.
+		///
+		/// Parameters:
+		/// - `region`: ISO 3166-1 alpha-2 country code.
+		/// - `multiaddrs`: Addresses the miner can be reached at.
+		/// - `declared_space`: Total space in bytes the miner commits to provide.
+		#[pallet::call_index(23)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::update_metadata(multiaddrs.len() as u32))]
+		pub fn update_metadata(
+			origin: OriginFor<T>,
+			region: RegionCode,
+			multiaddrs: BoundedVec<Multiaddr, T::MaxMultiaddrs>,
+			declared_space: u128,
+		) -> DispatchResult {
+			let controller = ensure_signed(origin)?;
+			let sender = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
+			let miner_info = <MinerItems<T>>::try_get(&sender).map_err(|_| Error::<T>::NotMiner)?;
+			ensure!(region.iter().all(|c| c.is_ascii_uppercase()), Error::<T>::InvalidRegion);
+
+			let used = miner_info.idle_space
+				.saturating_add(miner_info.service_space)
+				.saturating_add(miner_info.lock_space);
+			ensure!(declared_space >= used, Error::<T>::DeclaredSpaceTooSmall);
+
+			<MinerMeta<T>>::insert(&sender, MinerMetadata { region, multiaddrs, declared_space });
+
+			Self::deposit_event(Event::<T>::MetadataUpdated { acc: sender, region, declared_space });
+			Ok(())
+		}
+
+		/// Set the controller key that administers the miner.
+		///
+		/// The dispatch origin of this call must be the miner stash.
+		///
+		/// Parameters:
+		/// - `controller`: The new controller, the stash itself to drop the separate key.
+		#[pallet::call_index(24)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_controller())]
+		pub fn set_controller(origin: OriginFor<T>, controller: AccountOf<T>) -> DispatchResult {
+			let stash = ensure_signed(origin)?;
+			ensure!(MinerItems::<T>::contains_key(&stash), Error::<T>::NotMiner);
+			ensure!(
+				controller == stash || !(MinerItems::<T>::contains_key(&controller) || Self::is_bound_key(&controller)),
+				Error::<T>::KeyInUse
+			);
+
+			if let Some(old) = <Controller<T>>::take(&stash) {
+				<ControllerStash<T>>::remove(old);
+			}
+			if controller != stash {
+				<Controller<T>>::insert(&stash, &controller);
+				<ControllerStash<T>>::insert(&controller, &stash);
 			}
 
+			Self::deposit_event(Event::<T>::ControllerSet { stash, controller });
+			Ok(())
+		}
+
+		/// Rotate the worker key used on the mining servers for operational extrinsics.
+		///
+		/// The dispatch origin of this call must be the miner controller.
+		///
+		/// Parameters:
+		/// - `worker`: The new worker key, the stash itself to drop the separate key.
+		#[pallet::call_index(25)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::set_worker())]
+		pub fn set_worker(origin: OriginFor<T>, worker: AccountOf<T>) -> DispatchResult {
+			let controller = ensure_signed(origin)?;
+			let stash = Self::stash_of_controller(&controller).ok_or(Error::<T>::NotMiner)?;
+			ensure!(
+				worker == stash || !(MinerItems::<T>::contains_key(&worker) || Self::is_bound_key(&worker)),
+				Error::<T>::KeyInUse
+			);
+
+			if let Some(old) = <Worker<T>>::take(&stash) {
+				<WorkerStash<T>>::remove(old);
+			}
+			if worker != stash {
+				<Worker<T>>::insert(&stash, &worker);
+				<WorkerStash<T>>::insert(&worker, &stash);
+			}
+
+			Self::deposit_event(Event::<T>::WorkerSet { stash, worker });
+			Ok(())
+		}
+
+		/// Claim the rewards earned on delegated collateral.
+		#[pallet::call_index(22)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::claim_delegator_reward())]
+		pub fn claim_delegator_reward(origin: OriginFor<T>) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+
+			let reward = <DelegatorReward<T>>::take(&sender);
+			ensure!(!reward.is_zero(), Error::<T>::NoReward);
+
+			let reward_pot = T::PalletId::get().into_account_truncating();
+			<T as pallet::Config>::Currency::transfer(&reward_pot, &sender, reward, AllowDeath)?;
+
+			Self::deposit_event(Event::<T>::DelegatorRewardClaimed { delegator: sender, reward });
 			Ok(())
 		}
 
@@ -575,13 +1120,24 @@ impl<T: Config> Pallet<T> {
 		}
 
 		let state = Self::check_state(acc)?;
-		if state == STATE_EXIT.as_bytes().to_vec() {
+		if state == MinerState::Exit {
 			return Ok(());
 		}
 		MinerItems::<T>::try_mutate(acc, |miner_info_opt| -> DispatchResult {
 			let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::ConversionError)?;
-			miner_info.idle_space =
-				miner_info.idle_space.checked_add(increment).ok_or(Error::<T>::Overflow)?;
+			let idle_space = miner_info.idle_space.checked_add(increment).ok_or(Error::<T>::Overflow)?;
+
+			let power = Self::calculate_power(idle_space, miner_info.service_space);
+			ensure!(miner_info.collaterals >= Self::check_collateral_limit(power)?, Error::<T>::InsufficientCollateral);
+
+			if let Some(meta) = <MinerMeta<T>>::get(acc) {
+				let used = idle_space
+					.saturating_add(miner_info.service_space)
+					.saturating_add(miner_info.lock_space);
+				ensure!(used <= meta.declared_space, Error::<T>::DeclaredSpaceExceeded);
+			}
+
+			miner_info.idle_space = idle_space;
 			Ok(())
 		})?;
 
@@ -599,7 +1155,7 @@ impl<T: Config> Pallet<T> {
 		}
 
 		let state = Self::check_state(acc)?; //read 1
-		if state == STATE_EXIT.as_bytes().to_vec() {
+		if state == MinerState::Exit {
 			return Ok(());
 		}
 		MinerItems::<T>::try_mutate(acc, |miner_info_opt| -> DispatchResult {
@@ -624,7 +1180,7 @@ impl<T: Config> Pallet<T> {
 		}
 
 		let state = Self::check_state(acc)?;
-		if state == STATE_EXIT.as_bytes().to_vec() {
+		if state == MinerState::Exit {
 			return Ok(());
 		}
 		MinerItems::<T>::try_mutate(acc, |miner_info_opt| -> DispatchResult {
@@ -649,7 +1205,7 @@ impl<T: Config> Pallet<T> {
 		}
 
 		let state = Self::check_state(acc)?;
-		if state == STATE_EXIT.as_bytes().to_vec() {
+		if state == MinerState::Exit {
 			return Ok(());
 		}
 		MinerItems::<T>::try_mutate(acc, |miner_info_opt| -> DispatchResult {
@@ -672,6 +1228,8 @@ impl<T: Config> Pallet<T> {
 		power
 	}
 
+	// Delegator rewards are credited before the miner's order, so a failure must undo them.
+	#[transactional]
 	pub fn calculate_miner_reward(
 		miner: &AccountOf<T>,
 		total_reward: u128,
@@ -679,87 +1237,567 @@ impl<T: Config> Pallet<T> {
 		total_service_space: u128,
 		miner_idle_space: u128,
 		miner_service_space: u128,
-	) -> DispatchResult {
+	) -> Result<u128, DispatchError> {
 		let total_power = Self::calculate_power(total_idle_space, total_service_space);
 		let miner_power = Self::calculate_power(miner_idle_space, miner_service_space);
 
 		let miner_prop = Perbill::from_rational(miner_power, total_power);
 		let this_round_reward = miner_prop.mul_floor(total_reward);
-		let each_share = EACH_SHARE_MUTI.mul_floor(this_round_reward);
-		let each_share = each_share.checked_div(RELEASE_NUMBER.into()).ok_or(Error::<T>::Overflow)?;
-		let issued: BalanceOf<T> = ISSUE_MUTI.mul_floor(this_round_reward).try_into().map_err(|_| Error::<T>::Overflow)?;
-
-		let order = RewardOrder::<BalanceOf<T>>{
-			order_reward: this_round_reward.try_into().map_err(|_| Error::<T>::Overflow)?,
-			each_share: each_share.try_into().map_err(|_| Error::<T>::Overflow)?,
-			award_count: 1,
-			has_issued: true,
-		};
+		let round_reward: BalanceOf<T> = this_round_reward.try_into().map_err(|_| Error::<T>::Overflow)?;
+		let delegators_reward = Self::share_with_delegators(miner, round_reward)?;
+		let order_reward = round_reward.checked_sub(&delegators_reward).ok_or(Error::<T>::Overflow)?;
+		let issued = T::RewardImmediateShare::get().mul_floor(order_reward);
+		let vesting = order_reward.checked_sub(&issued).ok_or(Error::<T>::Overflow)?;
+		let release_number = T::RewardReleaseNumber::get().max(1);
+		let now = <frame_system::Pallet<T>>::block_number();
+		let start = Self::release_start(now);
+
 		// calculate available reward
 		RewardMap::<T>::try_mutate(miner, |opt_reward_info| -> DispatchResult {
 			let reward_info = opt_reward_info.as_mut().ok_or(Error::<T>::Unexpected)?;
-			// traverse the order list
-			for order_temp in reward_info.order_list.iter_mut() {
-				// skip if the order has been issued for 180 times
-				if order_temp.award_count == RELEASE_NUMBER {
-					continue;
-				}
-				reward_info.currently_available_reward = reward_info.currently_available_reward
-					.checked_add(&order_temp.each_share).ok_or(Error::<T>::Overflow)?;
-
-				order_temp.award_count += 1;
-			}
-
-			if reward_info.order_list.len() == RELEASE_NUMBER as usize {
-				reward_info.order_list.remove(0);
-			}
+			Self::release_reward_orders(reward_info, now)?;
 
 			reward_info.currently_available_reward = reward_info.currently_available_reward
-				.checked_add(&issued).ok_or(Error::<T>::Overflow)?
-				.checked_add(&order.each_share).ok_or(Error::<T>::Overflow)?;
+				.checked_add(&issued).ok_or(Error::<T>::Overflow)?;
 			reward_info.total_reward = reward_info.total_reward
-				.checked_add(&order.order_reward).ok_or(Error::<T>::Overflow)?;
-			reward_info.order_list.try_push(order.clone()).map_err(|_| Error::<T>::BoundedVecError)?;
+				.checked_add(&order_reward).ok_or(Error::<T>::Overflow)?;
+
+			// rounds of the same release interval share one order
+			match reward_info.order_list.last_mut() {
+				Some(last) if last.start == start => last.merge(order_reward, vesting),
+				_ => {
+					let order = RewardOrder::<BalanceOf<T>, BlockNumberOf<T>>{
+						order_reward,
+						vesting,
+						each_share: vesting / release_number.into(),
+						award_count: 0,
+						release_number,
+						start,
+					};
+					reward_info.order_list.try_push(order).map_err(|_| Error::<T>::TooManyRewardOrders)?;
+				},
+			}
 
-			Ok(())
+			Self::settle_from_reward(miner, reward_info)
 		})?;
 
 		<CurrencyReward<T>>::mutate(|v| -> DispatchResult {
-			*v = v.checked_sub(&order.order_reward).ok_or(Error::<T>::Overflow)?;
+			*v = v.checked_sub(&round_reward).ok_or(Error::<T>::Overflow)?;
 			Ok(())
 		})?;
+
+		if <AutoPayout<T>>::get(miner) {
+			<PendingPayout<T>>::insert(miner, ());
+		}
 		
+		Ok(this_round_reward)
+	}
+
+	// Block the orders created at `now` start vesting from, the end of the current release
+	// interval. Merging the rounds of an interval keeps at most `RewardReleaseNumber + 1`
+	// orders vesting, and rounding up never releases a tranche early.
+	fn release_start(now: BlockNumberOf<T>) -> BlockNumberOf<T> {
+		let interval = T::RewardReleaseInterval::get();
+		if interval.is_zero() {
+			return now;
+		}
+		let rem = now % interval;
+		if rem.is_zero() {
+			now
+		} else {
+			now.saturating_add(interval - rem)
+		}
+	}
+
+	// Number of tranches of `order` unlocked at block `now`.
+	fn due_tranches(order: &RewardOrder<BalanceOf<T>, BlockNumberOf<T>>, now: BlockNumberOf<T>) -> u32 {
+		let interval = T::RewardReleaseInterval::get();
+		if interval.is_zero() {
+			return order.release_number;
+		}
+		let elapsed = now.saturating_sub(order.start) / interval;
+		elapsed.saturated_into::<u32>().min(order.release_number)
+	}
+
+	// Moves every tranche unlocked by block `now` into the available reward and drops
+	// the orders that are fully released.
+	fn release_reward_orders(reward_info: &mut Reward<T>, now: BlockNumberOf<T>) -> DispatchResult {
+		for order in reward_info.order_list.iter_mut() {
+			let due = Self::due_tranches(order, now);
+			if due <= order.award_count {
+				continue;
+			}
+			let amount = order.releasable(due);
+			reward_info.currently_available_reward = reward_info.currently_available_reward
+				.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+			order.award_count = due;
+		}
+		reward_info.order_list.retain(|order| order.award_count < order.release_number);
+
 		Ok(())
 	}
 
-	pub fn deposit_punish(miner: &AccountOf<T>, punish_amount: BalanceOf<T>) -> DispatchResult {
+	// Spends the available reward on the miner's debt first and then on topping its
+	// collateral back up to the limit. A frozen miner is unfrozen once it is covered again.
+	fn settle_from_reward(miner: &AccountOf<T>, reward_info: &mut Reward<T>) -> DispatchResult {
 		<MinerItems<T>>::try_mutate(miner, |miner_info_opt| -> DispatchResult {
 			let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
-			
+
+			// slashed funds already sit in the reward pot, repaying only returns them to the pool
+			let repay = reward_info.currently_available_reward.min(miner_info.debt);
+			if !repay.is_zero() {
+				miner_info.debt = miner_info.debt.checked_sub(&repay).ok_or(Error::<T>::Overflow)?;
+				reward_info.currently_available_reward = reward_info.currently_available_reward
+					.checked_sub(&repay).ok_or(Error::<T>::Overflow)?;
+				reward_info.reward_issued = reward_info.reward_issued
+					.checked_add(&repay).ok_or(Error::<T>::Overflow)?;
+				<CurrencyReward<T>>::mutate(|v| -> DispatchResult {
+					*v = v.checked_add(&repay).ok_or(Error::<T>::Overflow)?;
+					Ok(())
+				})?;
+
+				Self::deposit_event(Event::<T>::DebtRepaid {
+					acc: miner.clone(),
+					amount: repay,
+					remaining: miner_info.debt,
+				});
+			}
+
+			if !miner_info.debt.is_zero()
+				|| !matches!(miner_info.state, MinerState::Positive | MinerState::Frozen)
+			{
+				return Ok(());
+			}
+
+			let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
+			let limit = Self::check_collateral_limit(power)?;
+			let top_up = limit.saturating_sub(miner_info.collaterals).min(reward_info.currently_available_reward);
+			if !top_up.is_zero() {
+				let reward_pot = T::PalletId::get().into_account_truncating();
+				<T as pallet::Config>::Currency::transfer(&reward_pot, miner, top_up, AllowDeath)?;
+				T::Currency::reserve(miner, top_up)?;
+				miner_info.collaterals = miner_info.collaterals.checked_add(&top_up).ok_or(Error::<T>::Overflow)?;
+				reward_info.currently_available_reward = reward_info.currently_available_reward
+					.checked_sub(&top_up).ok_or(Error::<T>::Overflow)?;
+				reward_info.reward_issued = reward_info.reward_issued
+					.checked_add(&top_up).ok_or(Error::<T>::Overflow)?;
+
+				Self::deposit_event(Event::<T>::CollateralToppedUp {
+					acc: miner.clone(),
+					amount: top_up,
+					collaterals: miner_info.collaterals,
+				});
+			}
+
+			if miner_info.state == MinerState::Frozen && miner_info.collaterals >= limit {
+				Self::transition_state(miner, miner_info, MinerState::Positive)?;
+			}
+
+			Ok(())
+		})
+	}
+
+	/// Projects the future reward unlocks of `miner` as `(block, amount)` pairs in block order.
+	///
+	/// Tranches that are already unlocked but not yet moved to the available reward are
+	/// reported at the current block.
+	pub fn reward_unlocks(miner: &AccountOf<T>) -> Vec<(BlockNumberOf<T>, BalanceOf<T>)> {
+		let reward_info = match <RewardMap<T>>::get(miner) {
+			Some(reward_info) => reward_info,
+			None => return Vec::new(),
+		};
+		let now = <frame_system::Pallet<T>>::block_number();
+		let interval = T::RewardReleaseInterval::get();
+
+		let mut unlocks: BTreeMap<BlockNumberOf<T>, BalanceOf<T>> = BTreeMap::new();
+		for order in reward_info.order_list.iter() {
+			let due = Self::due_tranches(order, now);
+			let mut released = order.award_count;
+			if due > released {
+				let amount = order.releasable(due);
+				let entry = unlocks.entry(now).or_insert_with(Zero::zero);
+				*entry = entry.saturating_add(amount);
+				released = due;
+			}
+			for tranche in released..order.release_number {
+				let mut next = order.clone();
+				next.award_count = tranche;
+				let amount = next.releasable(tranche + 1);
+				let at = order.start.saturating_add(interval.saturating_mul((tranche + 1).into()));
+				let entry = unlocks.entry(at).or_insert_with(Zero::zero);
+				*entry = entry.saturating_add(amount);
+			}
+		}
+
+		unlocks.into_iter().collect()
+	}
+
+	// Transfers the currently available reward of a positive miner to its beneficiary.
+	#[transactional]
+	fn pay_reward(miner: &AccountOf<T>) -> Result<BalanceOf<T>, DispatchError> {
+		let miner_info = <MinerItems<T>>::try_get(miner).map_err(|_| Error::<T>::NotMiner)?;
+		ensure!(miner_info.state == MinerState::Positive, Error::<T>::NotpositiveState);
+
+		<RewardMap<T>>::try_mutate(miner, |opt_reward| -> Result<BalanceOf<T>, DispatchError> {
+			let reward = opt_reward.as_mut().ok_or(Error::<T>::Unexpected)?;
+			Self::release_reward_orders(reward, <frame_system::Pallet<T>>::block_number())?;
+			Self::settle_from_reward(miner, reward)?;
+			let amount = reward.currently_available_reward;
+			ensure!(amount != 0u32.saturated_into(), Error::<T>::NoReward);
+			let reward_issued = reward.reward_issued.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+
 			let reward_pot = T::PalletId::get().into_account_truncating();
+			<T as pallet::Config>::Currency::transfer(&reward_pot, &miner_info.beneficiary, amount, AllowDeath)?;
+
+			reward.reward_issued = reward_issued;
+			reward.currently_available_reward = 0u32.saturated_into();
+
+			Self::deposit_event(Event::<T>::Receive {
+				acc: miner.clone(),
+				beneficiary: miner_info.beneficiary.clone(),
+				reward: amount,
+			});
+
+			Ok(amount)
+		})
+	}
+
+	// Pays at most `PayoutBatchSize` waiting miners, the rest is left for the following blocks.
+	// Miners that can't be paid, e.g. because they are frozen, claim manually later on.
+	fn auto_payout_batch() -> Weight {
+		let mut weight = T::DbWeight::get().reads(1);
+		let limit = T::PayoutBatchSize::get() as usize;
+		let batch: Vec<AccountOf<T>> = <PendingPayout<T>>::iter_keys().take(limit).collect();
+
+		for miner in batch {
+			<PendingPayout<T>>::remove(&miner);
+			if let Err(e) = Self::pay_reward(&miner) {
+				log::warn!("auto payout of miner reward failed: {:?}", e);
+			}
+			// miner and reward records, the reward pool, and the two transfers of pot,
+			// miner and beneficiary accounts made by the top-up and the payout
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(6, 7));
+		}
+
+		weight
+	}
+
+	/// The miner stash administered by `acc`. A stash without a separate controller
+	/// administers itself.
+	pub fn stash_of_controller(acc: &AccountOf<T>) -> Option<AccountOf<T>> {
+		if let Some(stash) = <ControllerStash<T>>::get(acc) {
+			return Some(stash);
+		}
+		(<MinerItems<T>>::contains_key(acc) && !<Controller<T>>::contains_key(acc)).then(|| acc.clone())
+	}
+
+	/// The miner stash `acc` works for. A stash without a separate worker key works for itself.
+	pub fn stash_of_worker(acc: &AccountOf<T>) -> Option<AccountOf<T>> {
+		if let Some(stash) = <WorkerStash<T>>::get(acc) {
+			return Some(stash);
+		}
+		(<MinerItems<T>>::contains_key(acc) && !<Worker<T>>::contains_key(acc)).then(|| acc.clone())
+	}
+
+	fn is_bound_key(acc: &AccountOf<T>) -> bool {
+		<ControllerStash<T>>::contains_key(acc) || <WorkerStash<T>>::contains_key(acc)
+	}
+
+	// Drops the controller and worker keys of a miner that is leaving.
+	fn remove_keys(stash: &AccountOf<T>) {
+		if let Some(controller) = <Controller<T>>::take(stash) {
+			<ControllerStash<T>>::remove(controller);
+		}
+		if let Some(worker) = <Worker<T>>::take(stash) {
+			<WorkerStash<T>>::remove(worker);
+		}
+	}
+
+	// Appends `acc` to the miner registry.
+	fn registry_insert(acc: &AccountOf<T>) -> DispatchResult {
+		let len = <MinerRegistryLen<T>>::get();
+		ensure!(len < T::ItemLimit::get(), Error::<T>::StorageLimitReached);
+
+		<MinerIndex<T>>::insert(acc, len);
+		<MinerByIndex<T>>::insert(len, acc);
+		<MinerRegistryLen<T>>::put(len + 1);
+
+		Ok(())
+	}
+
+	// Removes `acc` from the miner registry by moving the last miner into its slot.
+	fn registry_remove(acc: &AccountOf<T>) {
+		let index = match <MinerIndex<T>>::take(acc) {
+			Some(index) => index,
+			None => return,
+		};
+		let last = <MinerRegistryLen<T>>::get().saturating_sub(1);
+		if index != last {
+			if let Some(moved) = <MinerByIndex<T>>::get(last) {
+				<MinerIndex<T>>::insert(&moved, index);
+				<MinerByIndex<T>>::insert(index, moved);
+			}
+		}
+		<MinerByIndex<T>>::remove(last);
+		<MinerRegistryLen<T>>::put(last);
+	}
+
+	fn delegated_total(miner: &AccountOf<T>) -> BalanceOf<T> {
+		<Delegators<T>>::get(miner)
+			.iter()
+			.fold(BalanceOf::<T>::zero(), |total, d| total.saturating_add(d.amount))
+	}
+
+	// Credits the delegators' pro rata part of a round reward, less the miner's commission,
+	// and returns the total credited. Delegator rewards are claimable right away.
+	fn share_with_delegators(miner: &AccountOf<T>, reward: BalanceOf<T>) -> Result<BalanceOf<T>, DispatchError> {
+		let delegators = <Delegators<T>>::get(miner);
+		let delegated = Self::delegated_total(miner);
+		let collaterals = <MinerItems<T>>::try_get(miner).map_err(|_| Error::<T>::NotMiner)?.collaterals;
+		if delegated.is_zero() || collaterals.is_zero() {
+			return Ok(Zero::zero());
+		}
+
+		let gross = Perbill::from_rational(delegated, collaterals).mul_floor(reward);
+		let net = gross.saturating_sub(<Commission<T>>::get(miner).mul_floor(gross));
+
+		let mut credited = BalanceOf::<T>::zero();
+		for d in delegators.iter() {
+			let credit = Perbill::from_rational(d.amount, delegated).mul_floor(net);
+			<DelegatorReward<T>>::mutate(&d.delegator, |reward| -> DispatchResult {
+				*reward = reward.checked_add(&credit).ok_or(Error::<T>::Overflow)?;
+				Ok(())
+			})?;
+			credited = credited.checked_add(&credit).ok_or(Error::<T>::Overflow)?;
+		}
+
+		Ok(credited)
+	}
 
-			if miner_info.collaterals > punish_amount {
-				T::Currency::unreserve(miner, punish_amount);
-				T::Currency::transfer(miner, &reward_pot, punish_amount, AllowDeath)?;
-				<CurrencyReward<T>>::mutate(|reward| {
-					*reward = *reward + punish_amount;
+	// Slashes the delegators' pro rata part of `amount` taken from `collaterals` and returns
+	// the total slashed from them.
+	fn slash_delegators(
+		miner: &AccountOf<T>,
+		amount: BalanceOf<T>,
+		collaterals: BalanceOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		if amount.is_zero() {
+			return Ok(Zero::zero());
+		}
+
+		let reward_pot = T::PalletId::get().into_account_truncating();
+		<Delegators<T>>::try_mutate(miner, |delegators| -> Result<BalanceOf<T>, DispatchError> {
+			let mut slashed = BalanceOf::<T>::zero();
+			for d in delegators.iter_mut() {
+				let part = if amount >= collaterals {
+					d.amount
+				} else {
+					Perbill::from_rational(d.amount, collaterals).mul_floor(amount).min(d.amount)
+				};
+				T::Currency::unreserve(&d.delegator, part);
+				T::Currency::transfer(&d.delegator, &reward_pot, part, AllowDeath)?;
+				d.amount = d.amount.saturating_sub(part);
+				slashed = slashed.checked_add(&part).ok_or(Error::<T>::Overflow)?;
+			}
+			delegators.retain(|d| !d.amount.is_zero());
+
+			<CurrencyReward<T>>::mutate(|reward| {
+				*reward = *reward + slashed;
+			});
+
+			Ok(slashed)
+		})
+	}
+
+	// Slashes up to `amount` from collateral delegators started unbonding from `miner` after
+	// block `fault_at`, and returns the total slashed.
+	fn slash_delegator_unbonding(
+		miner: &AccountOf<T>,
+		amount: BalanceOf<T>,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<BalanceOf<T>, DispatchError> {
+		let mut slashed = BalanceOf::<T>::zero();
+		if amount.is_zero() {
+			return Ok(slashed);
+		}
+
+		let reward_pot = T::PalletId::get().into_account_truncating();
+		let mut cleared: Vec<AccountOf<T>> = Vec::new();
+		for delegator in <UnbondingDelegators<T>>::get(miner).iter() {
+			if slashed >= amount {
+				break;
+			}
+
+			let mut part = BalanceOf::<T>::zero();
+			<DelegatorUnbonding<T>>::mutate_exists(delegator, |chunks_opt| {
+				if let Some(chunks) = chunks_opt {
+					for chunk in chunks
+						.iter_mut()
+						.filter(|chunk| &chunk.miner == miner && chunk.requested_at > fault_at)
+					{
+						let take = amount.saturating_sub(slashed).saturating_sub(part).min(chunk.value);
+						chunk.value = chunk.value.saturating_sub(take);
+						part = part.saturating_add(take);
+					}
+					chunks.retain(|chunk| !chunk.value.is_zero());
+					if !chunks.iter().any(|chunk| &chunk.miner == miner) {
+						cleared.push(delegator.clone());
+					}
+					if chunks.is_empty() {
+						*chunks_opt = None;
+					}
+				}
+			});
+			if part.is_zero() {
+				continue;
+			}
+
+			T::Currency::unreserve(delegator, part);
+			T::Currency::transfer(delegator, &reward_pot, part, AllowDeath)?;
+			slashed = slashed.checked_add(&part).ok_or(Error::<T>::Overflow)?;
+		}
+
+		if !cleared.is_empty() {
+			<UnbondingDelegators<T>>::mutate_exists(miner, |delegators_opt| {
+				if let Some(delegators) = delegators_opt {
+					delegators.retain(|d| !cleared.contains(d));
+					if delegators.is_empty() {
+						*delegators_opt = None;
+					}
+				}
+			});
+		}
+		<CurrencyReward<T>>::mutate(|reward| {
+			*reward = *reward + slashed;
+		});
+
+		Ok(slashed)
+	}
+
+	// Returns every delegation of `miner` to its delegator.
+	fn release_delegations(miner: &AccountOf<T>) {
+		for d in <Delegators<T>>::take(miner).iter() {
+			T::Currency::unreserve(&d.delegator, d.amount);
+		}
+		<UnbondingDelegators<T>>::remove(miner);
+		<Commission<T>>::remove(miner);
+	}
+
+	// Removes the chunks `acc` unbonds as a miner that unlocked by `now` and returns their total.
+	fn drain_unlocked(acc: &AccountOf<T>, now: BlockNumberOf<T>) -> BalanceOf<T> {
+		let mut amount = BalanceOf::<T>::zero();
+		<Unbonding<T>>::mutate_exists(acc, |chunks_opt| {
+			if let Some(chunks) = chunks_opt {
+				chunks.retain(|chunk| {
+					if chunk.unlock_at <= now {
+						amount = amount.saturating_add(chunk.value);
+						false
+					} else {
+						true
+					}
 				});
-				miner_info.collaterals = miner_info.collaterals.checked_sub(&punish_amount).ok_or(Error::<T>::Overflow)?;
-			} else {
-				T::Currency::unreserve(miner, miner_info.collaterals);
-				T::Currency::transfer(miner, &reward_pot, miner_info.collaterals, AllowDeath)?;
-				<CurrencyReward<T>>::mutate(|reward| {
-					*reward = *reward + miner_info.collaterals;
+				if chunks.is_empty() {
+					*chunks_opt = None;
+				}
+			}
+		});
+
+		amount
+	}
+
+	// Removes the chunks `acc` unbonds as a delegator that unlocked by `now` and returns their total.
+	// Miners it no longer unbonds from stop tracking it.
+	fn drain_delegator_unlocked(acc: &AccountOf<T>, now: BlockNumberOf<T>) -> BalanceOf<T> {
+		let mut amount = BalanceOf::<T>::zero();
+		let mut drained: Vec<AccountOf<T>> = Vec::new();
+		<DelegatorUnbonding<T>>::mutate_exists(acc, |chunks_opt| {
+			if let Some(chunks) = chunks_opt {
+				chunks.retain(|chunk| {
+					if chunk.unlock_at <= now {
+						amount = amount.saturating_add(chunk.value);
+						if !drained.contains(&chunk.miner) {
+							drained.push(chunk.miner.clone());
+						}
+						false
+					} else {
+						true
+					}
 				});
-				miner_info.collaterals = BalanceOf::<T>::zero();
-				miner_info.debt = punish_amount.checked_sub(&miner_info.collaterals).ok_or(Error::<T>::Overflow)?;
+				drained.retain(|miner| !chunks.iter().any(|chunk| &chunk.miner == miner));
+				if chunks.is_empty() {
+					*chunks_opt = None;
+				}
 			}
+		});
+		for miner in drained.iter() {
+			<UnbondingDelegators<T>>::mutate_exists(miner, |delegators_opt| {
+				if let Some(delegators) = delegators_opt {
+					delegators.retain(|d| d != acc);
+					if delegators.is_empty() {
+						*delegators_opt = None;
+					}
+				}
+			});
+		}
+
+		amount
+	}
+
+	/// Slashes `punish_amount` for a fault committed at block `fault_at`.
+	///
+	/// The collateral is slashed first, shared pro rata between the miner and its delegators,
+	/// then collateral the miner and then its delegators started unbonding after the fault.
+	/// Whatever is still missing is recorded as debt.
+	pub fn deposit_punish(
+		miner: &AccountOf<T>,
+		punish_amount: BalanceOf<T>,
+		fault_at: BlockNumberOf<T>,
+	) -> DispatchResult {
+		<MinerItems<T>>::try_mutate(miner, |miner_info_opt| -> DispatchResult {
+			let miner_info = miner_info_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
+
+			let from_collaterals = punish_amount.min(miner_info.collaterals);
+			let own = miner_info.collaterals.saturating_sub(Self::delegated_total(miner));
+			let from_delegators = Self::slash_delegators(miner, from_collaterals, miner_info.collaterals)?;
+			let from_own = from_collaterals.saturating_sub(from_delegators).min(own);
+			let slashed_collaterals = from_delegators.saturating_add(from_own);
+			miner_info.collaterals = miner_info.collaterals.checked_sub(&slashed_collaterals).ok_or(Error::<T>::Overflow)?;
+			let mut shortfall = punish_amount.checked_sub(&slashed_collaterals).ok_or(Error::<T>::Overflow)?;
+			let mut slashed = from_own;
+
+			if !shortfall.is_zero() {
+				<Unbonding<T>>::mutate_exists(miner, |chunks_opt| {
+					if let Some(chunks) = chunks_opt {
+						for chunk in chunks.iter_mut().filter(|chunk| chunk.requested_at > fault_at) {
+							let take = shortfall.min(chunk.value);
+							chunk.value = chunk.value.saturating_sub(take);
+							shortfall = shortfall.saturating_sub(take);
+							slashed = slashed.saturating_add(take);
+						}
+						chunks.retain(|chunk| !chunk.value.is_zero());
+						if chunks.is_empty() {
+							*chunks_opt = None;
+						}
+					}
+				});
+			}
+
+			let reward_pot = T::PalletId::get().into_account_truncating();
+			T::Currency::unreserve(miner, slashed);
+			T::Currency::transfer(miner, &reward_pot, slashed, AllowDeath)?;
+			<CurrencyReward<T>>::mutate(|reward| {
+				*reward = *reward + slashed;
+			});
+			let from_delegator_unbonding = Self::slash_delegator_unbonding(miner, shortfall, fault_at)?;
+			shortfall = shortfall.checked_sub(&from_delegator_unbonding).ok_or(Error::<T>::Overflow)?;
+			miner_info.debt = miner_info.debt.checked_add(&shortfall).ok_or(Error::<T>::Overflow)?;
 
 			let power = Self::calculate_power(miner_info.idle_space, miner_info.service_space);
 			let limit = Self::check_collateral_limit(power)?;
 
-			if miner_info.collaterals < limit {
-				miner_info.state = STATE_FROZEN.as_bytes().to_vec().try_into().map_err(|_| Error::<T>::BoundedVecError)?;
+			// Locked miners keep their state so that the scheduled exit still applies.
+			if miner_info.collaterals < limit && miner_info.state == MinerState::Positive {
+				Self::transition_state(miner, miner_info, MinerState::Frozen)?;
 			}
 
 			Ok(())
@@ -768,29 +1806,62 @@ impl<T: Config> Pallet<T> {
 		Ok(())
 	}
 
-	pub fn idle_punish(miner: &AccountOf<T>, idle_space: u128, service_space: u128) -> DispatchResult {
+	/// Database weight of `calculate_miner_reward` for `miner`, which credits each delegator.
+	pub fn reward_weight(miner: &AccountOf<T>) -> Weight {
+		let delegators = <Delegators<T>>::decode_len(miner).unwrap_or(0) as u64;
+		T::DbWeight::get().reads_writes(8 + delegators, 7 + delegators)
+	}
+
+	/// Database weight of `deposit_punish` for `miner`, which slashes each delegator and
+	/// each delegator still unbonding from it.
+	pub fn punish_weight(miner: &AccountOf<T>) -> Weight {
+		let delegators = <Delegators<T>>::decode_len(miner).unwrap_or(0) as u64;
+		let unbonding = <UnbondingDelegators<T>>::decode_len(miner).unwrap_or(0) as u64;
+		T::DbWeight::get().reads_writes(
+			9 + delegators + 2 * unbonding,
+			7 + delegators + 2 * unbonding,
+		)
+	}
+
+	pub fn idle_punish(
+		miner: &AccountOf<T>,
+		idle_space: u128,
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
 		let power = Self::calculate_power(idle_space, service_space);
 		let limit = Self::check_collateral_limit(power)?;
 
 		let punish_amount = IDLE_PUNI_MUTI.mul_floor(limit);
 
-		Self::deposit_punish(miner, punish_amount)?;
+		Self::deposit_punish(miner, punish_amount, fault_at)?;
 
-		Ok(())
+		Ok(punish_amount.saturated_into())
 	}
 
-	pub fn service_punish(miner: &AccountOf<T>, idle_space: u128, service_space: u128) -> DispatchResult {
+	pub fn service_punish(
+		miner: &AccountOf<T>,
+		idle_space: u128,
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
 		let power = Self::calculate_power(idle_space, service_space);
 		let limit = Self::check_collateral_limit(power)?;
 
 		let punish_amount = SERVICE_PUNI_MUTI.mul_floor(limit);
 
-		Self::deposit_punish(miner, punish_amount)?;
+		Self::deposit_punish(miner, punish_amount, fault_at)?;
 
-		Ok(())
+		Ok(punish_amount.saturated_into())
 	}
 
-	pub fn clear_punish(miner: &AccountOf<T>, level: u8, idle_space: u128, service_space: u128) -> DispatchResult {
+	pub fn clear_punish(
+		miner: &AccountOf<T>,
+		level: u8,
+		idle_space: u128,
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
 		let power = Self::calculate_power(idle_space, service_space);
 		let limit = Self::check_collateral_limit(power)?;
 
@@ -801,9 +1872,9 @@ impl<T: Config> Pallet<T> {
 			_ => return Err(Error::<T>::Unexpected)?,
 		};
 
-		Self::deposit_punish(miner, punish_amount)?;
+		Self::deposit_punish(miner, punish_amount, fault_at)?;
 
-		Ok(())
+		Ok(punish_amount.saturated_into())
 	}
 
 	fn check_collateral_limit(power: u128) -> Result<BalanceOf<T>, Error<T>> {
@@ -814,15 +1885,23 @@ impl<T: Config> Pallet<T> {
 		Ok(limit)
 	}
 
-	fn check_state(acc: &AccountOf<T>) -> Result<Vec<u8>, Error<T>> {
-		Ok(<MinerItems<T>>::try_get(acc).map_err(|_e| Error::<T>::NotMiner)?.state.to_vec())
+	fn check_state(acc: &AccountOf<T>) -> Result<MinerState, Error<T>> {
+		Ok(<MinerItems<T>>::try_get(acc).map_err(|_e| Error::<T>::NotMiner)?.state)
 	}
 
-	fn vec_to_bound<P>(param: Vec<P>) -> Result<BoundedVec<P, T::ItemLimit>, DispatchError> {
-		let result: BoundedVec<P, T::ItemLimit> =
-			param.try_into().map_err(|_e| Error::<T>::StorageLimitReached)?;
+	// Moves the miner to `to` if the transition table allows it, and announces the change.
+	fn transition_state(
+		acc: &AccountOf<T>,
+		miner_info: &mut MinerInfo<AccountOf<T>, BalanceOf<T>>,
+		to: MinerState,
+	) -> DispatchResult {
+		let from = miner_info.state;
+		ensure!(from.can_transition_to(to), Error::<T>::InvalidStateTransition);
+		miner_info.state = to;
+
+		Self::deposit_event(Event::<T>::MinerStateChanged { acc: acc.clone(), from, to });
 
-		Ok(result)
+		Ok(())
 	}
 
 	// Note: that it is necessary to determine whether the state meets the exit conditions before use.
@@ -834,11 +1913,14 @@ impl<T: Config> Pallet<T> {
 				*v = *v + reward;
 			});
 		}
-		let mut miner_list = AllMiner::<T>::get();
-		miner_list.retain(|s| s != acc);
-		AllMiner::<T>::put(miner_list);
+		Self::registry_remove(acc);
 
 		<RewardMap<T>>::remove(acc);
+		<AutoPayout<T>>::remove(acc);
+		<PendingPayout<T>>::remove(acc);
+		<MinerMeta<T>>::remove(acc);
+		Self::remove_keys(acc);
+		Self::release_delegations(acc);
 		<MinerItems<T>>::remove(acc);
 
 		Ok(())
@@ -855,22 +1937,23 @@ impl<T: Config> Pallet<T> {
 			});
 		}
 
-		let mut miner_list = AllMiner::<T>::get();
-		miner_list.retain(|s| s != acc);
-		AllMiner::<T>::put(miner_list);
+		Self::registry_remove(acc);
 
 		<RewardMap<T>>::remove(acc);
+		<AutoPayout<T>>::remove(acc);
+		<PendingPayout<T>>::remove(acc);
 		<MinerItems<T>>::try_mutate(acc, |miner_opt| -> DispatchResult {
 			let miner_info = miner_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
-			miner_info.state = Self::vec_to_bound::<u8>(STATE_EXIT.as_bytes().to_vec())?;
-
-			Ok(())
+			Self::transition_state(acc, miner_info, MinerState::Exit)
 		})
 	}
 	// Note: that it is necessary to determine whether the state meets the exit conditions before use.
 	fn withdraw(acc: &AccountOf<T>) -> DispatchResult {
 		let miner_info = <MinerItems<T>>::try_get(acc).map_err(|_| Error::<T>::NotMiner)?;
-		T::Currency::unreserve(acc, miner_info.collaterals);
+		T::Currency::unreserve(acc, miner_info.collaterals.saturating_sub(Self::delegated_total(acc)));
+		Self::release_delegations(acc);
+		Self::remove_keys(acc);
+		<MinerMeta<T>>::remove(acc);
 		<MinerItems<T>>::remove(acc);
 
 		Ok(())
@@ -891,20 +1974,32 @@ impl<T: Config> OnUnbalanced<NegativeImbalanceOf<T>> for Pallet<T> {
 	}
 }
 
-pub trait MinerControl<AccountId> {
+pub trait MinerControl<AccountId, BlockNumber> {
 	fn add_miner_idle_space(acc: &AccountId, power: u128) -> DispatchResult;
 	fn sub_miner_idle_space(acc: &AccountId, power: u128) -> DispatchResult;
 	fn add_miner_service_space(acc: &AccountId, power: u128) -> DispatchResult;
 	fn sub_miner_service_space(acc: &AccountId, power: u128) -> DispatchResult;
 	fn get_power(acc: &AccountId) -> Result<(u128, u128), DispatchError>;
 	fn miner_is_exist(acc: AccountId) -> bool;
-	fn get_miner_state(acc: &AccountId) -> Result<Vec<u8>, DispatchError>;
-	fn get_all_miner() -> Result<Vec<AccountId>, DispatchError>;
+	fn get_miner_state(acc: &AccountId) -> Result<MinerState, DispatchError>;
+	/// The registered miner at `index`, for `index` below `get_miner_count()`.
+	fn get_miner_by_index(index: u32) -> Option<AccountId>;
 	fn lock_space(acc: &AccountId, space: u128) -> DispatchResult;
 	fn unlock_space(acc: &AccountId, space: u128) -> DispatchResult;
 	fn unlock_space_to_service(acc: &AccountId, space: u128) -> DispatchResult;
 	fn get_miner_idle_space(acc: &AccountId) -> Result<u128, DispatchError>;
+	/// Number of registered miners that have not exited.
 	fn get_miner_count() -> u32;
+	/// The region the miner declared, if any.
+	fn get_miner_region(acc: &AccountId) -> Option<RegionCode>;
+	/// The network addresses the miner published.
+	fn get_miner_multiaddrs(acc: &AccountId) -> Vec<Multiaddr>;
+	/// The total space the miner committed to, zero if not declared.
+	fn get_declared_space(acc: &AccountId) -> u128;
+	/// The miner stash administered by the controller key `acc`.
+	fn stash_of_controller(acc: &AccountId) -> Option<AccountId>;
+	/// The miner stash the worker key `acc` signs operational extrinsics for.
+	fn stash_of_worker(acc: &AccountId) -> Option<AccountId>;
 	fn get_reward() -> u128; 
 	fn calculate_miner_reward(
 		miner: &AccountId, 
@@ -913,10 +2008,14 @@ pub trait MinerControl<AccountId> {
 		total_service_space: u128,
 		miner_idle_space: u128,
 		miner_service_space: u128,
-	) -> DispatchResult;
-	fn clear_punish(miner: &AccountId, level: u8, idle_space: u128, service_space: u128) -> DispatchResult;
-	fn idle_punish(miner: &AccountId, idle_space: u128, service_space: u128) -> DispatchResult;
-	fn service_punish(miner: &AccountId, idle_space: u128, service_space: u128) -> DispatchResult;
+	) -> Result<u128, DispatchError>;
+	fn clear_punish(miner: &AccountId, level: u8, idle_space: u128, service_space: u128, fault_at: BlockNumber) -> Result<u128, DispatchError>;
+	fn idle_punish(miner: &AccountId, idle_space: u128, service_space: u128, fault_at: BlockNumber) -> Result<u128, DispatchError>;
+	fn service_punish(miner: &AccountId, idle_space: u128, service_space: u128, fault_at: BlockNumber) -> Result<u128, DispatchError>;
+	/// Database weight of `calculate_miner_reward` for `miner`, which grows with its delegators.
+	fn reward_weight(miner: &AccountId) -> Weight;
+	/// Database weight of a punishment of `miner`, which grows with its delegators.
+	fn punish_weight(miner: &AccountId) -> Weight;
 
 	fn execute_exit(acc: &AccountId) -> DispatchResult;
 	fn withdraw(acc: &AccountId) -> DispatchResult;
@@ -924,11 +2023,11 @@ pub trait MinerControl<AccountId> {
 
 	fn is_positive(miner: &AccountId) -> Result<bool, DispatchError>;
 	fn is_lock(miner: &AccountId) -> Result<bool, DispatchError>;
-	fn update_miner_state(miner: &AccountId, state: &str) -> DispatchResult;
+	fn update_miner_state(miner: &AccountId, state: MinerState) -> DispatchResult;
 	fn test_update_miner_idle_space(acc: &AccountId, space: u128) -> DispatchResult;
 }
 
-impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId> for Pallet<T> {
+impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId, BlockNumberOf<T>> for Pallet<T> {
 	fn add_miner_idle_space(acc: &<T as frame_system::Config>::AccountId, power: u128) -> DispatchResult {
 		Pallet::<T>::add_miner_idle_space(acc, power)?;
 		Ok(())
@@ -972,13 +2071,13 @@ impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId> for Pallet<
 		false
 	}
 
-	fn get_miner_state(acc: &AccountOf<T>) -> Result<Vec<u8>, DispatchError> {
+	fn get_miner_state(acc: &AccountOf<T>) -> Result<MinerState, DispatchError> {
 		let miner = <MinerItems<T>>::try_get(acc).map_err(|_| Error::<T>::NotMiner)?;
-		Ok(miner.state.to_vec())
+		Ok(miner.state)
 	}
 
-	fn get_all_miner() -> Result<Vec<AccountOf<T>>, DispatchError> {
-		Ok(AllMiner::<T>::get().to_vec())
+	fn get_miner_by_index(index: u32) -> Option<AccountOf<T>> {
+		<MinerByIndex<T>>::get(index)
 	}
 
 	fn lock_space(acc: &AccountOf<T>, space: u128) -> DispatchResult {
@@ -1009,7 +2108,27 @@ impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId> for Pallet<
 	}
 
 	fn get_miner_count() -> u32 {
-		<MinerItems<T>>::count()
+		<MinerRegistryLen<T>>::get()
+	}
+
+	fn get_miner_region(acc: &AccountOf<T>) -> Option<RegionCode> {
+		<MinerMeta<T>>::get(acc).map(|meta| meta.region)
+	}
+
+	fn get_miner_multiaddrs(acc: &AccountOf<T>) -> Vec<Multiaddr> {
+		<MinerMeta<T>>::get(acc).map(|meta| meta.multiaddrs.into_inner()).unwrap_or_default()
+	}
+
+	fn get_declared_space(acc: &AccountOf<T>) -> u128 {
+		<MinerMeta<T>>::get(acc).map(|meta| meta.declared_space).unwrap_or_default()
+	}
+
+	fn stash_of_controller(acc: &AccountOf<T>) -> Option<AccountOf<T>> {
+		Self::stash_of_controller(acc)
+	}
+
+	fn stash_of_worker(acc: &AccountOf<T>) -> Option<AccountOf<T>> {
+		Self::stash_of_worker(acc)
 	}
 
 	fn get_reward() -> u128 {
@@ -1023,7 +2142,7 @@ impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId> for Pallet<
 		total_service_space: u128,
 		miner_idle_space: u128,
 		miner_service_space: u128,
-	) -> DispatchResult {
+	) -> Result<u128, DispatchError> {
 		Self::calculate_miner_reward(
 			miner, 
 			total_reward, 
@@ -1038,49 +2157,52 @@ impl<T: Config> MinerControl<<T as frame_system::Config>::AccountId> for Pallet<
 		miner: &AccountOf<T>, 
 		level: u8, 
 		idle_space: u128, 
-		service_space: u128
-	) -> DispatchResult {
-		Self::clear_punish(miner, level, idle_space, service_space)
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
+		Self::clear_punish(miner, level, idle_space, service_space, fault_at)
 	}
 
 	fn idle_punish(
 		miner: &AccountOf<T>, 
 		idle_space: u128, 
-		service_space: u128
-	) -> DispatchResult {
-		Self::idle_punish(miner, idle_space, service_space)
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
+		Self::idle_punish(miner, idle_space, service_space, fault_at)
 	}
 
 	fn service_punish(
 		miner: &AccountOf<T>, 
 		idle_space: u128, 
-		service_space: u128
-	) -> DispatchResult {
-		Self::service_punish(miner, idle_space, service_space)
+		service_space: u128,
+		fault_at: BlockNumberOf<T>,
+	) -> Result<u128, DispatchError> {
+		Self::service_punish(miner, idle_space, service_space, fault_at)
+	}
+
+	fn reward_weight(miner: &AccountOf<T>) -> Weight {
+		Self::reward_weight(miner)
+	}
+
+	fn punish_weight(miner: &AccountOf<T>) -> Weight {
+		Self::punish_weight(miner)
 	}
 
 	fn is_positive(miner: &AccountOf<T>) -> Result<bool, DispatchError> {
 		let state = Self::get_miner_state(miner)?;
-		Ok(state == STATE_POSITIVE.as_bytes().to_vec())
+		Ok(state == MinerState::Positive)
 	}
 
 	fn is_lock(miner: &AccountOf<T>) -> Result<bool, DispatchError> {
 		let state = Self::get_miner_state(miner)?;
-		Ok(state == STATE_LOCK.as_bytes().to_vec())
+		Ok(state == MinerState::Lock)
 	}
 
-	fn update_miner_state(miner: &AccountOf<T>, state: &str) -> DispatchResult {
-		let state = match state {
-			STATE_POSITIVE | STATE_FROZEN | STATE_EXIT | STATE_LOCK => state.as_bytes().to_vec(),
-			_ => Err(Error::<T>::Overflow)?,
-		};
-
+	fn update_miner_state(miner: &AccountOf<T>, state: MinerState) -> DispatchResult {
 		<MinerItems<T>>::try_mutate(miner, |miner_opt| -> DispatchResult {
 			let miner_info = miner_opt.as_mut().ok_or(Error::<T>::NotMiner)?;
-
-			miner_info.state = state.try_into().map_err(|_| Error::<T>::BoundedVecError)?;
-
-			Ok(())
+			Self::transition_state(miner, miner_info, state)
 		})
 	}
 
@@ -0,0 +1,172 @@
+//! Storage migrations for the Sminer pallet.
+
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
+
+pub mod v1 {
+	use super::*;
+
+	#[derive(Decode, Encode)]
+	struct OldMinerInfo<AccountId, Balance, BoundedString> {
+		beneficiary: AccountId,
+		peer_id: PeerId,
+		collaterals: Balance,
+		debt: Balance,
+		state: BoundedString,
+		idle_space: u128,
+		service_space: u128,
+		lock_space: u128,
+	}
+
+	// Maps the byte string states used before v1. Unknown values, such as the
+	// retired `e_frozen`, become frozen so the miner has to top up to recover.
+	fn state_from_bytes(state: &[u8]) -> MinerState {
+		match state {
+			b"positive" => MinerState::Positive,
+			b"lock" => MinerState::Lock,
+			b"exit" => MinerState::Exit,
+			b"frozen" => MinerState::Frozen,
+			other => {
+				log::warn!("unknown miner state {:?}, migrated as frozen", other);
+				MinerState::Frozen
+			},
+		}
+	}
+
+	/// Replaces the byte string `MinerInfo.state` with `MinerState`.
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::warn!("Skipping sminer v1 migration, should be removed");
+				return T::DbWeight::get().reads(1);
+			}
+
+			let mut translated: u64 = 0;
+			<MinerItems<T>>::translate::<OldMinerInfo<AccountOf<T>, BalanceOf<T>, BoundedVec<u8, T::ItemLimit>>, _>(
+				|_acc, old| {
+					translated += 1;
+					Some(MinerInfo {
+						beneficiary: old.beneficiary,
+						peer_id: old.peer_id,
+						collaterals: old.collaterals,
+						debt: old.debt,
+						state: state_from_bytes(&old.state),
+						idle_space: old.idle_space,
+						service_space: old.service_space,
+						lock_space: old.lock_space,
+					})
+				},
+			);
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			log::info!("sminer v1 applied, {} miners migrated", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 1, "sminer v1 not applied");
+			Ok(())
+		}
+	}
+}
+
+pub mod v2 {
+	use super::*;
+
+	// Tranche count the old reward orders were released in.
+	const OLD_RELEASE_NUMBER: u8 = 2;
+
+	#[derive(Decode, Encode)]
+	struct OldRewardOrder<Balance> {
+		order_reward: Balance,
+		each_share: Balance,
+		award_count: u8,
+		has_issued: bool,
+	}
+
+	#[derive(Decode, Encode)]
+	struct OldReward<Balance> {
+		total_reward: Balance,
+		reward_issued: Balance,
+		currently_available_reward: Balance,
+		order_list: Vec<OldRewardOrder<Balance>>,
+	}
+
+	/// Moves reward orders to the block based vesting schedule.
+	///
+	/// Tranches still outstanding under the old schedule are released right away, so
+	/// every miner starts v2 with an empty order list.
+	pub struct MigrateToV2<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				log::warn!("Skipping sminer v2 migration, should be removed");
+				return T::DbWeight::get().reads(1);
+			}
+
+			let mut translated: u64 = 0;
+			<RewardMap<T>>::translate::<OldReward<BalanceOf<T>>, _>(|_acc, old| {
+				translated += 1;
+				let mut available = old.currently_available_reward;
+				for order in old.order_list.iter() {
+					let remaining = OLD_RELEASE_NUMBER.saturating_sub(order.award_count);
+					available = available.saturating_add(order.each_share.saturating_mul(remaining.into()));
+				}
+				Some(Reward::<T> {
+					total_reward: old.total_reward,
+					reward_issued: old.reward_issued,
+					currently_available_reward: available,
+					order_list: Default::default(),
+				})
+			});
+			StorageVersion::new(2).put::<Pallet<T>>();
+
+			log::info!("sminer v2 applied, {} reward records migrated", translated);
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 2, "sminer v2 not applied");
+			Ok(())
+		}
+	}
+}
+
+pub mod v3 {
+	use super::*;
+	use frame_support::{storage::migration::take_storage_value, traits::PalletInfoAccess};
+
+	/// Moves the miners in the `AllMiner` vector into the indexed registry.
+	pub struct MigrateToV3<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 2 {
+				log::warn!("Skipping sminer v3 migration, should be removed");
+				return T::DbWeight::get().reads(1);
+			}
+
+			let all_miner: Vec<AccountOf<T>> =
+				take_storage_value(<Pallet<T>>::name().as_bytes(), b"AllMiner", &[]).unwrap_or_default();
+			let mut migrated: u64 = 0;
+			for (index, miner) in all_miner.into_iter().enumerate() {
+				<MinerIndex<T>>::insert(&miner, index as u32);
+				<MinerByIndex<T>>::insert(index as u32, miner);
+				migrated += 1;
+			}
+			<MinerRegistryLen<T>>::put(migrated as u32);
+			StorageVersion::new(3).put::<Pallet<T>>();
+
+			log::info!("sminer v3 applied, {} miners indexed", migrated);
+			T::DbWeight::get().reads_writes(2, migrated * 2 + 3)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 3, "sminer v3 not applied");
+			Ok(())
+		}
+	}
+}
@@ -32,8 +32,6 @@ use sp_runtime::{
 	Perbill,
 };
 
-use std::convert::{ TryFrom };
-
 type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
 type Block = frame_system::mocking::MockBlock<Test>;
 pub type AccountId = u64;
@@ -143,9 +141,20 @@ parameter_types! {
 	pub const ItemLimit: u32 = 32;
 	pub const MultipleFines: u8 = 7;
 	pub const DepositBufferPeriod: u32 = 3;
-	pub const OneDay: u32 = 14400;
+	pub const OneDay: u64 = 14400;
 	pub const MaxAward: u128 = 1_306_849_000_000_000_000;
 	pub const LockInPeriod: u8 = 2;
+	pub const ChallengeMinerMax: u32 = 8000;
+	pub const PayoutBatchSize: u32 = 10;
+	pub const RewardImmediateShare: Perbill = Perbill::from_percent(20);
+	pub const RewardReleaseNumber: u32 = 2;
+	pub const RewardReleaseInterval: u64 = 10;
+	pub const MaxRewardOrders: u32 = 4;
+	pub const MinerUnbondingPeriod: u64 = 10;
+	pub const MinerMaxUnlockingChunks: u32 = 4;
+	pub const MaxDelegators: u32 = 4;
+	pub const MaxCommission: Perbill = Perbill::from_percent(50);
+	pub const MaxMultiaddrs: u32 = 4;
 }
 
 impl Config for Test {
@@ -163,6 +172,17 @@ impl Config for Test {
 	type AScheduler = Scheduler;
 	type LockInPeriod = LockInPeriod;
 	type MaxAward = MaxAward;
+	type ChallengeMinerMax = ChallengeMinerMax;
+	type PayoutBatchSize = PayoutBatchSize;
+	type RewardImmediateShare = RewardImmediateShare;
+	type RewardReleaseNumber = RewardReleaseNumber;
+	type RewardReleaseInterval = RewardReleaseInterval;
+	type MaxRewardOrders = MaxRewardOrders;
+	type UnbondingPeriod = MinerUnbondingPeriod;
+	type MaxUnlockingChunks = MinerMaxUnlockingChunks;
+	type MaxDelegators = MaxDelegators;
+	type MaxCommission = MaxCommission;
+	type MaxMultiaddrs = MaxMultiaddrs;
 }
 
 pub mod consts {
@@ -19,14 +19,10 @@
 
 use super::*;
 use frame_benchmarking::account;
-use frame_support::{assert_noop, assert_ok, traits::Len};
+use frame_support::{assert_err, assert_noop, assert_ok};
 use mock::{consts::*, new_test_ext, run_to_block, Balances, RuntimeOrigin, Sminer, System as Sys, Test};
 
 const UNIT_POWER_LIMIT: u128 = 2000_000_000_000_000u128;
-const STATE_POSITIVE: &str = "positive";
-const STATE_FROZEN: &str = "frozen";
-const STATE_EXIT_FROZEN: &str = "e_frozen";
-const STATE_EXIT: &str = "exit";
 
 #[test]
 fn miner_register_works() {
@@ -36,14 +32,14 @@ fn miner_register_works() {
 
 		let beneficiary = account::<mock::AccountId>("beneficiary", 0, 0);
 		let beneficiary_new = account::<mock::AccountId>("beneficiary_new", 0, 0);
-		let stake_amount: u128 = 2000;
-		let ip: IpAddress = IpAddress::IPV4([127,0,0,1],15000);
-		let ip_new: IpAddress = IpAddress::IPV4([127,0,0,1],15001);
+		let stake_amount: u128 = UNIT_POWER_LIMIT;
+		let peer_id: PeerId = [1u8; 38];
+		let peer_id_new: PeerId = [2u8; 38];
 
 		assert_ok!(Sminer::regnstk(
 			RuntimeOrigin::signed(ACCOUNT1.0),
 			beneficiary,
-			ip.clone(),
+			peer_id,
 			stake_amount
 		));
 
@@ -54,12 +50,9 @@ fn miner_register_works() {
 		//miner item check
 		let mr = &MinerItems::<Test>::get(ACCOUNT1.0).unwrap();
 		assert_eq!(stake_amount, mr.collaterals);
-		assert_eq!(Vec::from(STATE_POSITIVE), mr.state.to_vec());
-		assert!(mr.peer_id > 0);
+		assert_eq!(MinerState::Positive, mr.state);
 
-		assert_eq!(mr.peer_id, PeerIndex::<Test>::try_get().unwrap());
-
-		assert!(AllMiner::<Test>::try_get().unwrap().len() > 0);
+		assert_eq!(Some(ACCOUNT1.0), MinerByIndex::<Test>::get(MinerIndex::<Test>::get(ACCOUNT1.0).unwrap()));
 
 		let event =
 			Sys::events().pop().expect("Expected at least one Registered to be found").event;
@@ -79,14 +72,14 @@ fn miner_register_works() {
 			event
 		);
 
-		assert_eq!(ip, mr.ip);
-		assert_ok!(Sminer::update_ip(RuntimeOrigin::signed(ACCOUNT1.0), ip_new.clone()));
+		assert_eq!(peer_id, mr.peer_id);
+		assert_ok!(Sminer::update_peer_id(RuntimeOrigin::signed(ACCOUNT1.0), peer_id_new));
 		let mr = &MinerItems::<Test>::get(ACCOUNT1.0).unwrap();
-		assert_eq!(ip_new, mr.ip);
+		assert_eq!(peer_id_new, mr.peer_id);
 		let event =
 			Sys::events().pop().expect("Expected at least one Registered to be found").event;
 		assert_eq!(
-			mock::RuntimeEvent::from(Event::UpdataIp { acc: ACCOUNT1.0, old: ip, new: ip_new }),
+			mock::RuntimeEvent::from(Event::UpdataIp { acc: ACCOUNT1.0, old: peer_id, new: peer_id_new }),
 			event
 		);
 	});
@@ -103,19 +96,19 @@ fn increase_collateral_works_on_normal_state() {
 		assert_ok!(Sminer::regnstk(
 			RuntimeOrigin::signed(ACCOUNT1.0),
 			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			2000
+			[0u8; 38],
+			UNIT_POWER_LIMIT
 		));
 
 		assert_ok!(Sminer::increase_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 3000));
 		// balance check
-		assert_eq!(ACCOUNT1.1 - 2000 - 3000, Balances::free_balance(&ACCOUNT1.0));
-		assert_eq!(2000 + 3000, Balances::reserved_balance(&ACCOUNT1.0));
+		assert_eq!(ACCOUNT1.1 - UNIT_POWER_LIMIT - 3000, Balances::free_balance(&ACCOUNT1.0));
+		assert_eq!(UNIT_POWER_LIMIT + 3000, Balances::reserved_balance(&ACCOUNT1.0));
 
 		let mi = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(Vec::from(STATE_POSITIVE), mi.state.to_vec());
+		assert_eq!(MinerState::Positive, mi.state);
 
-		assert_eq!(2000 + 3000, mi.collaterals);
+		assert_eq!(UNIT_POWER_LIMIT + 3000, mi.collaterals);
 
 		let event = Sys::events()
 			.pop()
@@ -131,10 +124,10 @@ fn increase_collateral_works_on_normal_state() {
 	});
 }
 
-fn set_miner_state(account_id: u64, state: &str) {
+fn set_miner_state(account_id: u64, state: MinerState) {
 	let _ = MinerItems::<Test>::try_mutate(account_id, |opt| -> DispatchResult {
 		let mr = opt.as_mut().unwrap();
-		mr.state = Vec::from(state).try_into().unwrap();
+		mr.state = state;
 		Ok(())
 	});
 }
@@ -142,98 +135,54 @@ fn set_miner_state(account_id: u64, state: &str) {
 #[test]
 fn increase_collateral_works_on_frozen_state() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			2000
-		));
-		set_miner_state(ACCOUNT1.0, STATE_FROZEN);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		// 4 TiB of idle space is 1.2 TiB of power, which needs twice the base collateral
+		MinerItems::<Test>::mutate(ACCOUNT1.0, |miner| miner.as_mut().unwrap().idle_space = 4 * T_BYTE);
+		set_miner_state(ACCOUNT1.0, MinerState::Frozen);
 
 		assert_eq!(UNIT_POWER_LIMIT, Sminer::check_collateral_limit(0u128).unwrap());
 		assert_ok!(Sminer::increase_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 3000));
 		assert_eq!(
-			Vec::from(STATE_FROZEN),
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state.to_vec()
+			MinerState::Frozen,
+			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state
 		);
 
 		assert_ok!(Sminer::increase_collateral(
 			RuntimeOrigin::signed(ACCOUNT1.0),
-			2 * UNIT_POWER_LIMIT + 1
+			UNIT_POWER_LIMIT
 		));
 		assert_eq!(
-			Vec::from(STATE_POSITIVE),
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state.to_vec()
+			MinerState::Positive,
+			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state
 		);
 	});
 }
 
 #[test]
-fn increase_collateral_works_on_exit_frozen_state() {
+fn execute_exit_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			2000
-		));
-		set_miner_state(ACCOUNT1.0, STATE_EXIT_FROZEN);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
 
-		assert_ok!(Sminer::increase_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 3000));
-		assert_eq!(
-			Vec::from(STATE_EXIT_FROZEN),
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state.to_vec()
-		);
+		assert_ok!(<Sminer as MinerControl<_, _>>::update_miner_state(&ACCOUNT1.0, MinerState::Lock));
+		assert_ok!(<Sminer as MinerControl<_, _>>::execute_exit(&ACCOUNT1.0));
 
-		assert_ok!(Sminer::increase_collateral(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			2 * UNIT_POWER_LIMIT + 1
-		));
 		assert_eq!(
-			Vec::from(STATE_EXIT),
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state.to_vec()
+			MinerState::Exit,
+			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state
 		);
+		assert_eq!(0, MinerRegistryLen::<Test>::get());
+		assert!(!RewardMap::<Test>::contains_key(ACCOUNT1.0));
 	});
 }
 
 #[test]
-fn exit_miner_works() {
+fn execute_exit_on_abnormal_state() {
 	new_test_ext().execute_with(|| {
-		assert_noop!(Sminer::exit_miner(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::NotMiner);
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-
-		Sys::set_block_number(2);
-		assert_ok!(Sminer::exit_miner(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
 
-		assert_eq!(
-			Vec::from(STATE_EXIT),
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().state.to_vec()
-		);
-		assert_eq!(2, MinerLockIn::<Test>::try_get(ACCOUNT1.0).unwrap());
-		let event = Sys::events().pop().expect("Expected at least one MinerExit to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::MinerExit { acc: ACCOUNT1.0 }), event);
-	});
-}
-
-#[test]
-fn exit_miner_on_abnormal_state() {
-	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-
-		set_miner_state(ACCOUNT1.0, STATE_EXIT);
-		assert_noop!(
-			Sminer::exit_miner(RuntimeOrigin::signed(ACCOUNT1.0)),
-			Error::<Test>::NotpositiveState
+		assert_err!(
+			<Sminer as MinerControl<_, _>>::execute_exit(&ACCOUNT1.0),
+			Error::<Test>::InvalidStateTransition
 		);
 	});
 }
@@ -241,411 +190,469 @@ fn exit_miner_on_abnormal_state() {
 #[test]
 fn withdraw_should_work() {
 	new_test_ext().execute_with(|| {
-		assert_noop!(Sminer::withdraw(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::NotMiner);
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1], 15000),
-			UNIT_POWER_LIMIT
-		));
+		assert_noop!(<Sminer as MinerControl<_, _>>::withdraw(&ACCOUNT1.0), Error::<Test>::NotMiner);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
 
 		let free_balance_after_reg = Balances::free_balance(&ACCOUNT1.0);
 
-		// the miner should exit before withdraw
-		assert_noop!(Sminer::withdraw(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::NotExisted);
-		assert_ok!(Sminer::exit_miner(RuntimeOrigin::signed(ACCOUNT1.0)));
-		// the miner should withdraw after 1200 blocks
-		assert_noop!(Sminer::withdraw(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::LockInNotOver);
-
-		let all_miner_cnt = AllMiner::<Test>::try_get().unwrap().len();
-
-		Sys::set_block_number(57700);
-		assert_ok!(Sminer::withdraw(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_ok!(<Sminer as MinerControl<_, _>>::update_miner_state(&ACCOUNT1.0, MinerState::Lock));
+		assert_ok!(<Sminer as MinerControl<_, _>>::execute_exit(&ACCOUNT1.0));
+		assert_ok!(<Sminer as MinerControl<_, _>>::withdraw(&ACCOUNT1.0));
 
 		// balance check
 		assert_eq!(free_balance_after_reg + UNIT_POWER_LIMIT, Balances::free_balance(&ACCOUNT1.0));
 		assert_eq!(0, Balances::reserved_balance(&ACCOUNT1.0));
-
-		assert_eq!(all_miner_cnt - 1, AllMiner::<Test>::try_get().unwrap().len());
 		assert!(!MinerItems::<Test>::contains_key(ACCOUNT1.0));
-
-		// event check
-		let event =
-			Sys::events().pop().expect("Expected at least one MinerClaim to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::MinerClaim { acc: ACCOUNT1.0 }), event);
 	});
 }
 
+const FIXED_CHARGE_AMOUNT: u128 = 10000000000000000u128;
 #[test]
-fn add_reward_order1_should_work() {
+fn faucet_should_work() {
 	new_test_ext().execute_with(|| {
-		let reward_amount = 100000_u128;
-		// first reward
-		Sys::set_block_number(1);
-		assert_ok!(Sminer::add_reward_order1(&ACCOUNT1.0, reward_amount));
-		let reward_orders = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(1, reward_orders.len());
-		let ro = reward_orders.get(0).unwrap();
-		assert_eq!(reward_amount, ro.calculate_reward);
-		assert_eq!(1, ro.start_t);
-		assert_eq!(2592000u64 + 1u64, ro.deadline); // FIXME! the 1296000 is hardcode in the tested function
-											// second reward
-		Sys::set_block_number(2);
-		assert_ok!(Sminer::add_reward_order1(&ACCOUNT1.0, reward_amount));
-		let reward_orders = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(2, reward_orders.len());
-		let ro = reward_orders.get(1).unwrap();
-		assert_eq!(reward_amount, ro.calculate_reward);
-		assert_eq!(2, ro.start_t);
-
-		assert_eq!(2592000 + 2u64, ro.deadline);
+		assert_ok!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777));
+		assert_eq!(FIXED_CHARGE_AMOUNT, Balances::free_balance(&777));
+
+		//FIXME! the assert_noop! not work, why?
+		//assert_noop!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777), Error::<Test>::LessThan24Hours);
+		if let Err(e) = Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777) {
+			if let DispatchError::Module(m) = e {
+				assert_eq!("LessThan24Hours", m.message.unwrap());
+			}
+		}
+		Sys::set_block_number(1u64 + 28800u64);
+		assert_ok!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777));
 	});
 }
 
 #[test]
-fn add_reward_order1_should_panic_on_exceed_item_limit() {
-	new_test_ext().execute_with(|| {
-		let reward_amount = 100000_u128;
-		let n = mock::ItemLimit::get();
-		for i in 1..(n + 1) {
-			Sys::set_block_number(i as u64);
-			assert_ok!(Sminer::add_reward_order1(&ACCOUNT1.0, reward_amount));
-		}
-		assert_eq!(n as usize, CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0).unwrap().len());
-		//FIXME! Raise a panic? Is it appropriate in a production environment?
-		let _ = Sminer::add_reward_order1(&ACCOUNT1.0, reward_amount);
-	});
+fn miner_state_follows_transition_table() {
+	assert!(MinerState::Positive.can_transition_to(MinerState::Frozen));
+	assert!(MinerState::Positive.can_transition_to(MinerState::Lock));
+	assert!(MinerState::Frozen.can_transition_to(MinerState::Positive));
+	assert!(MinerState::Lock.can_transition_to(MinerState::Exit));
+
+	assert!(!MinerState::Exit.can_transition_to(MinerState::Positive));
+	assert!(!MinerState::Frozen.can_transition_to(MinerState::Lock));
+	assert!(!MinerState::Lock.can_transition_to(MinerState::Frozen));
+	assert!(!MinerState::Positive.can_transition_to(MinerState::Positive));
 }
 
 #[test]
-fn add_power_should_work() {
+fn update_miner_state_rejects_invalid_transition() {
 	new_test_ext().execute_with(|| {
 		assert_ok!(Sminer::regnstk(
 			RuntimeOrigin::signed(ACCOUNT1.0),
 			123,
-			IpAddress::IPV4([127,0,0,1],15000),
+			[0u8; 38],
 			UNIT_POWER_LIMIT
 		));
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(0, m1.power);
-		let _ = Sminer::add_power(&m1.peer_id, 10_000);
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(10_000, m1.power);
-		assert_eq!(10_000, TotalIdleSpace::<Test>::try_get().unwrap());
 
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT2.0),
-			321,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-		let m2 = MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap();
-		assert_eq!(0, m2.power);
-		let _ = Sminer::add_power(&m2.peer_id, 20_000);
-		let m2 = MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap();
-		assert_eq!(20_000, m2.power);
-		assert_eq!(30_000, TotalIdleSpace::<Test>::try_get().unwrap());
+		assert_ok!(<Sminer as MinerControl<_, _>>::update_miner_state(&ACCOUNT1.0, MinerState::Lock));
+		let event = Sys::events().pop().expect("Expected at least one MinerStateChanged to be found").event;
+		assert_eq!(
+			mock::RuntimeEvent::from(Event::MinerStateChanged {
+				acc: ACCOUNT1.0,
+				from: MinerState::Positive,
+				to: MinerState::Lock,
+			}),
+			event
+		);
+
+		assert_ok!(<Sminer as MinerControl<_, _>>::update_miner_state(&ACCOUNT1.0, MinerState::Exit));
+		assert_noop!(
+			<Sminer as MinerControl<_, _>>::update_miner_state(&ACCOUNT1.0, MinerState::Positive),
+			Error::<Test>::InvalidStateTransition
+		);
 	});
 }
 
-/// 750_000TCESS
-const FIXED_REWARD_AMOUNT: u128 = 750_000_000000000000_u128;
+fn credit_reward(miner: u64, amount: u128) {
+	let reward_pot: u64 = mock::RewardPalletId::get().into_account_truncating();
+	let _ = Balances::deposit_creating(&reward_pot, amount * 2);
+	let _ = RewardMap::<Test>::try_mutate(miner, |opt| -> DispatchResult {
+		opt.as_mut().unwrap().currently_available_reward = amount;
+		Ok(())
+	});
+}
 
 #[test]
-fn increase_rewards_should_work() {
+fn rewards_are_paid_to_beneficiary() {
 	new_test_ext().execute_with(|| {
-		CurrencyReward::<Test>::put(750_000_000_000_000_000);
-		assert_noop!(Sminer::timed_increase_rewards(RuntimeOrigin::root()), Error::<Test>::DivideByZero);
+		let beneficiary = 123;
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), beneficiary, [0u8; 38], UNIT_POWER_LIMIT));
+		credit_reward(ACCOUNT1.0, 1000);
 
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT2.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let m2 = MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap();
-		let _ = Sminer::add_power(&m1.peer_id, 10_000);
-		let _ = Sminer::add_power(&m2.peer_id, 20_000);
-
-		let total_power = TotalIdleSpace::<Test>::try_get().unwrap();
-		assert_eq!(30_000, total_power);
-
-		assert_ok!(Sminer::timed_increase_rewards(RuntimeOrigin::root()));
-
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let m2 = MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap();
-		assert_eq!(10_000, m1.power);
-		assert_eq!(20_000, m2.power);
-
-		// the timed_increase_rewards() not reward actual, just make reward order only
-		let reward_order1 = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0)
-			.unwrap()
-			.to_vec()
-			.pop()
-			.unwrap();
-		assert_eq!(FIXED_REWARD_AMOUNT * m1.power / total_power, reward_order1.calculate_reward);
-		let reward_order2 = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT2.0)
-			.unwrap()
-			.to_vec()
-			.pop()
-			.unwrap();
-		assert_eq!(FIXED_REWARD_AMOUNT * m2.power / total_power, reward_order2.calculate_reward);
+		let miner_balance = Balances::free_balance(&ACCOUNT1.0);
+		assert_ok!(Sminer::receive_reward(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_eq!(1000, Balances::free_balance(&beneficiary));
+		assert_eq!(miner_balance, Balances::free_balance(&ACCOUNT1.0));
+		assert_eq!(1000, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().reward_issued);
 
-		// event check
-		let event = Sys::events().pop().expect("Expected at least one TimedTask to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::TimedTask()), event);
+		assert_noop!(Sminer::receive_reward(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::NoReward);
 	});
 }
 
 #[test]
-fn task_award_table_should_work() {
+fn auto_payout_pays_opted_in_miners() {
 	new_test_ext().execute_with(|| {
-		CurrencyReward::<Test>::put(750_000_000_000_000_000);
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT2.0),
-			321,
-			IpAddress::IPV4([127,0,0,1],15001),
-			UNIT_POWER_LIMIT
-		));
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT2.0), 456, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::set_auto_payout(RuntimeOrigin::signed(ACCOUNT1.0), true));
+
+		// both miners hold half the power, 20% of each half is available right away
+		CurrencyReward::<Test>::put(2000);
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 2000, 200, 0, 100, 0));
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT2.0, 2000, 200, 0, 100, 0));
+		assert!(PendingPayout::<Test>::contains_key(ACCOUNT1.0));
+		assert!(!PendingPayout::<Test>::contains_key(ACCOUNT2.0));
+
+		<Sminer as frame_support::traits::Hooks<u64>>::on_initialize(2);
+
+		assert_eq!(200, Balances::free_balance(&123));
+		assert_eq!(0, Balances::free_balance(&456));
+		assert!(!PendingPayout::<Test>::contains_key(ACCOUNT1.0));
+		assert_eq!(200, RewardMap::<Test>::get(ACCOUNT2.0).unwrap().currently_available_reward);
+	});
+}
 
-		let _ = Sminer::add_power(&ACCOUNT1.0, 10_000);
-		let _ = Sminer::add_power(&ACCOUNT2.0, 20_000);
-		let total_power = TotalIdleSpace::<Test>::try_get().unwrap();
+#[test]
+fn round_reward_vests_by_block() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		let reward_pot: u64 = mock::RewardPalletId::get().into_account_truncating();
+		let _ = Balances::deposit_creating(&reward_pot, 10_000);
+		CurrencyReward::<Test>::put(1000);
+
+		// the miner holds all the power, 20% is available now and the rest vests in two tranches
+		// counted from the end of the current release interval
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0));
+		assert_eq!(200, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().currently_available_reward);
+		assert_eq!(vec![(20, 400), (30, 400)], Sminer::reward_unlocks(&ACCOUNT1.0));
+
+		run_to_block(19);
+		assert_ok!(Sminer::receive_reward(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_eq!(200, Balances::free_balance(&123));
+
+		run_to_block(20);
+		assert_eq!(vec![(20, 400), (30, 400)], Sminer::reward_unlocks(&ACCOUNT1.0));
+		assert_ok!(Sminer::receive_reward(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_eq!(600, Balances::free_balance(&123));
+		assert_eq!(vec![(30, 400)], Sminer::reward_unlocks(&ACCOUNT1.0));
+
+		Sys::set_block_number(30);
+		assert_ok!(Sminer::receive_reward(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_eq!(1000, Balances::free_balance(&123));
+		assert!(RewardMap::<Test>::get(ACCOUNT1.0).unwrap().order_list.is_empty());
+		assert!(Sminer::reward_unlocks(&ACCOUNT1.0).is_empty());
+	});
+}
 
-		assert_eq!(30_000, total_power);
-		assert_ok!(Sminer::timed_increase_rewards(RuntimeOrigin::root())); // <-- dependency method
+#[test]
+fn rounds_of_one_release_interval_share_an_order() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		CurrencyReward::<Test>::put(3000);
+
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0));
+		Sys::set_block_number(10);
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0));
+		assert_eq!(1, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().order_list.len());
+		assert_eq!(vec![(20, 800), (30, 800)], Sminer::reward_unlocks(&ACCOUNT1.0));
+
+		Sys::set_block_number(11);
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0));
+		assert_eq!(2, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().order_list.len());
+		assert_eq!(vec![(20, 800), (30, 1200), (40, 400)], Sminer::reward_unlocks(&ACCOUNT1.0));
+	});
+}
 
-		assert_ok!(Sminer::timed_task_award_table(RuntimeOrigin::root())); // <-- target method here!
+#[test]
+fn full_reward_orders_are_never_released_early() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		CurrencyReward::<Test>::put(1000);
+
+		// only reachable with a MaxRewardOrders too small for the release schedule
+		RewardMap::<Test>::mutate(ACCOUNT1.0, |reward_info| {
+			let reward_info = reward_info.as_mut().unwrap();
+			for start in [100, 110, 120, 130] {
+				let order = RewardOrder { order_reward: 500, vesting: 400, each_share: 200, award_count: 0, release_number: 2, start };
+				reward_info.order_list.try_push(order).unwrap();
+			}
+		});
 
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let total_reward = FIXED_REWARD_AMOUNT * m1.power / total_power;
-		assert_eq!(
-			total_reward,
-			MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().reward_info.total_reward
+		assert_noop!(
+			Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0),
+			Error::<Test>::TooManyRewardOrders
 		);
+		assert_eq!(0, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().currently_available_reward);
+	});
+}
 
-		let ro1 = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0)
-			.unwrap()
-			.to_vec()
-			.pop()
-			.unwrap();
-		let rc1 = RewardClaimMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-		assert_eq!(total_reward, rc1.total_reward);
-		// 180 periods, 80% one period rewards(1/180) + 20% total rewards
-		assert_eq!(
-			ro1.calculate_reward * 8 / 10 / 180 + total_reward * 2 / 10,
-			rc1.current_availability
-		);
+#[test]
+fn rewards_settle_debt_and_collateral_before_payout() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, UNIT_POWER_LIMIT + 100, 1));
+		let miner_info = MinerItems::<Test>::get(ACCOUNT1.0).unwrap();
+		assert_eq!(0, miner_info.collaterals);
+		assert_eq!(100, miner_info.debt);
+		assert_eq!(MinerState::Frozen, miner_info.state);
+
+		// the immediate share covers the debt, a full collateral and 50 more
+		let round_reward = 5 * (UNIT_POWER_LIMIT + 150);
+		CurrencyReward::<Test>::put(round_reward);
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, round_reward, 100, 0, 100, 0));
+
+		let miner_info = MinerItems::<Test>::get(ACCOUNT1.0).unwrap();
+		assert_eq!(0, miner_info.debt);
+		assert_eq!(UNIT_POWER_LIMIT, miner_info.collaterals);
+		assert_eq!(MinerState::Positive, miner_info.state);
+		assert_eq!(UNIT_POWER_LIMIT, Balances::reserved_balance(&ACCOUNT1.0));
+		assert_eq!(50, RewardMap::<Test>::get(ACCOUNT1.0).unwrap().currently_available_reward);
+		Sys::assert_has_event(mock::RuntimeEvent::Sminer(Event::DebtRepaid { acc: ACCOUNT1.0, amount: 100, remaining: 0 }));
+		Sys::assert_has_event(mock::RuntimeEvent::Sminer(Event::CollateralToppedUp {
+			acc: ACCOUNT1.0,
+			amount: UNIT_POWER_LIMIT,
+			collaterals: UNIT_POWER_LIMIT,
+		}));
+	});
+}
 
-		let md = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap().reward_info;
-		assert_eq!(total_reward, md.total_reward);
-		assert_eq!(total_reward, md.total_not_receive);
-		assert_eq!(rc1.current_availability, md.total_rewards_currently_available);
-
-		// second reward period
-		Sys::set_block_number(1200);
-		assert_ok!(Sminer::timed_increase_rewards(RuntimeOrigin::root()));
-		assert_ok!(Sminer::timed_task_award_table(RuntimeOrigin::root()));
-		assert_eq!(2, CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0).unwrap().len());
-		let ro2 = CalculateRewardOrderMap::<Test>::try_get(ACCOUNT1.0)
-			.unwrap()
-			.to_vec()
-			.pop()
-			.unwrap();
-		let rc2 = RewardClaimMap::<Test>::try_get(ACCOUNT2.0).unwrap();
-		let total_reward = total_reward * 2;
-		assert_eq!(
-			total_reward,
-			MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap().reward_info.total_reward
-		);
-		assert_eq!(total_reward, rc2.total_reward);
-		let t = ro1.calculate_reward * 8 / 10 / 180 + ro2.calculate_reward * 8 / 10 / 180;
-		assert_eq!(
-			(rc1.current_availability + t + ro2.calculate_reward) * 2,
-			rc2.current_availability
+#[test]
+fn unbond_collateral_above_limit() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT + 1000));
+		assert_noop!(
+			Sminer::unbond_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 1001),
+			Error::<Test>::CollateralBelowLimit
 		);
+		assert_ok!(Sminer::unbond_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 600));
+		assert_eq!(UNIT_POWER_LIMIT + 400, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().collaterals);
+		assert_eq!(UNIT_POWER_LIMIT + 1000, Balances::reserved_balance(&ACCOUNT1.0));
+
+		assert_noop!(Sminer::withdraw_unbonded(RuntimeOrigin::signed(ACCOUNT1.0)), Error::<Test>::NothingToWithdraw);
+		Sys::set_block_number(11);
+		assert_ok!(Sminer::withdraw_unbonded(RuntimeOrigin::signed(ACCOUNT1.0)));
+		assert_eq!(UNIT_POWER_LIMIT + 400, Balances::reserved_balance(&ACCOUNT1.0));
+		assert!(!Unbonding::<Test>::contains_key(ACCOUNT1.0));
+	});
+}
 
-		let md = MinerItems::<Test>::try_get(ACCOUNT2.0).unwrap().reward_info;
-		assert_eq!(total_reward, md.total_reward);
-		assert_eq!(total_reward, md.total_not_receive);
-		assert_eq!(rc2.current_availability, md.total_rewards_currently_available);
-
-		// event check
-		let event = Sys::events().pop().expect("Expected at least one TimedTask to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::TimedTask()), event);
+#[test]
+fn unbonding_collateral_is_slashable_for_earlier_faults() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT + 1000));
+		Sys::set_block_number(5);
+		assert_ok!(Sminer::unbond_collateral(RuntimeOrigin::signed(ACCOUNT1.0), 1000));
+
+		// committed after the request, the chunk is out of reach and the rest becomes debt
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, UNIT_POWER_LIMIT + 100, 6));
+		assert_eq!(100, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().debt);
+		assert_eq!(1000, Unbonding::<Test>::get(ACCOUNT1.0)[0].value);
+
+		// committed before the request, the chunk is slashed
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, 300, 4));
+		assert_eq!(100, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().debt);
+		assert_eq!(700, Unbonding::<Test>::get(ACCOUNT1.0)[0].value);
+		assert_eq!(700, Balances::reserved_balance(&ACCOUNT1.0));
 	});
 }
 
 #[test]
-fn timed_user_receive_award1_should_work() {
+fn delegators_share_rewards_and_slashes() {
 	new_test_ext().execute_with(|| {
-		CurrencyReward::<Test>::put(750_000_000_000_000_000);
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT2.0),
-			321,
-			IpAddress::IPV4([127,0,0,1],15001),
-			UNIT_POWER_LIMIT
-		));
-		let _ = Sminer::add_power(&ACCOUNT1.0, 10_000);
-		let _ = Sminer::add_power(&ACCOUNT2.0, 20_000);
-		let total_power = TotalIdleSpace::<Test>::try_get().unwrap();
-		assert_eq!(30_000, total_power);
-
-		assert_ok!(Sminer::timed_increase_rewards(RuntimeOrigin::root())); // <-- dependency method
-		assert_ok!(Sminer::timed_task_award_table(RuntimeOrigin::root())); // <-- dependency method
-
-		let rc1 = RewardClaimMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let rc2 = RewardClaimMap::<Test>::try_get(ACCOUNT2.0).unwrap();
-		assert_eq!(250_000_000_000_000_000, rc1.total_reward);
-		assert_eq!(51111111111111111, rc1.current_availability);
-		assert_eq!(250000000000000000, rc1.total_not_receive);
-		assert_eq!(0, rc1.have_to_receive);
-		assert_eq!(500000000000000000, rc2.total_reward);
-		assert_eq!(102222222222222222, rc2.current_availability);
-		assert_eq!(500000000000000000, rc2.total_not_receive);
-		assert_eq!(0, rc2.have_to_receive);
-
-		let jackpot_balance = Balances::free_balance(&mock::RewardPalletId::get().into_account_truncating());
-		assert_ok!(Sminer::timed_user_receive_award1(RuntimeOrigin::root())); // <-- target method here!
-
-		let rc1b = RewardClaimMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let rc2b = RewardClaimMap::<Test>::try_get(ACCOUNT2.0).unwrap();
-		assert_eq!(rc1.total_reward, rc1b.total_reward);
-		assert_eq!(0, rc1b.current_availability);
-		assert_eq!(rc1.total_reward - rc1.current_availability, rc1b.total_not_receive);
-		assert_eq!(rc1.current_availability, rc1b.have_to_receive);
-		assert_eq!(rc2.total_reward, rc2b.total_reward);
-		assert_eq!(0, rc2b.current_availability);
-		assert_eq!(rc2.total_reward - rc2.current_availability, rc2b.total_not_receive);
-		assert_eq!(rc2.current_availability, rc2b.have_to_receive);
-
-		assert_eq!(rc1b.have_to_receive, Balances::free_balance(&123));
-		assert_eq!(rc2b.have_to_receive, Balances::free_balance(&321));
-		assert_eq!(
-			jackpot_balance - rc1b.have_to_receive - rc2b.have_to_receive,
-			Balances::free_balance(&mock::RewardPalletId::get().into_account_truncating())
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_noop!(
+			Sminer::set_commission(RuntimeOrigin::signed(ACCOUNT1.0), Perbill::from_percent(51)),
+			Error::<Test>::CommissionTooHigh
 		);
+		assert_ok!(Sminer::set_commission(RuntimeOrigin::signed(ACCOUNT1.0), Perbill::from_percent(10)));
+		assert_ok!(Sminer::delegate(RuntimeOrigin::signed(ACCOUNT2.0), ACCOUNT1.0, UNIT_POWER_LIMIT));
+		assert_eq!(2 * UNIT_POWER_LIMIT, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().collaterals);
+		assert_eq!(UNIT_POWER_LIMIT, Balances::reserved_balance(&ACCOUNT2.0));
+
+		// half of the reward belongs to the delegator, less 10% commission
+		CurrencyReward::<Test>::put(1000);
+		assert_ok!(Sminer::calculate_miner_reward(&ACCOUNT1.0, 1000, 100, 0, 100, 0));
+		assert_eq!(450, DelegatorReward::<Test>::get(ACCOUNT2.0));
+		let reward_info = RewardMap::<Test>::get(ACCOUNT1.0).unwrap();
+		assert_eq!(550, reward_info.total_reward);
+		assert_eq!(110, reward_info.currently_available_reward);
+
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, UNIT_POWER_LIMIT, 1));
+		assert_eq!(UNIT_POWER_LIMIT / 2, Balances::reserved_balance(&ACCOUNT1.0));
+		assert_eq!(UNIT_POWER_LIMIT / 2, Balances::reserved_balance(&ACCOUNT2.0));
+		assert_eq!(UNIT_POWER_LIMIT / 2, Delegators::<Test>::get(ACCOUNT1.0)[0].amount);
+
+		assert_ok!(Sminer::undelegate(RuntimeOrigin::signed(ACCOUNT2.0), ACCOUNT1.0, UNIT_POWER_LIMIT / 2));
+		assert!(Delegators::<Test>::get(ACCOUNT1.0).is_empty());
+		assert_eq!(UNIT_POWER_LIMIT / 2, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().collaterals);
+		Sys::set_block_number(11);
+		assert_ok!(Sminer::withdraw_unbonded(RuntimeOrigin::signed(ACCOUNT2.0)));
+		assert_eq!(0, Balances::reserved_balance(&ACCOUNT2.0));
+
+		let free = Balances::free_balance(&ACCOUNT2.0);
+		assert_ok!(Sminer::claim_delegator_reward(RuntimeOrigin::signed(ACCOUNT2.0)));
+		assert_eq!(free + 450, Balances::free_balance(&ACCOUNT2.0));
 	});
 }
 
 #[test]
-fn timed_user_receive_award1_total_award() {
+fn delegator_unbonding_is_slashable_for_earlier_faults() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			UNIT_POWER_LIMIT
-		));
-		let m1 = MinerItems::<Test>::try_get(ACCOUNT1.0).unwrap();
-		let _ = Sminer::add_power(&m1.peer_id, 10_000);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::delegate(RuntimeOrigin::signed(ACCOUNT2.0), ACCOUNT1.0, UNIT_POWER_LIMIT));
+		Sys::set_block_number(5);
+		assert_ok!(Sminer::undelegate(RuntimeOrigin::signed(ACCOUNT2.0), ACCOUNT1.0, UNIT_POWER_LIMIT));
+		assert_eq!(vec![ACCOUNT2.0], UnbondingDelegators::<Test>::get(ACCOUNT1.0).into_inner());
+
+		// committed before the request, the delegator's chunk covers what the collateral can't
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, UNIT_POWER_LIMIT + 300, 4));
+		assert_eq!(0, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().debt);
+		assert_eq!(UNIT_POWER_LIMIT - 300, DelegatorUnbonding::<Test>::get(ACCOUNT2.0)[0].value);
+		assert_eq!(UNIT_POWER_LIMIT - 300, Balances::reserved_balance(&ACCOUNT2.0));
+
+		// committed after the request, the chunk is out of reach
+		assert_ok!(Sminer::deposit_punish(&ACCOUNT1.0, 100, 6));
+		assert_eq!(100, MinerItems::<Test>::get(ACCOUNT1.0).unwrap().debt);
+		assert_eq!(UNIT_POWER_LIMIT - 300, DelegatorUnbonding::<Test>::get(ACCOUNT2.0)[0].value);
+
+		Sys::set_block_number(15);
+		assert_ok!(Sminer::withdraw_unbonded(RuntimeOrigin::signed(ACCOUNT2.0)));
+		assert_eq!(0, Balances::reserved_balance(&ACCOUNT2.0));
+		assert!(!DelegatorUnbonding::<Test>::contains_key(ACCOUNT2.0));
+		assert!(!UnbondingDelegators::<Test>::contains_key(ACCOUNT1.0));
+	});
+}
 
-		assert_ok!(Sminer::timed_increase_rewards(RuntimeOrigin::root()));
+#[test]
+fn miner_cannot_unbond_delegated_collateral() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::delegate(RuntimeOrigin::signed(ACCOUNT2.0), ACCOUNT1.0, 2 * UNIT_POWER_LIMIT));
+		assert_noop!(
+			Sminer::unbond_collateral(RuntimeOrigin::signed(ACCOUNT1.0), UNIT_POWER_LIMIT + 1),
+			Error::<Test>::NotEnoughOwnCollateral
+		);
+	});
+}
 
-		for _ in 0..180 {
-			assert_ok!(Sminer::timed_task_award_table(RuntimeOrigin::root()));
-			assert_ok!(Sminer::timed_user_receive_award1(RuntimeOrigin::root()));
-			let rc1a = RewardClaimMap::<Test>::try_get(ACCOUNT1.0).unwrap();
-			if rc1a.have_to_receive == rc1a.total_reward {
-				break;
-			}
+#[test]
+fn miner_registry_stays_dense_on_exit() {
+	new_test_ext().execute_with(|| {
+		for acc in [ACCOUNT1.0, ACCOUNT2.0, ACCOUNT3.0] {
+			assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(acc), acc, [0u8; 38], UNIT_POWER_LIMIT));
 		}
-		assert!(CalculateRewardOrderMap::<Test>::contains_key(ACCOUNT1.0));
-		assert!(RewardClaimMap::<Test>::contains_key(ACCOUNT1.0));
+		assert_eq!(3, <Sminer as MinerControl<_, _>>::get_miner_count());
+
+		assert_ok!(Sminer::force_miner_exit(&ACCOUNT1.0));
+		assert_eq!(2, MinerRegistryLen::<Test>::get());
+		assert_eq!(None, MinerIndex::<Test>::get(ACCOUNT1.0));
+		// the last miner moved into the freed slot
+		assert_eq!(Some(0), MinerIndex::<Test>::get(ACCOUNT3.0));
+		assert_eq!(Some(ACCOUNT3.0), <Sminer as MinerControl<_, _>>::get_miner_by_index(0));
+		assert_eq!(Some(ACCOUNT2.0), <Sminer as MinerControl<_, _>>::get_miner_by_index(1));
+		assert_eq!(None, <Sminer as MinerControl<_, _>>::get_miner_by_index(2));
 	});
 }
 
 #[test]
-fn failure_to_replenish_sufficient_deposit() {
+fn update_metadata_works() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			1u128
-		));
+		let multiaddrs: BoundedVec<Multiaddr, <Test as Config>::MaxMultiaddrs> =
+			vec![b"/ip4/127.0.0.1/tcp/15001".to_vec().try_into().unwrap()].try_into().unwrap();
+		assert_noop!(
+			Sminer::update_metadata(RuntimeOrigin::signed(ACCOUNT1.0), *b"DE", multiaddrs.clone(), 0),
+			Error::<Test>::NotMiner
+		);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_noop!(
+			Sminer::update_metadata(RuntimeOrigin::signed(ACCOUNT1.0), *b"de", multiaddrs.clone(), 0),
+			Error::<Test>::InvalidRegion
+		);
+		assert_ok!(Sminer::add_miner_idle_space(&ACCOUNT1.0, 1024));
+		assert_noop!(
+			Sminer::update_metadata(RuntimeOrigin::signed(ACCOUNT1.0), *b"DE", multiaddrs.clone(), 1000),
+			Error::<Test>::DeclaredSpaceTooSmall
+		);
 
-		let miner = <MinerItems<Test>>::get(&ACCOUNT1.0).unwrap();
-		assert_eq!(miner.state.to_vec(), "positive".as_bytes().to_vec());
-		assert_ok!(Sminer::join_buffer_pool(ACCOUNT1.0));
-		let miner = <MinerItems<Test>>::get(&ACCOUNT1.0).unwrap();
-		assert_eq!(miner.state.to_vec(), "frozen".as_bytes().to_vec());
-		assert_ok!(Sminer::start_buffer_period_schedule());
-		assert!(BadMiner::<Test>::contains_key(ACCOUNT1.0));
-		let event = Sys::events().pop().expect("Expected at least one MinerExit to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::StartOfBufferPeriod { when: 1 }), event);
-
-		run_to_block(3 * 14400 + 1);
-		assert!(!BadMiner::<Test>::contains_key(ACCOUNT1.0));
-		assert!(!MinerItems::<Test>::contains_key(ACCOUNT1.0));
+		assert_ok!(Sminer::update_metadata(RuntimeOrigin::signed(ACCOUNT1.0), *b"DE", multiaddrs.clone(), T_BYTE));
+		assert_eq!(Some(*b"DE"), <Sminer as MinerControl<_, _>>::get_miner_region(&ACCOUNT1.0));
+		assert_eq!(multiaddrs.into_inner(), <Sminer as MinerControl<_, _>>::get_miner_multiaddrs(&ACCOUNT1.0));
+		assert_eq!(T_BYTE, <Sminer as MinerControl<_, _>>::get_declared_space(&ACCOUNT1.0));
 	});
 }
 
 #[test]
-fn full_deposit_within_the_buffer_period() {
+fn idle_space_needs_collateral() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::regnstk(
-			RuntimeOrigin::signed(ACCOUNT1.0),
-			123,
-			IpAddress::IPV4([127,0,0,1],15000),
-			1u128
-		));
+		assert_noop!(
+			Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT - 1),
+			Error::<Test>::InsufficientCollateral
+		);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
 
-		assert_ok!(Sminer::join_buffer_pool(ACCOUNT1.0));
-		assert_ok!(Sminer::start_buffer_period_schedule());
-		assert!(BadMiner::<Test>::contains_key(ACCOUNT1.0));
-		let event = Sys::events().pop().expect("Expected at least one MinerExit to be found").event;
-		assert_eq!(mock::RuntimeEvent::from(Event::StartOfBufferPeriod { when: 1 }), event);
+		// up to 1 TiB of power is covered by the base collateral
+		assert_ok!(Sminer::add_miner_idle_space(&ACCOUNT1.0, 3 * T_BYTE));
+		assert_noop!(
+			Sminer::add_miner_idle_space(&ACCOUNT1.0, T_BYTE),
+			Error::<Test>::InsufficientCollateral
+		);
 
 		assert_ok!(Sminer::increase_collateral(RuntimeOrigin::signed(ACCOUNT1.0), UNIT_POWER_LIMIT));
-		assert!(!BadMiner::<Test>::contains_key(ACCOUNT1.0));
-		let event = Sys::events().pop().expect("Expected at least one MinerExit to be found").event;
-		assert_eq!(
-			mock::RuntimeEvent::from(Event::IncreaseCollateral {
-				acc: ACCOUNT1.0,
-				balance: UNIT_POWER_LIMIT + 1u128
-			}),
-			event
-		);
+		assert_ok!(Sminer::add_miner_idle_space(&ACCOUNT1.0, T_BYTE));
 	});
 }
 
-const FIXED_CHARGE_AMOUNT: u128 = 10000000000000000u128;
 #[test]
-fn faucet_should_work() {
+fn idle_space_stays_within_declared_space() {
 	new_test_ext().execute_with(|| {
-		assert_ok!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777));
-		assert_eq!(FIXED_CHARGE_AMOUNT, Balances::free_balance(&777));
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(ACCOUNT1.0), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_ok!(Sminer::add_miner_idle_space(&ACCOUNT1.0, 1024));
+		assert_ok!(Sminer::update_metadata(RuntimeOrigin::signed(ACCOUNT1.0), *b"DE", Default::default(), 2048));
 
-		//FIXME! the assert_noop! not work, why?
-		//assert_noop!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777), Error::<Test>::LessThan24Hours);
-		if let Err(e) = Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777) {
-			if let DispatchError::Module(m) = e {
-				assert_eq!("LessThan24Hours", m.message.unwrap());
-			}
-		}
-		Sys::set_block_number(1u64 + 28800u64);
-		assert_ok!(Sminer::faucet(RuntimeOrigin::signed(ACCOUNT1.0), 777));
+		// the collateral covers far more, the declared capacity is what limits the miner
+		assert_ok!(Sminer::add_miner_idle_space(&ACCOUNT1.0, 1024));
+		assert_noop!(
+			Sminer::add_miner_idle_space(&ACCOUNT1.0, 1),
+			Error::<Test>::DeclaredSpaceExceeded
+		);
+		assert_eq!(Ok((2048, 0)), <Sminer as MinerControl<_, _>>::get_power(&ACCOUNT1.0));
 	});
 }
 
+#[test]
+fn controller_and_worker_keys_map_to_stash() {
+	new_test_ext().execute_with(|| {
+		let (stash, controller, worker) = (ACCOUNT1.0, ACCOUNT2.0, ACCOUNT3.0);
+		assert_ok!(Sminer::regnstk(RuntimeOrigin::signed(stash), 123, [0u8; 38], UNIT_POWER_LIMIT));
+		assert_eq!(Some(stash), Sminer::stash_of_controller(&stash));
+		assert_eq!(Some(stash), Sminer::stash_of_worker(&stash));
+
+		assert_ok!(Sminer::set_controller(RuntimeOrigin::signed(stash), controller));
+		assert_noop!(Sminer::set_worker(RuntimeOrigin::signed(stash), worker), Error::<Test>::NotMiner);
+		assert_noop!(Sminer::set_worker(RuntimeOrigin::signed(controller), controller), Error::<Test>::KeyInUse);
+		assert_ok!(Sminer::set_worker(RuntimeOrigin::signed(controller), worker));
+
+		assert_eq!(Some(stash), <Sminer as MinerControl<_, _>>::stash_of_controller(&controller));
+		assert_eq!(Some(stash), <Sminer as MinerControl<_, _>>::stash_of_worker(&worker));
+		assert_eq!(None, Sminer::stash_of_controller(&stash));
+		assert_eq!(None, Sminer::stash_of_worker(&stash));
+
+		// administration goes through the controller and applies to the stash
+		assert_ok!(Sminer::update_beneficiary(RuntimeOrigin::signed(controller), 456));
+		assert_eq!(456, MinerItems::<Test>::get(stash).unwrap().beneficiary);
+
+		// keys of an active miner cannot be registered as a new miner
+		assert_noop!(
+			Sminer::regnstk(RuntimeOrigin::signed(worker), worker, [0u8; 38], UNIT_POWER_LIMIT),
+			Error::<Test>::KeyInUse
+		);
+
+		assert_ok!(Sminer::force_miner_exit(&stash));
+		assert_eq!(None, Sminer::stash_of_worker(&worker));
+		assert_eq!(None, Sminer::stash_of_controller(&controller));
+	});
+}
@@ -1,21 +1,48 @@
 use super::*;
-use frame_support::pallet_prelude::MaxEncodedLen;
+use frame_support::{pallet_prelude::MaxEncodedLen, CloneNoBound, EqNoBound, PartialEqNoBound, RuntimeDebugNoBound};
 
 /// The custom struct for storing info of storage miners.
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
-pub struct MinerInfo<AccountId, Balance, BoundedString> {
+pub struct MinerInfo<AccountId, Balance> {
 	//Income account
 	pub(super) beneficiary: AccountId,
 	pub(super) peer_id: PeerId,
 	pub(super) collaterals: Balance,
 	pub(super) debt: Balance,
-	//nomal, exit, frozen, e_frozen
-	pub(super) state: BoundedString,
+	pub(super) state: MinerState,
 	pub(super) idle_space: u128,
 	pub(super) service_space: u128,
 	pub(super) lock_space: u128,
 }
 
+/// Lifecycle state of a storage miner.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, Copy, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum MinerState {
+	//Registered with enough collateral, takes part in storage and challenges
+	Positive,
+	//Collateral fell below the limit for its space, until it is topped up
+	Frozen,
+	//Preparing to exit, no new files are assigned
+	Lock,
+	//Exited, only the collateral withdrawal is left
+	Exit,
+}
+
+impl MinerState {
+	/// Whether a miner in this state may move to `next`.
+	///
+	/// positive -> frozen | lock, frozen -> positive, lock -> exit. Exit is final.
+	pub fn can_transition_to(&self, next: MinerState) -> bool {
+		matches!(
+			(self, next),
+			(MinerState::Positive, MinerState::Frozen)
+				| (MinerState::Positive, MinerState::Lock)
+				| (MinerState::Frozen, MinerState::Positive)
+				| (MinerState::Lock, MinerState::Exit)
+		)
+	}
+}
+
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
 #[scale_info(skip_type_params(T))]
 #[codec(mel_bound())]
@@ -26,16 +53,85 @@ pub struct Reward<T: pallet::Config> {
 	pub(super) reward_issued: BalanceOf<T>,
 	//Currently available reward
 	pub(super) currently_available_reward: BalanceOf<T>,
-	//Reward order list, up to 180 reward orders can be accumulated
-	pub(super) order_list: BoundedVec<RewardOrder<BalanceOf<T>>, ConstU32<{RELEASE_NUMBER as u32}>>,
+	//Reward orders that are still vesting, up to `MaxRewardOrders` can be accumulated
+	pub(super) order_list: BoundedVec<RewardOrder<BalanceOf<T>, BlockNumberOf<T>>, T::MaxRewardOrders>,
 }
 
+/// The reward of one challenge round, released in tranches after the immediate share.
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
-pub struct RewardOrder<Balance> {
+pub struct RewardOrder<Balance, BlockNumber> {
 	pub(super) order_reward: Balance,
+	//The part of the order reward that vests, excluding the immediate share
+	pub(super) vesting: Balance,
 	pub(super) each_share: Balance,
-	pub(super) award_count: u8,
-	pub(super) has_issued: bool,
+	//Number of tranches released so far
+	pub(super) award_count: u32,
+	pub(super) release_number: u32,
+	//End of the release interval the order was created in, tranche `n` unlocks at `start + n * RewardReleaseInterval`
+	pub(super) start: BlockNumber,
+}
+
+impl<Balance, BlockNumber> RewardOrder<Balance, BlockNumber>
+where
+	Balance: AtLeast32BitUnsigned + Copy,
+{
+	/// Amount unlocked by releasing tranches `award_count + 1 ..= due`.
+	///
+	/// The last tranche also carries the rounding remainder of `each_share`.
+	pub(super) fn releasable(&self, due: u32) -> Balance {
+		let due = due.min(self.release_number);
+		if due <= self.award_count {
+			return Zero::zero();
+		}
+		if due == self.release_number {
+			let released = self.each_share.saturating_mul(self.award_count.into());
+			return self.vesting.saturating_sub(released);
+		}
+		self.each_share.saturating_mul((due - self.award_count).into())
+	}
+
+	/// Adds the reward of another round of the same release interval, before any tranche is released.
+	pub(super) fn merge(&mut self, order_reward: Balance, vesting: Balance) {
+		self.order_reward = self.order_reward.saturating_add(order_reward);
+		self.vesting = self.vesting.saturating_add(vesting);
+		self.each_share = self.vesting / self.release_number.max(1).into();
+	}
+}
+
+/// Collateral on its way out, still reserved and slashable until `unlock_at`.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct UnlockChunk<Balance, BlockNumber> {
+	pub(super) value: Balance,
+	//Block the unbonding was requested, faults committed before it can still be slashed from the chunk
+	pub(super) requested_at: BlockNumber,
+	pub(super) unlock_at: BlockNumber,
+}
+
+/// Delegated collateral on its way out, still slashable for faults `miner` committed before `requested_at`.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct DelegatorUnlockChunk<AccountId, Balance, BlockNumber> {
+	pub(super) miner: AccountId,
+	pub(super) value: Balance,
+	pub(super) requested_at: BlockNumber,
+	pub(super) unlock_at: BlockNumber,
+}
+
+/// Self-declared information clients use to choose miners.
+#[derive(PartialEqNoBound, EqNoBound, Encode, Decode, CloneNoBound, RuntimeDebugNoBound, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(MaxAddrs))]
+#[codec(mel_bound())]
+pub struct MinerMetadata<MaxAddrs: Get<u32>> {
+	pub(super) region: RegionCode,
+	pub(super) multiaddrs: BoundedVec<Multiaddr, MaxAddrs>,
+	//Total space in bytes the miner commits to provide
+	pub(super) declared_space: u128,
+}
+
+/// Collateral a third party staked toward a miner.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct Delegation<AccountId, Balance> {
+	pub(super) delegator: AccountId,
+	pub(super) amount: Balance,
 }
 
 /// The custom struct for storing info of storage FaucetRecord.
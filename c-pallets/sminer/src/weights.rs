@@ -60,6 +60,16 @@ pub trait WeightInfo {
 	fn timing_task_increase_power_rewards() -> Weight;
 	fn timing_task_award_table() -> Weight;
 	fn timing_user_receive_award() -> Weight;
+	fn set_commission() -> Weight;
+	fn delegate(d: u32, ) -> Weight;
+	fn undelegate(d: u32, u: u32, ) -> Weight;
+	fn claim_delegator_reward() -> Weight;
+	fn unbond_collateral(d: u32, u: u32, ) -> Weight;
+	fn withdraw_unbonded(u: u32, ) -> Weight;
+	fn set_auto_payout() -> Weight;
+	fn set_controller() -> Weight;
+	fn set_worker() -> Weight;
+	fn update_metadata(m: u32, ) -> Weight;
 }
 
 /// Weights for pallet_sminer using the Substrate node and recommended hardware.
@@ -169,6 +179,103 @@ impl<T: frame_system::Config> WeightInfo for SubstrateWeight<T> {
 			.saturating_add(T::DbWeight::get().reads(2 as u64))
 			.saturating_add(T::DbWeight::get().writes(2 as u64))
 	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer Commission (r:0 w:1)
+	fn set_commission() -> Weight {
+		Weight::from_ref_time(38_412_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Sminer Delegators (r:1 w:1)
+	fn delegate(d: u32, ) -> Weight {
+		Weight::from_ref_time(61_904_000 as u64)
+			.saturating_add(Weight::from_ref_time(96_000 as u64).saturating_mul(d as u64))
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer Delegators (r:1 w:1)
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: Sminer DelegatorUnbonding (r:1 w:1)
+	// Storage: Sminer UnbondingDelegators (r:1 w:1)
+	fn undelegate(d: u32, u: u32, ) -> Weight {
+		Weight::from_ref_time(70_318_000 as u64)
+			.saturating_add(Weight::from_ref_time(188_000 as u64).saturating_mul(d as u64))
+			.saturating_add(Weight::from_ref_time(54_000 as u64).saturating_mul(u as u64))
+			.saturating_add(T::DbWeight::get().reads(4 as u64))
+			.saturating_add(T::DbWeight::get().writes(4 as u64))
+	}
+	// Storage: Sminer DelegatorReward (r:1 w:1)
+	// Storage: System Account (r:2 w:2)
+	fn claim_delegator_reward() -> Weight {
+		Weight::from_ref_time(57_233_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: Sminer Delegators (r:1 w:0)
+	// Storage: Sminer Unbonding (r:1 w:1)
+	fn unbond_collateral(d: u32, u: u32, ) -> Weight {
+		Weight::from_ref_time(54_671_000 as u64)
+			.saturating_add(Weight::from_ref_time(71_000 as u64).saturating_mul(d as u64))
+			.saturating_add(Weight::from_ref_time(48_000 as u64).saturating_mul(u as u64))
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+	}
+	// Storage: Sminer Unbonding (r:1 w:1)
+	// Storage: Sminer DelegatorUnbonding (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Sminer UnbondingDelegators (r:1 w:1)
+	fn withdraw_unbonded(u: u32, ) -> Weight {
+		Weight::from_ref_time(49_352_000 as u64)
+			.saturating_add(Weight::from_ref_time(8_914_000 as u64).saturating_mul(u as u64))
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().reads((1 as u64).saturating_mul(u as u64)))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+			.saturating_add(T::DbWeight::get().writes((1 as u64).saturating_mul(u as u64)))
+	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer AutoPayout (r:0 w:1)
+	// Storage: Sminer PendingPayout (r:0 w:1)
+	fn set_auto_payout() -> Weight {
+		Weight::from_ref_time(39_127_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(2 as u64))
+	}
+	// Storage: Sminer MinerItems (r:2 w:0)
+	// Storage: Sminer ControllerStash (r:1 w:2)
+	// Storage: Sminer WorkerStash (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:1)
+	fn set_controller() -> Weight {
+		Weight::from_ref_time(47_385_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(5 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer ControllerStash (r:2 w:0)
+	// Storage: Sminer MinerItems (r:2 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer WorkerStash (r:1 w:2)
+	// Storage: Sminer Worker (r:1 w:1)
+	fn set_worker() -> Weight {
+		Weight::from_ref_time(51_906_000 as u64)
+			.saturating_add(T::DbWeight::get().reads(7 as u64))
+			.saturating_add(T::DbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer MinerMeta (r:0 w:1)
+	fn update_metadata(m: u32, ) -> Weight {
+		Weight::from_ref_time(42_566_000 as u64)
+			.saturating_add(Weight::from_ref_time(412_000 as u64).saturating_mul(m as u64))
+			.saturating_add(T::DbWeight::get().reads(3 as u64))
+			.saturating_add(T::DbWeight::get().writes(1 as u64))
+	}
 }
 
 // For backwards compatibility and tests
@@ -277,4 +384,101 @@ impl WeightInfo for () {
 			.saturating_add(RocksDbWeight::get().reads(2 as u64))
 			.saturating_add(RocksDbWeight::get().writes(2 as u64))
 	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer Commission (r:0 w:1)
+	fn set_commission() -> Weight {
+		Weight::from_ref_time(38_412_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Sminer Delegators (r:1 w:1)
+	fn delegate(d: u32, ) -> Weight {
+		Weight::from_ref_time(61_904_000 as u64)
+			.saturating_add(Weight::from_ref_time(96_000 as u64).saturating_mul(d as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer Delegators (r:1 w:1)
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: Sminer DelegatorUnbonding (r:1 w:1)
+	// Storage: Sminer UnbondingDelegators (r:1 w:1)
+	fn undelegate(d: u32, u: u32, ) -> Weight {
+		Weight::from_ref_time(70_318_000 as u64)
+			.saturating_add(Weight::from_ref_time(188_000 as u64).saturating_mul(d as u64))
+			.saturating_add(Weight::from_ref_time(54_000 as u64).saturating_mul(u as u64))
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	// Storage: Sminer DelegatorReward (r:1 w:1)
+	// Storage: System Account (r:2 w:2)
+	fn claim_delegator_reward() -> Weight {
+		Weight::from_ref_time(57_233_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer MinerItems (r:1 w:1)
+	// Storage: Sminer Delegators (r:1 w:0)
+	// Storage: Sminer Unbonding (r:1 w:1)
+	fn unbond_collateral(d: u32, u: u32, ) -> Weight {
+		Weight::from_ref_time(54_671_000 as u64)
+			.saturating_add(Weight::from_ref_time(71_000 as u64).saturating_mul(d as u64))
+			.saturating_add(Weight::from_ref_time(48_000 as u64).saturating_mul(u as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Storage: Sminer Unbonding (r:1 w:1)
+	// Storage: Sminer DelegatorUnbonding (r:1 w:1)
+	// Storage: System Account (r:1 w:1)
+	// Storage: Sminer UnbondingDelegators (r:1 w:1)
+	fn withdraw_unbonded(u: u32, ) -> Weight {
+		Weight::from_ref_time(49_352_000 as u64)
+			.saturating_add(Weight::from_ref_time(8_914_000 as u64).saturating_mul(u as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(u as u64)))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes((1 as u64).saturating_mul(u as u64)))
+	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer AutoPayout (r:0 w:1)
+	// Storage: Sminer PendingPayout (r:0 w:1)
+	fn set_auto_payout() -> Weight {
+		Weight::from_ref_time(39_127_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Storage: Sminer MinerItems (r:2 w:0)
+	// Storage: Sminer ControllerStash (r:1 w:2)
+	// Storage: Sminer WorkerStash (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:1)
+	fn set_controller() -> Weight {
+		Weight::from_ref_time(47_385_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer ControllerStash (r:2 w:0)
+	// Storage: Sminer MinerItems (r:2 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer WorkerStash (r:1 w:2)
+	// Storage: Sminer Worker (r:1 w:1)
+	fn set_worker() -> Weight {
+		Weight::from_ref_time(51_906_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(7 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Storage: Sminer ControllerStash (r:1 w:0)
+	// Storage: Sminer MinerItems (r:1 w:0)
+	// Storage: Sminer Controller (r:1 w:0)
+	// Storage: Sminer MinerMeta (r:0 w:1)
+	fn update_metadata(m: u32, ) -> Weight {
+		Weight::from_ref_time(42_566_000 as u64)
+			.saturating_add(Weight::from_ref_time(412_000 as u64).saturating_mul(m as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
 }
\ No newline at end of file
@@ -0,0 +1,17 @@
+//! Runtime API definition for the tee-worker pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::Codec;
+use sp_std::vec::Vec;
+
+pub use pallet_tee_worker::{TeeCapabilities, TeeWorkerEndpoint};
+
+sp_api::decl_runtime_apis! {
+	pub trait TeeWorkerApi<AccountId> where
+		AccountId: Codec,
+	{
+		/// Attested workers that are not exiting, with how to reach them and what they offer.
+		fn healthy_tee_workers() -> Vec<TeeWorkerEndpoint<AccountId>>;
+	}
+}
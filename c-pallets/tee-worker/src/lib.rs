@@ -5,9 +5,9 @@
 
 
 #[cfg(test)]
-mod tests;
-
 mod mock;
+#[cfg(test)]
+mod tests;
 
 mod types;
 pub use types::*;
@@ -17,13 +17,13 @@ pub mod benchmarking;
 
 use codec::{Decode, Encode};
 use frame_support::{
-	dispatch::DispatchResult, traits::ReservableCurrency, transactional, BoundedVec, PalletId,
+	dispatch::DispatchResult, traits::{ReservableCurrency, UnixTime}, transactional, BoundedVec, PalletId,
 	pallet_prelude::*,
 };
 pub use pallet::*;
 use scale_info::TypeInfo;
 use sp_runtime::{
-	DispatchError, RuntimeDebug,
+	DispatchError, PerThing, Perbill, RuntimeDebug,
 };
 use sp_std::{ 
 	convert::TryInto,
@@ -36,8 +36,12 @@ use cp_cess_common::*;
 use frame_system::{ensure_signed, pallet_prelude::*};
 use cp_enclave_verify::*;
 pub mod weights;
+pub mod migrations;
 
 type AccountOf<T> = <T as frame_system::Config>::AccountId;
+type BlockNumberOf<T> = <T as frame_system::Config>::BlockNumber;
+
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
 
 #[frame_support::pallet]
 pub mod pallet {
@@ -72,6 +76,30 @@ pub mod pallet {
 
         #[pallet::constant]
         type MaxWhitelist: Get<u32> + Clone + Eq + PartialEq;
+		//Maximum age of an attestation report, in seconds
+		#[pallet::constant]
+		type MaxReportAge: Get<u64>;
+		//Maximum number of certificate revocation lists checked during attestation
+		#[pallet::constant]
+		type MaxCrls: Get<u32> + Clone + Eq + PartialEq;
+		//Number of blocks an attestation stays valid before the worker has to reattest
+		#[pallet::constant]
+		type AttestationValidity: Get<BlockNumberOf<Self>>;
+		//Number of blocks a lapsed worker is kept before it is deregistered
+		#[pallet::constant]
+		type AttestationGracePeriod: Get<BlockNumberOf<Self>>;
+		//Share of attested workers that must approve a PoDR2 key rotation
+		#[pallet::constant]
+		type Podr2KeyThreshold: Get<Perbill>;
+		//Minimum number of blocks between a rotation proposal and its activation,
+		//giving miners time to regenerate their tags
+		#[pallet::constant]
+		type Podr2KeyActivationDelay: Get<BlockNumberOf<Self>>;
+		//Where the pending verify missions of an exiting worker are handed off
+		type VerifyMission: VerifyMissionHandoff<Self::AccountId>;
+		//Number of blocks an exiting worker has to finish or hand off its missions
+		#[pallet::constant]
+		type ExitTimeout: Get<BlockNumberOf<Self>>;
 	}
 
 	#[pallet::event]
@@ -79,12 +107,44 @@ pub mod pallet {
 	pub enum Event<T: Config> {
 		//Scheduling registration method
 		RegistrationTeeWorker { acc: AccountOf<T>, peer_id: PeerId },
-
+		//The worker changed how miners reach it or what it offers
 		UpdateScheduler { acc: AccountOf<T>, endpoint: IpAddress },
 
 		Exit { acc: AccountOf<T> },
+		//The worker stopped taking new missions and has until `deadline` to complete its exit
+		ExitRequested { acc: AccountOf<T>, deadline: BlockNumberOf<T> },
+		//The worker did not complete its exit in time; it is slashed if it left missions behind
+		ExitForced { acc: AccountOf<T>, slashed: bool },
 
 		UpdatePeerId { acc: AccountOf<T> },
+
+		UpdateDcapRootCert,
+		//Attestations are only accepted from enclaves signed by `mr_signer`, or any signer if none
+		UpdateMrSigner { mr_signer: Option<[u8; 32]> },
+
+		UpdateRevocationLists { count: u32 },
+
+		Reattested { acc: AccountOf<T> },
+		//The attestation lapsed and was not renewed within the grace period
+		AttestationExpired { acc: AccountOf<T> },
+
+		Podr2KeyProposed { acc: AccountOf<T>, activation: BlockNumberOf<T> },
+
+		Podr2KeyApproved { acc: AccountOf<T> },
+
+		Podr2KeyScheduled { activation: BlockNumberOf<T> },
+
+		Podr2KeyActivated,
+		//The proposal reached its activation block without enough approvals
+		Podr2KeyProposalExpired,
+
+		WhitelistUpdated { mr_enclave: [u8; 64], expiry: Option<BlockNumberOf<T>> },
+
+		WhitelistRemoved { mr_enclave: [u8; 64] },
+
+		MrEnclaveExpired { mr_enclave: [u8; 64] },
+		//A worker attested with a removed measurement, it was deregistered or has to reattest
+		MrEnclaveRevoked { acc: AccountOf<T>, deregistered: bool },
 	}
 
 	#[pallet::error]
@@ -107,6 +167,40 @@ pub mod pallet {
 		NonTeeWorker,
 
 		VerifyCertFailed,
+		//The MRENCLAVE of the report is not in the whitelist
+		MrEnclaveNotWhitelisted,
+		//The enclave is not signed by the configured MRSIGNER
+		MrSignerMismatch,
+		//The report data is not bound to the registering node key
+		ReportDataMismatch,
+		//No root certificate is configured for DCAP attestation
+		DcapRootNotSet,
+		//A certificate revocation list is not valid DER
+		InvalidCrl,
+		//The worker's attestation has lapsed
+		AttestationLapsed,
+		//A PoDR2 key rotation is already proposed or scheduled
+		RotationInProgress,
+		//There is no PoDR2 key rotation to approve
+		NoPendingRotation,
+		//The worker already approved the pending rotation
+		AlreadyApproved,
+		//The activation block leaves miners too little time to regenerate tags
+		ActivationTooEarly,
+		//The measurement is not in the whitelist
+		NotWhitelisted,
+		//The expiry block has already passed
+		InvalidExpiry,
+		//The worker already requested to exit
+		AlreadyExiting,
+		//The worker has not requested to exit
+		NotExiting,
+		//The worker has to offer at least one service
+		NoCapability,
+		//A key, signature, certificate or hex string could not be decoded
+		MalformedInput,
+		//The attestation report is malformed, stale or its quote status is not trusted
+		InvalidReport,
 	}
 
 	#[pallet::storage]
@@ -122,13 +216,98 @@ pub mod pallet {
 	#[pallet::getter(fn tee_podr2_pk)]
 	pub(super) type TeePodr2Pk<T: Config> = StorageValue<_, Podr2Key>;
 
+	//The key replaced by the last rotation, kept so tags generated under it can still be checked
+	#[pallet::storage]
+	#[pallet::getter(fn previous_podr2_pk)]
+	pub(super) type PreviousPodr2Pk<T: Config> = StorageValue<_, Podr2Key>;
+
+	//Rotation proposal still collecting TEE worker approvals
+	#[pallet::storage]
+	#[pallet::getter(fn pending_podr2_key)]
+	pub(super) type PendingPodr2Key<T: Config> = StorageValue<_, Podr2KeyProposal<T>>;
+
+	//Approved rotation waiting for its activation block
+	#[pallet::storage]
+	#[pallet::getter(fn scheduled_podr2_key)]
+	pub(super) type ScheduledPodr2Key<T: Config> = StorageValue<_, (Podr2Key, BlockNumberOf<T>)>;
+
 	#[pallet::storage]
 	#[pallet::getter(fn mr_enclave_whitelist)]
-	pub(super) type MrEnclaveWhitelist<T: Config> = StorageValue<_, BoundedVec<[u8; 64], T::MaxWhitelist>, ValueQuery>;
+	pub(super) type MrEnclaveWhitelist<T: Config> =
+		StorageValue<_, BoundedVec<MrEnclaveEntry<BlockNumberOf<T>>, T::MaxWhitelist>, ValueQuery>;
+
+	//DER encoded root CA certificate the DCAP PCK and TCB signing chains must lead to
+	#[pallet::storage]
+	#[pallet::getter(fn dcap_root_cert)]
+	pub(super) type DcapRootCert<T: Config> = StorageValue<_, CertDer>;
+
+	//MRSIGNER the attested enclaves must be signed with, any signer is accepted while unset
+	#[pallet::storage]
+	#[pallet::getter(fn mr_signer)]
+	pub(super) type MrSigner<T: Config> = StorageValue<_, [u8; 32]>;
+
+	//DER encoded CRLs applied to the IAS and DCAP certificate chains during attestation
+	#[pallet::storage]
+	#[pallet::getter(fn revocation_lists)]
+	pub(super) type RevocationLists<T: Config> = StorageValue<_, BoundedVec<CrlDer, T::MaxCrls>, ValueQuery>;
+
+	//Workers whose grace period ends at the given block, checked in `on_initialize`
+	#[pallet::storage]
+	#[pallet::getter(fn attestation_deadline)]
+	pub(super) type AttestationDeadline<T: Config> =
+		StorageMap<_, Blake2_128Concat, BlockNumberOf<T>, BoundedVec<AccountOf<T>, T::SchedulerMaximum>, ValueQuery>;
+
+	//Workers in the middle of exiting, with the block by which the exit must be completed
+	#[pallet::storage]
+	#[pallet::getter(fn exiting)]
+	pub(super) type Exiting<T: Config> = StorageMap<_, Blake2_128Concat, AccountOf<T>, BlockNumberOf<T>>;
+
+	//Exiting workers whose exit deadline is the given block, checked in `on_initialize`
+	#[pallet::storage]
+	#[pallet::getter(fn exit_deadline)]
+	pub(super) type ExitDeadline<T: Config> =
+		StorageMap<_, Blake2_128Concat, BlockNumberOf<T>, BoundedVec<AccountOf<T>, T::SchedulerMaximum>, ValueQuery>;
 
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberOf<T>> for Pallet<T> {
+		fn on_initialize(now: BlockNumberOf<T>) -> Weight {
+			let mut weight: Weight = T::DbWeight::get().reads_writes(3, 1);
+
+			weight = weight.saturating_add(Self::activate_podr2_key(now));
+			weight = weight.saturating_add(Self::purge_expired_mr_enclaves(now));
+			weight = weight.saturating_add(Self::force_timed_out_exits(now));
+
+			let whitelist = <MrEnclaveWhitelist<T>>::get();
+			for acc in <AttestationDeadline<T>>::take(now) {
+				weight = weight.saturating_add(T::DbWeight::get().reads(1));
+				let expired = match <TeeWorkerMap<T>>::get(&acc) {
+					Some(TeeWorkerInfo { mr_enclave: Some(mr_enclave), attested_at, .. }) =>
+						Self::attestation_deadline_of(attested_at) <= now
+							|| !Self::is_whitelisted(&whitelist, &mr_enclave, now),
+					// Migrated workers are only scheduled once, at the end of their grace period.
+					Some(TeeWorkerInfo { mr_enclave: None, .. }) => true,
+					None => false,
+				};
+				// Workers that reattested in time were rescheduled to a later block.
+				if expired {
+					weight = weight.saturating_add(T::DbWeight::get().reads_writes(1, 2));
+					if let Err(e) = T::VerifyMission::hand_off_missions(&acc) {
+						log::error!("hand off missions of expired tee worker failed: {:?}", e);
+					}
+					Self::remove_tee_worker(&acc);
+					Self::deposit_event(Event::<T>::AttestationExpired { acc });
+				}
+			}
+
+			weight
+		}
+	}
+
 	#[pallet::call]
 	impl<T: Config> Pallet<T> {
 		//Scheduling registration method
@@ -140,10 +319,13 @@ pub mod pallet {
 			stash_account: AccountOf<T>,
 			node_key: NodePublicKey,
 			peer_id: PeerId,
-			podr2_pbk: Podr2Key,
-			sgx_attestation_report: SgxAttestationReport,
+			endpoint: IpAddress,
+			multiaddr: Multiaddr,
+			capabilities: TeeCapabilities,
+			attestation_report: AttestationReport,
 		) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
+			ensure!(!capabilities.is_empty(), Error::<T>::NoCapability);
 			//Even if the primary key is not present here, panic will not be caused
 			let acc = <pallet_cess_staking::Pallet<T>>::bonded(&stash_account)
 				.ok_or(Error::<T>::NotBond)?;
@@ -152,24 +334,23 @@ pub mod pallet {
 			}
 			ensure!(!TeeWorkerMap::<T>::contains_key(&sender), Error::<T>::AlreadyRegistration);
 
-			let _ = verify_miner_cert(
-				&sgx_attestation_report.sign, 
-				&sgx_attestation_report.cert_der, 
-				&sgx_attestation_report.report_json_raw,
-			).ok_or(Error::<T>::VerifyCertFailed)?;
+			let mr_enclave = Self::check_attestation(&attestation_report, &node_key)?;
 
+			let now = <frame_system::Pallet<T>>::block_number();
 			let tee_worker_info = TeeWorkerInfo::<T> {
 				controller_account: sender.clone(),
 				peer_id: peer_id.clone(),
+				endpoint,
+				multiaddr,
+				capabilities,
 				node_key,
 				stash_account: stash_account,
+				attested_at: now,
+				mr_enclave: Some(mr_enclave),
 			};
 
-			if TeeWorkerMap::<T>::count() == 0 {
-				<TeePodr2Pk<T>>::put(podr2_pbk);
-			}
-
 			TeeWorkerMap::<T>::insert(&sender, tee_worker_info);
+			Self::schedule_attestation_deadline(&sender, Self::attestation_deadline_of(now))?;
 
 			Self::deposit_event(Event::<T>::RegistrationTeeWorker { acc: sender, peer_id: peer_id });
 
@@ -198,54 +379,271 @@ pub mod pallet {
 		#[pallet::weight(100_000_000)]
 		pub fn test_rsa_verify(origin: OriginFor<T>, key: Vec<u8>, sig: Vec<u8>, msg: Vec<u8>) -> DispatchResult {
 			let _ = ensure_signed(origin)?;
-			let result = verify_rsa(&key, &msg, &sig);
+			verify_rsa(&key, &msg, &sig).map_err(Error::<T>::from)?;
 
-			ensure!(result, Error::<T>::VerifyCertFailed);
 			Ok(())
 		}
 
-        #[pallet::call_index(3)]
-        #[transactional]
+		//Adds a measurement or updates the expiry of an existing one
+		#[pallet::call_index(3)]
+		#[transactional]
 		#[pallet::weight(100_000_000)]
-        pub fn update_whitelist(origin: OriginFor<T>, mr_enclave: [u8; 64]) -> DispatchResult {
+		pub fn update_whitelist(
+			origin: OriginFor<T>,
+			mr_enclave: [u8; 64],
+			expiry: Option<BlockNumberOf<T>>,
+		) -> DispatchResult {
 			let _ = ensure_root(origin)?;
-			<MrEnclaveWhitelist<T>>::mutate(|list| -> DispatchResult {
-                list.try_push(mr_enclave).unwrap();
-                Ok(())
-            })?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			if let Some(expiry) = expiry {
+				ensure!(expiry > now, Error::<T>::InvalidExpiry);
+			}
+
+			<MrEnclaveWhitelist<T>>::try_mutate(|list| -> DispatchResult {
+				match list.iter_mut().find(|entry| entry.mr_enclave.eq_ignore_ascii_case(&mr_enclave)) {
+					Some(entry) => entry.expiry = expiry,
+					None => list
+						.try_push(MrEnclaveEntry { mr_enclave, expiry })
+						.map_err(|_| Error::<T>::StorageLimitReached)?,
+				}
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::<T>::WhitelistUpdated { mr_enclave, expiry });
 
 			Ok(())
 		}
 
+		//Removes a measurement. Workers attested with it are deregistered right away,
+		//or get the attestation grace period to reattest with another measurement.
+		#[pallet::call_index(12)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn remove_from_whitelist(
+			origin: OriginFor<T>,
+			mr_enclave: [u8; 64],
+			deregister: bool,
+		) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+
+			<MrEnclaveWhitelist<T>>::try_mutate(|list| -> DispatchResult {
+				let index = list
+					.iter()
+					.position(|entry| entry.mr_enclave.eq_ignore_ascii_case(&mr_enclave))
+					.ok_or(Error::<T>::NotWhitelisted)?;
+				list.remove(index);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::<T>::WhitelistRemoved { mr_enclave });
+
+			Self::revoke_mr_enclave(&mr_enclave, deregister)
+		}
+
+		//First phase of the exit: the worker stops receiving new verify missions
 		#[pallet::call_index(4)]
         #[transactional]
 		#[pallet::weight(100_000_000)]
 		pub fn exit(origin: OriginFor<T>) -> DispatchResult {
 			let sender = ensure_signed(origin)?;
+			ensure!(TeeWorkerMap::<T>::contains_key(&sender), Error::<T>::NonTeeWorker);
+			ensure!(!<Exiting<T>>::contains_key(&sender), Error::<T>::AlreadyExiting);
 
-			TeeWorkerMap::<T>::remove(&sender);
+			let now = <frame_system::Pallet<T>>::block_number();
+			let deadline = now.saturating_add(T::ExitTimeout::get());
+			<ExitDeadline<T>>::try_mutate(deadline, |list| -> DispatchResult {
+				list.try_push(sender.clone()).map_err(|_| Error::<T>::StorageLimitReached)?;
+				Ok(())
+			})?;
+			<Exiting<T>>::insert(&sender, deadline);
 
-			if TeeWorkerMap::<T>::count() == 0 {
-				<TeePodr2Pk<T>>::kill();
-			}
+			Self::deposit_event(Event::<T>::ExitRequested { acc: sender, deadline });
+
+			Ok(())
+		}
+
+		//Second phase of the exit: missions the worker did not verify are handed off to other workers
+		#[pallet::call_index(13)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn complete_exit(origin: OriginFor<T>) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			ensure!(<Exiting<T>>::contains_key(&sender), Error::<T>::NotExiting);
+
+			T::VerifyMission::hand_off_missions(&sender)?;
+			Self::remove_tee_worker(&sender);
 
 			Self::deposit_event(Event::<T>::Exit { acc: sender });
 
 			Ok(())
 		}
 
-		//For TEST
-		#[pallet::call_index(5)]
+		//Updates how miners reach the worker and which services it offers
+		#[pallet::call_index(14)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::update_scheduler())]
+		pub fn update_endpoint(
+			origin: OriginFor<T>,
+			endpoint: IpAddress,
+			multiaddr: Multiaddr,
+			capabilities: TeeCapabilities,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			ensure!(!capabilities.is_empty(), Error::<T>::NoCapability);
+
+			<TeeWorkerMap<T>>::try_mutate(&sender, |info_opt| -> DispatchResult {
+				let info = info_opt.as_mut().ok_or(Error::<T>::NonTeeWorker)?;
+				info.endpoint = endpoint.clone();
+				info.multiaddr = multiaddr;
+				info.capabilities = capabilities;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::<T>::UpdateScheduler { acc: sender, endpoint });
+
+			Ok(())
+		}
+
+		#[pallet::call_index(6)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn update_dcap_root_cert(origin: OriginFor<T>, root_cert: CertDer) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+
+			<DcapRootCert<T>>::put(root_cert);
+
+			Self::deposit_event(Event::<T>::UpdateDcapRootCert);
+
+			Ok(())
+		}
+
+		#[pallet::call_index(15)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn update_mr_signer(origin: OriginFor<T>, mr_signer: Option<[u8; 32]>) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+
+			match mr_signer {
+				Some(signer) => <MrSigner<T>>::put(signer),
+				None => <MrSigner<T>>::kill(),
+			}
+
+			Self::deposit_event(Event::<T>::UpdateMrSigner { mr_signer });
+
+			Ok(())
+		}
+
+		//Replaces the whole set of revocation lists, so a list can be refreshed or dropped in one call
+		#[pallet::call_index(7)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn update_revocation_lists(origin: OriginFor<T>, crls: BoundedVec<CrlDer, T::MaxCrls>) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+
+			let crl_list: Vec<&[u8]> = crls.iter().map(|crl| crl.as_slice()).collect();
+			parse_crls(&crl_list).map_err(Error::<T>::from)?;
+
+			let count = crls.len() as u32;
+			<RevocationLists<T>>::put(crls);
+
+			Self::deposit_event(Event::<T>::UpdateRevocationLists { count });
+
+			Ok(())
+		}
+
+		//Refreshes the attestation of a registered worker, also after it lapsed within the grace period
+		#[pallet::call_index(8)]
+		#[transactional]
+		#[pallet::weight(<T as pallet::Config>::WeightInfo::registration_scheduler())]
+		pub fn reattest(origin: OriginFor<T>, attestation_report: AttestationReport) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+
+			<TeeWorkerMap<T>>::try_mutate(&sender, |info_opt| -> DispatchResult {
+				let info = info_opt.as_mut().ok_or(Error::<T>::NonTeeWorker)?;
+				info.mr_enclave = Some(Self::check_attestation(&attestation_report, &info.node_key)?);
+				info.attested_at = now;
+				Ok(())
+			})?;
+			Self::schedule_attestation_deadline(&sender, Self::attestation_deadline_of(now))?;
+
+			Self::deposit_event(Event::<T>::Reattested { acc: sender });
+
+			Ok(())
+		}
+
+		//Proposes a new PoDR2 key; the proposer's approval is counted right away
+		#[pallet::call_index(9)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn propose_podr2_key(
+			origin: OriginFor<T>,
+			podr2_pbk: Podr2Key,
+			activation: BlockNumberOf<T>,
+		) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			Self::ensure_attested(&sender)?;
+			ensure!(
+				!<PendingPodr2Key<T>>::exists() && !<ScheduledPodr2Key<T>>::exists(),
+				Error::<T>::RotationInProgress
+			);
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(
+				activation >= now.saturating_add(T::Podr2KeyActivationDelay::get()),
+				Error::<T>::ActivationTooEarly
+			);
+
+			let mut approvals: BoundedVec<AccountOf<T>, T::SchedulerMaximum> = Default::default();
+			approvals.try_push(sender.clone()).map_err(|_| Error::<T>::StorageLimitReached)?;
+			let proposal = Podr2KeyProposal::<T> { key: podr2_pbk, activation, approvals };
+
+			Self::deposit_event(Event::<T>::Podr2KeyProposed { acc: sender, activation });
+
+			Self::try_schedule_podr2_key(proposal);
+
+			Ok(())
+		}
+
+		#[pallet::call_index(10)]
+		#[transactional]
+		#[pallet::weight(100_000_000)]
+		pub fn approve_podr2_key(origin: OriginFor<T>) -> DispatchResult {
+			let sender = ensure_signed(origin)?;
+			Self::ensure_attested(&sender)?;
+
+			let mut proposal = <PendingPodr2Key<T>>::get().ok_or(Error::<T>::NoPendingRotation)?;
+			ensure!(!proposal.approvals.contains(&sender), Error::<T>::AlreadyApproved);
+			proposal.approvals.try_push(sender.clone()).map_err(|_| Error::<T>::StorageLimitReached)?;
+
+			Self::deposit_event(Event::<T>::Podr2KeyApproved { acc: sender });
+
+			Self::try_schedule_podr2_key(proposal);
+
+			Ok(())
+		}
+
+		//Governance path, also used to install the first key. Replaces any pending proposal.
+		#[pallet::call_index(11)]
 		#[transactional]
-		#[pallet::weight(100_00_000)]
-		pub fn update_pk(origin: OriginFor<T>, podr2_pbk: Podr2Key) -> DispatchResult {
-			let _sender = ensure_signed(origin)?;
+		#[pallet::weight(100_000_000)]
+		pub fn force_podr2_key(
+			origin: OriginFor<T>,
+			podr2_pbk: Podr2Key,
+			activation: BlockNumberOf<T>,
+		) -> DispatchResult {
+			let _ = ensure_root(origin)?;
+			let now = <frame_system::Pallet<T>>::block_number();
+			ensure!(activation > now, Error::<T>::ActivationTooEarly);
+
+			<PendingPodr2Key<T>>::kill();
+			<ScheduledPodr2Key<T>>::put((podr2_pbk, activation));
 
-			<TeePodr2Pk<T>>::put(podr2_pbk);
+			Self::deposit_event(Event::<T>::Podr2KeyScheduled { activation });
 
 			Ok(())
 		}
 
+
 		// #[pallet::call_index(6)]
 		// #[transactional]
 		// #[pallet::weight(100_00_000)]
@@ -284,11 +682,315 @@ pub mod pallet {
 	}
 }
 
+impl<T: Config> Pallet<T> {
+	// Verifies the attestation, its MRENCLAVE and MRSIGNER and that it is bound to `node_key`.
+	fn check_attestation(
+		attestation_report: &AttestationReport,
+		node_key: &NodePublicKey,
+	) -> Result<[u8; 32], DispatchError> {
+		let (mr_enclave, mr_signer, report_data) = Self::verify_attestation(attestation_report)?;
+		Self::check_mr_signer(&mr_signer)?;
+
+		let now = <frame_system::Pallet<T>>::block_number();
+		ensure!(
+			Self::is_whitelisted(&<MrEnclaveWhitelist<T>>::get(), &mr_enclave, now),
+			Error::<T>::MrEnclaveNotWhitelisted
+		);
+		ensure!(report_data[..32] == node_key.0[..], Error::<T>::ReportDataMismatch);
+
+		Ok(mr_enclave)
+	}
+
+	// Enclaves have to be signed by the configured MRSIGNER, if there is one.
+	fn check_mr_signer(mr_signer: &[u8; 32]) -> DispatchResult {
+		if let Some(expected) = <MrSigner<T>>::get() {
+			ensure!(&expected == mr_signer, Error::<T>::MrSignerMismatch);
+		}
+		Ok(())
+	}
+
+	// An attestation lapses after the validity period, or once its measurement leaves the whitelist.
+	fn is_attested(
+		info: &TeeWorkerInfo<T>,
+		now: BlockNumberOf<T>,
+		whitelist: &[MrEnclaveEntry<BlockNumberOf<T>>],
+	) -> bool {
+		match info.mr_enclave {
+			Some(mr_enclave) => now < info.attested_at.saturating_add(T::AttestationValidity::get())
+				&& Self::is_whitelisted(whitelist, &mr_enclave, now),
+			// A migrated worker keeps serving until the reattest deadline set by the migration.
+			None => now < info.attested_at.saturating_add(T::AttestationGracePeriod::get()),
+		}
+	}
+
+	// Attested workers that are not exiting, the ones that get new missions.
+	fn is_healthy(
+		acc: &AccountOf<T>,
+		info: &TeeWorkerInfo<T>,
+		now: BlockNumberOf<T>,
+		whitelist: &[MrEnclaveEntry<BlockNumberOf<T>>],
+	) -> bool {
+		Self::is_attested(info, now, whitelist) && !<Exiting<T>>::contains_key(acc)
+	}
+
+	// Endpoints and capabilities of the healthy workers, for miners looking for a TEE.
+	pub fn healthy_workers() -> Vec<TeeWorkerEndpoint<AccountOf<T>>> {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let whitelist = <MrEnclaveWhitelist<T>>::get();
+
+		<TeeWorkerMap<T>>::iter()
+			// Migrated workers are left out until they publish their endpoint and services.
+			.filter(|(acc, info)| !info.capabilities.is_empty() && Self::is_healthy(acc, info, now, &whitelist))
+			.map(|(acc, info)| TeeWorkerEndpoint {
+				controller_account: acc,
+				peer_id: info.peer_id,
+				endpoint: info.endpoint,
+				multiaddr: info.multiaddr,
+				capabilities: info.capabilities,
+			})
+			.collect()
+	}
+
+	fn attestation_deadline_of(attested_at: BlockNumberOf<T>) -> BlockNumberOf<T> {
+		attested_at
+			.saturating_add(T::AttestationValidity::get())
+			.saturating_add(T::AttestationGracePeriod::get())
+	}
+
+	fn schedule_attestation_deadline(acc: &AccountOf<T>, deadline: BlockNumberOf<T>) -> DispatchResult {
+		<AttestationDeadline<T>>::try_mutate(deadline, |list| -> DispatchResult {
+			if !list.contains(acc) {
+				list.try_push(acc.clone()).map_err(|_| Error::<T>::StorageLimitReached)?;
+			}
+			Ok(())
+		})
+	}
+
+	fn remove_tee_worker(acc: &AccountOf<T>) {
+		TeeWorkerMap::<T>::remove(acc);
+		<Exiting<T>>::remove(acc);
+	}
+
+	// Removes workers that did not complete their exit in time. Their missions are still
+	// handed off so miners are not stranded, but leaving work behind is slashed.
+	fn force_timed_out_exits(now: BlockNumberOf<T>) -> Weight {
+		let mut weight = T::DbWeight::get().reads_writes(1, 1);
+
+		for acc in <ExitDeadline<T>>::take(now) {
+			weight = weight.saturating_add(T::DbWeight::get().reads(1));
+			// Workers that completed their exit in time are no longer exiting.
+			if <Exiting<T>>::get(&acc) != Some(now) {
+				continue;
+			}
+			weight = weight.saturating_add(T::DbWeight::get().reads_writes(2, 2));
+			let slashed = T::VerifyMission::pending_missions(&acc) > 0;
+			if slashed {
+				if let Err(e) = Self::punish_scheduler(acc.clone()) {
+					log::error!("punish exiting tee worker failed: {:?}", e);
+				}
+				if let Err(e) = T::VerifyMission::hand_off_missions(&acc) {
+					log::error!("hand off missions of exiting tee worker failed: {:?}", e);
+				}
+			}
+			Self::remove_tee_worker(&acc);
+			Self::deposit_event(Event::<T>::ExitForced { acc, slashed });
+		}
+
+		weight
+	}
+
+	fn ensure_attested(acc: &AccountOf<T>) -> DispatchResult {
+		let info = <TeeWorkerMap<T>>::get(acc).ok_or(Error::<T>::NonTeeWorker)?;
+		let now = <frame_system::Pallet<T>>::block_number();
+		ensure!(Self::is_attested(&info, now, &<MrEnclaveWhitelist<T>>::get()), Error::<T>::AttestationLapsed);
+		Ok(())
+	}
+
+	// Schedules the proposal once enough attested workers approved it, otherwise keeps it pending.
+	fn try_schedule_podr2_key(proposal: Podr2KeyProposal<T>) {
+		let active = Self::get_controller_list().len() as u32;
+		let threshold = T::Podr2KeyThreshold::get().mul_ceil(active).max(1);
+
+		if proposal.approvals.len() as u32 >= threshold {
+			<PendingPodr2Key<T>>::kill();
+			<ScheduledPodr2Key<T>>::put((proposal.key, proposal.activation));
+			Self::deposit_event(Event::<T>::Podr2KeyScheduled { activation: proposal.activation });
+		} else {
+			<PendingPodr2Key<T>>::put(proposal);
+		}
+	}
+
+	fn activate_podr2_key(now: BlockNumberOf<T>) -> Weight {
+		if let Some((key, activation)) = <ScheduledPodr2Key<T>>::get() {
+			if activation <= now {
+				if let Some(previous) = <TeePodr2Pk<T>>::get() {
+					<PreviousPodr2Pk<T>>::put(previous);
+				}
+				<TeePodr2Pk<T>>::put(key);
+				<ScheduledPodr2Key<T>>::kill();
+				Self::deposit_event(Event::<T>::Podr2KeyActivated);
+				return T::DbWeight::get().reads_writes(1, 3);
+			}
+		}
+
+		if let Some(proposal) = <PendingPodr2Key<T>>::get() {
+			if proposal.activation <= now {
+				<PendingPodr2Key<T>>::kill();
+				Self::deposit_event(Event::<T>::Podr2KeyProposalExpired);
+				return T::DbWeight::get().writes(1);
+			}
+		}
+
+		Weight::from_ref_time(0)
+	}
+
+	// Verifies either kind of attestation and returns its MRENCLAVE, MRSIGNER and report data.
+	fn verify_attestation(
+		attestation_report: &AttestationReport,
+	) -> Result<([u8; 32], [u8; 32], [u8; 64]), DispatchError> {
+		let now = <T as pallet_cess_staking::Config>::UnixTime::now().as_secs();
+		let crls = <RevocationLists<T>>::get();
+		let crls: Vec<&[u8]> = crls.iter().map(|crl| crl.as_slice()).collect();
+
+		match attestation_report {
+			AttestationReport::Ias(report) => {
+				let report = verify_miner_cert(
+					&report.sign,
+					&report.cert_der,
+					&report.report_json_raw,
+					&crls,
+					now,
+					T::MaxReportAge::get(),
+				).map_err(Error::<T>::from)?;
+				Ok((report.mr_enclave, report.mr_signer, report.report_data))
+			},
+			AttestationReport::Dcap(report) => {
+				let root_cert = <DcapRootCert<T>>::get().ok_or(Error::<T>::DcapRootNotSet)?;
+				let report = verify_dcap_quote(
+					&report.quote,
+					&report.tcb_info,
+					&report.tcb_info_signature,
+					&report.tcb_signing_cert,
+					&root_cert,
+					&crls,
+					now,
+				).map_err(Error::<T>::from)?;
+				Ok((report.mr_enclave, report.mr_signer, report.report_data))
+			},
+		}
+	}
+
+	// The whitelist stores MRENCLAVE values as hex strings.
+	fn is_whitelisted(
+		whitelist: &[MrEnclaveEntry<BlockNumberOf<T>>],
+		mr_enclave: &[u8; 32],
+		now: BlockNumberOf<T>,
+	) -> bool {
+		let hex = u8v_to_hex(mr_enclave);
+		whitelist.iter().any(|entry| {
+			entry.mr_enclave.eq_ignore_ascii_case(&hex) && entry.expiry.map_or(true, |expiry| now < expiry)
+		})
+	}
+
+	fn purge_expired_mr_enclaves(now: BlockNumberOf<T>) -> Weight {
+		let mut whitelist = <MrEnclaveWhitelist<T>>::get();
+		let expired: Vec<[u8; 64]> = whitelist
+			.iter()
+			.filter(|entry| entry.expiry.map_or(false, |expiry| expiry <= now))
+			.map(|entry| entry.mr_enclave)
+			.collect();
+		if expired.is_empty() {
+			return Weight::from_ref_time(0);
+		}
+
+		whitelist.retain(|entry| !expired.contains(&entry.mr_enclave));
+		<MrEnclaveWhitelist<T>>::put(whitelist);
+
+		let mut weight = T::DbWeight::get().writes(1);
+		for mr_enclave in expired {
+			Self::deposit_event(Event::<T>::MrEnclaveExpired { mr_enclave });
+			// Expiry is announced ahead of time, so workers only get the grace period.
+			if let Err(e) = Self::revoke_mr_enclave(&mr_enclave, false) {
+				log::warn!("failed to revoke expired MRENCLAVE: {:?}", e);
+			}
+			weight = weight.saturating_add(
+				T::DbWeight::get().reads_writes(<TeeWorkerMap<T>>::count() as u64, 1),
+			);
+		}
+
+		weight
+	}
+
+	// Deregisters the workers attested with `mr_enclave`, or schedules their removal
+	// at the end of the grace period unless they reattest with another measurement.
+	fn revoke_mr_enclave(mr_enclave: &[u8; 64], deregister: bool) -> DispatchResult {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let deadline = now.saturating_add(T::AttestationGracePeriod::get());
+
+		let affected: Vec<AccountOf<T>> = <TeeWorkerMap<T>>::iter()
+			.filter(|(_, info)| {
+				info.mr_enclave.map_or(false, |attested| u8v_to_hex(&attested).eq_ignore_ascii_case(mr_enclave))
+			})
+			.map(|(acc, _)| acc)
+			.collect();
+
+		for acc in affected {
+			if deregister {
+				// A compromised measurement has to be revocable even when no other worker can take
+				// the missions over, those are reassigned by the audit pallet once they time out.
+				if let Err(e) = T::VerifyMission::hand_off_missions(&acc) {
+					log::warn!("hand off missions of revoked tee worker failed: {:?}", e);
+				}
+				Self::remove_tee_worker(&acc);
+			} else {
+				Self::schedule_attestation_deadline(&acc, deadline)?;
+			}
+			Self::deposit_event(Event::<T>::MrEnclaveRevoked { acc, deregistered: deregister });
+		}
+
+		Ok(())
+	}
+}
+
+impl<T: Config> From<VerifyError> for Error<T> {
+	fn from(e: VerifyError) -> Self {
+		match e {
+			VerifyError::InvalidHex
+			| VerifyError::InvalidPublicKey
+			| VerifyError::InvalidSignature
+			| VerifyError::InvalidCertificate => Error::<T>::MalformedInput,
+			VerifyError::InvalidCrl => Error::<T>::InvalidCrl,
+			VerifyError::InvalidReport => Error::<T>::InvalidReport,
+			VerifyError::SignatureMismatch
+			| VerifyError::CertificateRejected
+			| VerifyError::QuoteRejected => Error::<T>::VerifyCertFailed,
+		}
+	}
+}
+
+pub trait VerifyMissionHandoff<AccountId> {
+	// Number of proofs still waiting for `acc` to verify them.
+	fn pending_missions(acc: &AccountId) -> u32;
+	// Moves the proofs waiting for `acc` to other active workers.
+	fn hand_off_missions(acc: &AccountId) -> DispatchResult;
+}
+
+impl<AccountId> VerifyMissionHandoff<AccountId> for () {
+	fn pending_missions(_acc: &AccountId) -> u32 {
+		0
+	}
+
+	fn hand_off_missions(_acc: &AccountId) -> DispatchResult {
+		Ok(())
+	}
+}
+
 pub trait ScheduleFind<AccountId> {
 	fn contains_scheduler(acc: AccountId) -> bool;
 	fn punish_scheduler(acc: AccountId) -> DispatchResult;
 	fn get_first_controller() -> Result<AccountId, DispatchError>;
 	fn get_controller_list() -> Vec<AccountId>;
+	fn get_credit_score(acc: AccountId) -> u32;
 }
 
 impl<T: Config> ScheduleFind<<T as frame_system::Config>::AccountId> for Pallet<T> {
@@ -309,13 +1011,26 @@ impl<T: Config> ScheduleFind<<T as frame_system::Config>::AccountId> for Pallet<
 		return Ok(controller_acc);
 	}
 
+	// Workers whose attestation lapsed or who are exiting are left out, so they get no new verify missions.
 	fn get_controller_list() -> Vec<AccountOf<T>> {
+		let now = <frame_system::Pallet<T>>::block_number();
+		let whitelist = <MrEnclaveWhitelist<T>>::get();
 		let mut acc_list: Vec<AccountOf<T>> = Default::default();
 
-		for (acc, _) in <TeeWorkerMap<T>>::iter() {
-			acc_list.push(acc);
+		for (acc, info) in <TeeWorkerMap<T>>::iter() {
+			if Self::is_healthy(&acc, &info, now, &whitelist) {
+				acc_list.push(acc);
+			}
 		}
 
 		acc_list
 	}
+
+	// Credits are recorded against the stash account, like punishments.
+	fn get_credit_score(acc: AccountOf<T>) -> u32 {
+		match TeeWorkerMap::<T>::get(&acc) {
+			Some(tee_worker) => T::CreditCounter::credit_score(&tee_worker.stash_account),
+			None => 0,
+		}
+	}
 }
@@ -0,0 +1,82 @@
+//! Storage migrations for the Tee Worker pallet.
+
+use super::*;
+use frame_support::traits::{GetStorageVersion, OnRuntimeUpgrade};
+
+pub mod v1 {
+	use super::*;
+
+	#[derive(Decode, Encode)]
+	struct OldTeeWorkerInfo<AccountId> {
+		controller_account: AccountId,
+		peer_id: PeerId,
+		node_key: NodePublicKey,
+		stash_account: AccountId,
+	}
+
+	/// Moves the registered workers to the attestation aware `TeeWorkerInfo` and
+	/// the whitelisted measurements to `MrEnclaveEntry`.
+	///
+	/// The measurement the old workers attested with was never stored, so they are
+	/// migrated without one. They keep serving for the attestation grace period, by
+	/// the end of which they have to reattest or are removed.
+	/// They also have to publish their endpoint and services with `update_endpoint`.
+	/// Whitelisted measurements are kept without an expiry.
+	pub struct MigrateToV1<T>(sp_std::marker::PhantomData<T>);
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				log::warn!("Skipping tee-worker v1 migration, should be removed");
+				return T::DbWeight::get().reads(1);
+			}
+
+			let now = <frame_system::Pallet<T>>::block_number();
+			let mut migrated: Vec<AccountOf<T>> = Default::default();
+			<TeeWorkerMap<T>>::translate::<OldTeeWorkerInfo<AccountOf<T>>, _>(|acc, old| {
+				migrated.push(acc);
+				Some(TeeWorkerInfo::<T> {
+					controller_account: old.controller_account,
+					peer_id: old.peer_id,
+					endpoint: IpAddress::IPV4([0u8; 4], 0),
+					multiaddr: Default::default(),
+					capabilities: Default::default(),
+					node_key: old.node_key,
+					stash_account: old.stash_account,
+					attested_at: now,
+					mr_enclave: None,
+				})
+			});
+
+			let mut whitelisted: u64 = 0;
+			let translated = <MrEnclaveWhitelist<T>>::translate::<BoundedVec<[u8; 64], T::MaxWhitelist>, _>(|old| {
+				let entries: Vec<MrEnclaveEntry<BlockNumberOf<T>>> = old?
+					.into_iter()
+					.map(|mr_enclave| MrEnclaveEntry { mr_enclave, expiry: None })
+					.collect();
+				whitelisted = entries.len() as u64;
+				entries.try_into().ok()
+			});
+			if translated.is_err() {
+				log::error!("failed to decode the tee-worker whitelist, it has to be set up again");
+			}
+
+			let deadline = now.saturating_add(T::AttestationGracePeriod::get());
+			for acc in migrated.iter() {
+				if let Err(e) = Pallet::<T>::schedule_attestation_deadline(acc, deadline) {
+					log::error!("failed to schedule the reattest deadline of a migrated tee worker: {:?}", e);
+				}
+			}
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			let count = migrated.len() as u64;
+			log::info!("tee-worker v1 applied, {} workers and {} measurements migrated", count, whitelisted);
+			T::DbWeight::get().reads_writes(count * 2 + 2, count * 2 + 2)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(_state: Vec<u8>) -> Result<(), &'static str> {
+			frame_support::ensure!(Pallet::<T>::on_chain_storage_version() == 1, "tee-worker v1 not applied");
+			Ok(())
+		}
+	}
+}
@@ -0,0 +1,310 @@
+//! Test utilities
+
+use super::*;
+use crate as tee_worker;
+use frame_election_provider_support::{onchain, SequentialPhragmen};
+use frame_support::{
+	parameter_types,
+	traits::{ConstU32, OnInitialize},
+};
+use pallet_cess_staking::{RewardDestination, TestBenchmarkingConfig, UseNominatorsAndValidatorsMap, UseValidatorsMap};
+use sp_core::H256;
+use sp_runtime::{
+	testing::Header,
+	traits::{BlakeTwo256, IdentityLookup},
+	Perbill,
+};
+use std::{cell::RefCell, collections::BTreeMap, marker::PhantomData};
+use cp_scheduler_credit::SchedulerStashAccountFinder;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+pub type AccountId = u64;
+type BlockNumber = u64;
+type Balance = u64;
+
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system,
+		Balances: pallet_balances,
+		Timestamp: pallet_timestamp,
+		Staking: pallet_cess_staking,
+		SchedulerCredit: pallet_scheduler_credit,
+		TeeWorker: tee_worker,
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::Everything;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type RuntimeOrigin = RuntimeOrigin;
+	type RuntimeCall = RuntimeCall;
+	type Index = u64;
+	type BlockNumber = BlockNumber;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<Balance>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+	type Balance = Balance;
+	type DustRemoval = ();
+	type RuntimeEvent = RuntimeEvent;
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+}
+
+parameter_types! {
+	pub const MinimumPeriod: u64 = 1;
+}
+
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+pub struct OnChainSeqPhragmen;
+
+impl onchain::Config for OnChainSeqPhragmen {
+	type System = Test;
+	type Solver = SequentialPhragmen<AccountId, Perbill>;
+	type DataProvider = Staking;
+	type WeightInfo = ();
+	type MaxWinners = ConstU32<100>;
+	type VotersBound = ConstU32<{ u32::MAX }>;
+	type TargetsBound = ConstU32<{ u32::MAX }>;
+}
+
+impl pallet_cess_staking::Config for Test {
+	const ERAS_PER_YEAR: u64 = 8766;
+	const FIRST_YEAR_VALIDATOR_REWARDS: Balance = 618_000_000;
+	const FIRST_YEAR_SMINER_REWARDS: Balance = 309_000_000;
+	const REWARD_DECREASE_RATIO: Perbill = Perbill::from_perthousand(794);
+	const REWARD_DECREASE_YEARS: u64 = 30;
+	type SminerRewardPool = ();
+	type Currency = Balances;
+	type CurrencyBalance = Balance;
+	type UnixTime = Timestamp;
+	type CurrencyToVote = frame_support::traits::SaturatingCurrencyToVote;
+	type ElectionProvider = onchain::OnChainExecution<OnChainSeqPhragmen>;
+	type GenesisElectionProvider = Self::ElectionProvider;
+	type MaxNominations = ConstU32<16>;
+	type RewardRemainder = ();
+	type RuntimeEvent = RuntimeEvent;
+	type Slash = ();
+	type Reward = ();
+	type SessionsPerEra = ();
+	type BondingDuration = ();
+	type SlashDeferDuration = ();
+	type SlashCancelOrigin = frame_system::EnsureRoot<Self::AccountId>;
+	type SessionInterface = ();
+	type EraPayout = ();
+	type NextNewSession = ();
+	type MaxNominatorRewardedPerValidator = ConstU32<64>;
+	type OffendingValidatorsThreshold = ();
+	type VoterList = UseNominatorsAndValidatorsMap<Self>;
+	type TargetList = UseValidatorsMap<Self>;
+	type MaxUnlockingChunks = ConstU32<32>;
+	type HistoryDepth = ConstU32<84>;
+	type OnStakerSlash = ();
+	type BenchmarkingConfig = TestBenchmarkingConfig;
+	type WeightInfo = ();
+}
+
+pub struct MockStashAccountFinder<AccountId>(PhantomData<AccountId>);
+
+impl<AccountId: Clone> SchedulerStashAccountFinder<AccountId> for MockStashAccountFinder<AccountId> {
+	fn find_stash_account_id(ctrl_account_id: &AccountId) -> Option<AccountId> {
+		Some(ctrl_account_id.clone())
+	}
+}
+
+parameter_types! {
+	pub const PeriodDuration: BlockNumber = 64_000;
+}
+
+impl pallet_scheduler_credit::Config for Test {
+	type StashAccountFinder = MockStashAccountFinder<Self::AccountId>;
+	type PeriodDuration = PeriodDuration;
+}
+
+thread_local! {
+	pub static PENDING_MISSIONS: RefCell<BTreeMap<AccountId, u32>> = RefCell::new(BTreeMap::new());
+	pub static HANDED_OFF: RefCell<Vec<AccountId>> = RefCell::new(Vec::new());
+	pub static NO_TEE_FOR_HANDOFF: RefCell<bool> = RefCell::new(false);
+}
+
+// Stands in for the audit pallet, which owns the verify missions.
+pub struct MockVerifyMission;
+
+impl MockVerifyMission {
+	pub fn set_pending(acc: AccountId, count: u32) {
+		PENDING_MISSIONS.with(|p| p.borrow_mut().insert(acc, count));
+	}
+
+	pub fn handed_off() -> Vec<AccountId> {
+		HANDED_OFF.with(|h| h.borrow().clone())
+	}
+
+	// Makes hand offs fail, as when there is no other worker to take the missions.
+	pub fn set_no_tee_for_handoff() {
+		NO_TEE_FOR_HANDOFF.with(|n| *n.borrow_mut() = true);
+	}
+}
+
+impl VerifyMissionHandoff<AccountId> for MockVerifyMission {
+	fn pending_missions(acc: &AccountId) -> u32 {
+		PENDING_MISSIONS.with(|p| p.borrow().get(acc).copied().unwrap_or(0))
+	}
+
+	fn hand_off_missions(acc: &AccountId) -> DispatchResult {
+		if NO_TEE_FOR_HANDOFF.with(|n| *n.borrow()) {
+			return Err(DispatchError::Other("no tee for hand off"));
+		}
+		PENDING_MISSIONS.with(|p| p.borrow_mut().remove(acc));
+		HANDED_OFF.with(|h| h.borrow_mut().push(*acc));
+		Ok(())
+	}
+}
+
+parameter_types! {
+	pub const TeeWorkerPalletId: PalletId = PalletId(*b"filmpdpt");
+	#[derive(Clone, PartialEq, Eq)]
+	pub const StringLimit: u32 = 100;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const ParamsLimit: u32 = 359;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const SchedulerMaximum: u32 = 100;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const MaxWhitelist: u32 = 4;
+	pub const MaxReportAge: u64 = 3600;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const MaxCrls: u32 = 4;
+	pub const AttestationValidity: u64 = 100;
+	pub const AttestationGracePeriod: u64 = 20;
+	pub const Podr2KeyThreshold: Perbill = Perbill::from_percent(67);
+	pub const Podr2KeyActivationDelay: u64 = 10;
+	pub const ExitTimeout: u64 = 30;
+}
+
+impl Config for Test {
+	type RuntimeEvent = RuntimeEvent;
+	type Currency = Balances;
+	type TeeWorkerPalletId = TeeWorkerPalletId;
+	type StringLimit = StringLimit;
+	type ParamsLimit = ParamsLimit;
+	type SchedulerMaximum = SchedulerMaximum;
+	type WeightInfo = ();
+	type CreditCounter = SchedulerCredit;
+	type MaxWhitelist = MaxWhitelist;
+	type MaxReportAge = MaxReportAge;
+	type MaxCrls = MaxCrls;
+	type AttestationValidity = AttestationValidity;
+	type AttestationGracePeriod = AttestationGracePeriod;
+	type Podr2KeyThreshold = Podr2KeyThreshold;
+	type Podr2KeyActivationDelay = Podr2KeyActivationDelay;
+	type VerifyMission = MockVerifyMission;
+	type ExitTimeout = ExitTimeout;
+}
+
+pub mod consts {
+	use super::AccountId;
+
+	// (stash, controller) pairs, bonded in `new_test_ext`.
+	pub const WORKER1: (AccountId, AccountId) = (11, 10);
+	pub const WORKER2: (AccountId, AccountId) = (21, 20);
+	pub const WORKER3: (AccountId, AccountId) = (31, 30);
+
+	// MRENCLAVE of the enclave the mock workers run.
+	pub const MR_ENCLAVE: [u8; 32] = [0xab; 32];
+}
+
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	use consts::*;
+	let mut t = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: [WORKER1, WORKER2, WORKER3]
+			.iter()
+			.flat_map(|(stash, controller)| vec![(*stash, 1_000_000), (*controller, 1_000_000)])
+			.collect(),
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+	let mut ext = sp_io::TestExternalities::new(t);
+	ext.execute_with(|| {
+		System::set_block_number(1); //must set block_number, otherwise the deposit_event() don't work
+		pallet_cess_staking::CurrentEra::<Test>::put(0);
+		for (stash, controller) in [WORKER1, WORKER2, WORKER3] {
+			Staking::bond(RuntimeOrigin::signed(stash), controller, 100_000, RewardDestination::Staked).unwrap();
+		}
+		PENDING_MISSIONS.with(|p| p.borrow_mut().clear());
+		HANDED_OFF.with(|h| h.borrow_mut().clear());
+		NO_TEE_FOR_HANDOFF.with(|n| *n.borrow_mut() = false);
+	});
+	ext
+}
+
+pub fn run_to_block(n: u64) {
+	while System::block_number() < n {
+		System::set_block_number(System::block_number() + 1);
+		<TeeWorker as OnInitialize<BlockNumber>>::on_initialize(System::block_number());
+	}
+}
+
+// Whitelists the mock enclave, optionally until `expiry`.
+pub fn whitelist_mr_enclave(expiry: Option<BlockNumber>) {
+	TeeWorker::update_whitelist(RuntimeOrigin::root(), u8v_to_hex(&consts::MR_ENCLAVE), expiry).unwrap();
+}
+
+// Registers a worker as if it passed attestation at the current block, which cannot be
+// produced without an enclave.
+pub fn register_attested(worker: (AccountId, AccountId)) {
+	let (stash, controller) = worker;
+	let now = System::block_number();
+	let info = TeeWorkerInfo::<Test> {
+		controller_account: controller,
+		peer_id: [controller as u8; 38],
+		endpoint: IpAddress::IPV4([127, 0, 0, controller as u8], 15001),
+		multiaddr: Default::default(),
+		capabilities: TeeCapabilities { tag_calculation: true, verification: true, filler_generation: false },
+		node_key: NodePublicKey::from_raw([controller as u8; 32]),
+		stash_account: stash,
+		attested_at: now,
+		mr_enclave: Some(consts::MR_ENCLAVE),
+	};
+	TeeWorkerMap::<Test>::insert(&controller, info);
+	TeeWorker::schedule_attestation_deadline(&controller, TeeWorker::attestation_deadline_of(now)).unwrap();
+}
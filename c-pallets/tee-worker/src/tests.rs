@@ -1,35 +1,574 @@
-
 use super::*;
-use libp2p::core::{
-	multiaddr::{Multiaddr, Protocol},
-	PeerId,
-};
+use crate::{mock::*, Event as TeeWorkerEvent};
+use frame_support::{assert_noop, assert_ok, traits::OnRuntimeUpgrade};
+use mock::consts::*;
+
+fn capabilities() -> TeeCapabilities {
+	TeeCapabilities { tag_calculation: true, verification: true, filler_generation: false }
+}
+
+// A DCAP report that can only get as far as the root certificate check.
+fn dcap_report() -> AttestationReport {
+	AttestationReport::Dcap(DcapAttestationReport {
+		quote: Default::default(),
+		tcb_info: Default::default(),
+		tcb_info_signature: [0u8; 64],
+		tcb_signing_cert: Default::default(),
+	})
+}
+
+fn register(origin: AccountId, stash: AccountId, capabilities: TeeCapabilities) -> DispatchResult {
+	TeeWorker::register(
+		RuntimeOrigin::signed(origin),
+		stash,
+		NodePublicKey::from_raw([origin as u8; 32]),
+		[origin as u8; 38],
+		IpAddress::IPV4([127, 0, 0, 1], 15001),
+		Default::default(),
+		capabilities,
+		dcap_report(),
+	)
+}
+
+#[test]
+fn register_checks_bond_and_capabilities() {
+	new_test_ext().execute_with(|| {
+		let (stash, controller) = WORKER1;
+		assert_noop!(register(controller, stash, TeeCapabilities::default()), Error::<Test>::NoCapability);
+		assert_noop!(register(controller, 99, capabilities()), Error::<Test>::NotBond);
+		assert_noop!(register(WORKER2.1, stash, capabilities()), Error::<Test>::NotController);
+
+		register_attested(WORKER1);
+		assert_noop!(register(controller, stash, capabilities()), Error::<Test>::AlreadyRegistration);
+	});
+}
+
+#[test]
+fn register_requires_attestation() {
+	new_test_ext().execute_with(|| {
+		let (stash, controller) = WORKER1;
+		assert_noop!(register(controller, stash, capabilities()), Error::<Test>::DcapRootNotSet);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&controller));
+	});
+}
+
+#[test]
+fn reattest_keeps_the_worker_on_a_rejected_report() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(
+			TeeWorker::reattest(RuntimeOrigin::signed(WORKER1.1), dcap_report()),
+			Error::<Test>::NonTeeWorker
+		);
+
+		register_attested(WORKER1);
+		run_to_block(50);
+		assert_noop!(
+			TeeWorker::reattest(RuntimeOrigin::signed(WORKER1.1), dcap_report()),
+			Error::<Test>::DcapRootNotSet
+		);
+		assert_eq!(TeeWorkerMap::<Test>::get(&WORKER1.1).unwrap().attested_at, 1);
+	});
+}
+
+#[test]
+fn attestation_expires_after_grace_period() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		// Attested at block 1: valid until 101, removed at the end of the grace period.
+		assert_eq!(AttestationDeadline::<Test>::get(121).into_inner(), vec![WORKER1.1]);
+		assert_eq!(TeeWorker::get_controller_list(), vec![WORKER1.1]);
+
+		run_to_block(101);
+		assert!(TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(TeeWorker::get_controller_list().is_empty());
+		assert_noop!(TeeWorker::ensure_attested(&WORKER1.1), Error::<Test>::AttestationLapsed);
+
+		run_to_block(121);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(!AttestationDeadline::<Test>::contains_key(121));
+		System::assert_last_event(TeeWorkerEvent::<Test>::AttestationExpired { acc: WORKER1.1 }.into());
+	});
+}
+
+#[test]
+fn reattested_worker_is_not_expired() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+
+		// What a successful `reattest` does once the report checks out.
+		run_to_block(110);
+		TeeWorkerMap::<Test>::mutate(&WORKER1.1, |info| info.as_mut().unwrap().attested_at = 110);
+		assert_ok!(TeeWorker::schedule_attestation_deadline(&WORKER1.1, TeeWorker::attestation_deadline_of(110)));
+		assert_eq!(TeeWorker::get_controller_list(), vec![WORKER1.1]);
+
+		run_to_block(121);
+		assert!(TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+
+		run_to_block(230);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+	});
+}
+
+#[test]
+fn migration_v1_keeps_the_whitelist_without_expiry() {
+	new_test_ext().execute_with(|| {
+		let mr_enclave = u8v_to_hex(&MR_ENCLAVE);
+		let old: Vec<[u8; 64]> = vec![mr_enclave];
+		frame_support::storage::unhashed::put(&MrEnclaveWhitelist::<Test>::hashed_key(), &old);
+		StorageVersion::new(0).put::<TeeWorker>();
+
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(
+			MrEnclaveWhitelist::<Test>::get().into_inner(),
+			vec![MrEnclaveEntry { mr_enclave, expiry: None }]
+		);
+	});
+}
+
+#[test]
+fn migration_v1_forces_a_reattest() {
+	#[derive(Encode)]
+	struct OldTeeWorkerInfo {
+		controller_account: AccountId,
+		peer_id: PeerId,
+		node_key: NodePublicKey,
+		stash_account: AccountId,
+	}
+
+	new_test_ext().execute_with(|| {
+		let whitelist: Vec<[u8; 64]> = vec![u8v_to_hex(&MR_ENCLAVE)];
+		frame_support::storage::unhashed::put(&MrEnclaveWhitelist::<Test>::hashed_key(), &whitelist);
+		let (stash, controller) = WORKER1;
+		let old = OldTeeWorkerInfo {
+			controller_account: controller,
+			peer_id: [1u8; 38],
+			node_key: NodePublicKey::from_raw([1u8; 32]),
+			stash_account: stash,
+		};
+		frame_support::storage::unhashed::put(&TeeWorkerMap::<Test>::hashed_key_for(&controller), &old);
+		StorageVersion::new(0).put::<TeeWorker>();
+
+		run_to_block(5);
+		migrations::v1::MigrateToV1::<Test>::on_runtime_upgrade();
+		assert_eq!(TeeWorker::on_chain_storage_version(), 1);
+
+		let info = TeeWorkerMap::<Test>::get(&controller).unwrap();
+		assert_eq!(info.stash_account, stash);
+		assert_eq!(info.peer_id, [1u8; 38]);
+		assert_eq!(info.attested_at, 5);
+		assert_eq!(info.mr_enclave, None);
+		// The worker keeps its missions while it has time to reattest.
+		assert_eq!(TeeWorker::get_controller_list(), vec![controller]);
+		// It is not handed to miners before it publishes where it can be reached.
+		assert_eq!(info.endpoint, IpAddress::IPV4([0u8; 4], 0));
+		assert!(info.capabilities.is_empty());
+		assert!(TeeWorker::healthy_workers().is_empty());
+		assert_eq!(AttestationDeadline::<Test>::get(25).into_inner(), vec![controller]);
+
+		run_to_block(24);
+		assert_eq!(TeeWorker::get_controller_list(), vec![controller]);
+		run_to_block(25);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&controller));
+		assert!(TeeWorker::get_controller_list().is_empty());
+	});
+}
+
+#[test]
+fn update_whitelist_rejects_a_past_expiry() {
+	new_test_ext().execute_with(|| {
+		run_to_block(10);
+		let mr_enclave = u8v_to_hex(&MR_ENCLAVE);
+		assert_noop!(
+			TeeWorker::update_whitelist(RuntimeOrigin::root(), mr_enclave, Some(10)),
+			Error::<Test>::InvalidExpiry
+		);
+
+		assert_ok!(TeeWorker::update_whitelist(RuntimeOrigin::root(), mr_enclave, Some(50)));
+		// Updating an entry changes its expiry instead of adding it twice.
+		assert_ok!(TeeWorker::update_whitelist(RuntimeOrigin::root(), mr_enclave, None));
+		assert_eq!(
+			MrEnclaveWhitelist::<Test>::get().into_inner(),
+			vec![MrEnclaveEntry { mr_enclave, expiry: None }]
+		);
+	});
+}
+
+#[test]
+fn mr_signer_is_checked_once_configured() {
+	new_test_ext().execute_with(|| {
+		let signer = [0x5au8; 32];
+		// Without a configured signer any enclave signer is accepted.
+		assert_ok!(TeeWorker::check_mr_signer(&[0u8; 32]));
+
+		assert_noop!(TeeWorker::update_mr_signer(RuntimeOrigin::signed(WORKER1.1), Some(signer)), sp_runtime::DispatchError::BadOrigin);
+		assert_ok!(TeeWorker::update_mr_signer(RuntimeOrigin::root(), Some(signer)));
+		System::assert_last_event(TeeWorkerEvent::<Test>::UpdateMrSigner { mr_signer: Some(signer) }.into());
+		assert_ok!(TeeWorker::check_mr_signer(&signer));
+		assert_noop!(TeeWorker::check_mr_signer(&[0u8; 32]), Error::<Test>::MrSignerMismatch);
+
+		assert_ok!(TeeWorker::update_mr_signer(RuntimeOrigin::root(), None));
+		assert_eq!(None, MrSigner::<Test>::get());
+		assert_ok!(TeeWorker::check_mr_signer(&[0u8; 32]));
+	});
+}
+
+#[test]
+fn expired_mr_enclave_gives_workers_the_grace_period() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(Some(50));
+		register_attested(WORKER1);
+		MockVerifyMission::set_pending(WORKER1.1, 2);
+
+		run_to_block(50);
+		assert!(MrEnclaveWhitelist::<Test>::get().is_empty());
+		System::assert_has_event(
+			TeeWorkerEvent::<Test>::MrEnclaveExpired { mr_enclave: u8v_to_hex(&MR_ENCLAVE) }.into(),
+		);
+		System::assert_has_event(
+			TeeWorkerEvent::<Test>::MrEnclaveRevoked { acc: WORKER1.1, deregistered: false }.into(),
+		);
+		assert!(TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(TeeWorker::get_controller_list().is_empty());
+		assert_eq!(AttestationDeadline::<Test>::get(70).into_inner(), vec![WORKER1.1]);
+
+		run_to_block(70);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		// The missions left behind are handed off before the worker is removed.
+		assert_eq!(MockVerifyMission::handed_off(), vec![WORKER1.1]);
+		assert_eq!(MockVerifyMission::pending_missions(&WORKER1.1), 0);
+	});
+}
+
+#[test]
+fn revoked_mr_enclave_deregisters_workers() {
+	new_test_ext().execute_with(|| {
+		let mr_enclave = u8v_to_hex(&MR_ENCLAVE);
+		assert_noop!(
+			TeeWorker::remove_from_whitelist(RuntimeOrigin::root(), mr_enclave, true),
+			Error::<Test>::NotWhitelisted
+		);
+
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		MockVerifyMission::set_pending(WORKER1.1, 3);
+
+		assert_ok!(TeeWorker::remove_from_whitelist(RuntimeOrigin::root(), mr_enclave, true));
+		assert!(MrEnclaveWhitelist::<Test>::get().is_empty());
+		assert_eq!(TeeWorkerMap::<Test>::count(), 0);
+		let mut handed_off = MockVerifyMission::handed_off();
+		handed_off.sort();
+		assert_eq!(handed_off, vec![WORKER1.1, WORKER2.1]);
+		System::assert_has_event(
+			TeeWorkerEvent::<Test>::MrEnclaveRevoked { acc: WORKER1.1, deregistered: true }.into(),
+		);
+	});
+}
+
+#[test]
+fn revoked_mr_enclave_deregisters_the_only_worker() {
+	new_test_ext().execute_with(|| {
+		let mr_enclave = u8v_to_hex(&MR_ENCLAVE);
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		MockVerifyMission::set_pending(WORKER1.1, 3);
+		MockVerifyMission::set_no_tee_for_handoff();
+
+		assert_ok!(TeeWorker::remove_from_whitelist(RuntimeOrigin::root(), mr_enclave, true));
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		// The missions stay with the audit pallet, which reassigns them once they time out.
+		assert!(MockVerifyMission::handed_off().is_empty());
+		assert_eq!(MockVerifyMission::pending_missions(&WORKER1.1), 3);
+		System::assert_has_event(
+			TeeWorkerEvent::<Test>::MrEnclaveRevoked { acc: WORKER1.1, deregistered: true }.into(),
+		);
+	});
+}
+
+#[test]
+fn revoked_mr_enclave_can_leave_time_to_reattest() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		run_to_block(10);
+
+		assert_ok!(TeeWorker::remove_from_whitelist(RuntimeOrigin::root(), u8v_to_hex(&MR_ENCLAVE), false));
+		assert!(TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(MockVerifyMission::handed_off().is_empty());
+		assert_eq!(AttestationDeadline::<Test>::get(30).into_inner(), vec![WORKER1.1]);
+
+		run_to_block(30);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert_eq!(MockVerifyMission::handed_off(), vec![WORKER1.1]);
+	});
+}
+
+#[test]
+fn update_endpoint_works() {
+	new_test_ext().execute_with(|| {
+		let endpoint = IpAddress::IPV6([1, 2, 3, 4, 5, 6, 7, 8], 16001);
+		let multiaddr: Multiaddr = b"/ip6/1:2:3:4:5:6:7:8/tcp/16001".to_vec().try_into().unwrap();
+		let services = TeeCapabilities { tag_calculation: false, verification: true, filler_generation: true };
+		assert_noop!(
+			TeeWorker::update_endpoint(RuntimeOrigin::signed(WORKER1.1), endpoint.clone(), multiaddr.clone(), services),
+			Error::<Test>::NonTeeWorker
+		);
+
+		register_attested(WORKER1);
+		assert_noop!(
+			TeeWorker::update_endpoint(
+				RuntimeOrigin::signed(WORKER1.1),
+				endpoint.clone(),
+				multiaddr.clone(),
+				TeeCapabilities::default()
+			),
+			Error::<Test>::NoCapability
+		);
+
+		assert_ok!(TeeWorker::update_endpoint(
+			RuntimeOrigin::signed(WORKER1.1),
+			endpoint.clone(),
+			multiaddr.clone(),
+			services
+		));
+		let info = TeeWorkerMap::<Test>::get(&WORKER1.1).unwrap();
+		assert_eq!(info.endpoint, endpoint);
+		assert_eq!(info.multiaddr, multiaddr);
+		assert_eq!(info.capabilities, services);
+		System::assert_last_event(TeeWorkerEvent::<Test>::UpdateScheduler { acc: WORKER1.1, endpoint }.into());
+	});
+}
+
+#[test]
+fn healthy_workers_leaves_out_lapsed_and_exiting_workers() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		register_attested(WORKER3);
+		TeeWorkerMap::<Test>::mutate(&WORKER3.1, |info| info.as_mut().unwrap().mr_enclave = Some([0xcd; 32]));
+		assert_ok!(TeeWorker::exit(RuntimeOrigin::signed(WORKER2.1)));
+
+		let info = TeeWorkerMap::<Test>::get(&WORKER1.1).unwrap();
+		assert_eq!(
+			TeeWorker::healthy_workers(),
+			vec![TeeWorkerEndpoint {
+				controller_account: WORKER1.1,
+				peer_id: info.peer_id,
+				endpoint: info.endpoint,
+				multiaddr: info.multiaddr,
+				capabilities: info.capabilities,
+			}]
+		);
+
+		run_to_block(101);
+		assert!(TeeWorker::healthy_workers().is_empty());
+	});
+}
+
+#[test]
+fn complete_exit_hands_off_missions() {
+	new_test_ext().execute_with(|| {
+		assert_noop!(TeeWorker::exit(RuntimeOrigin::signed(WORKER1.1)), Error::<Test>::NonTeeWorker);
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		assert_noop!(TeeWorker::complete_exit(RuntimeOrigin::signed(WORKER1.1)), Error::<Test>::NotExiting);
+
+		assert_ok!(TeeWorker::exit(RuntimeOrigin::signed(WORKER1.1)));
+		assert_noop!(TeeWorker::exit(RuntimeOrigin::signed(WORKER1.1)), Error::<Test>::AlreadyExiting);
+		assert_eq!(Exiting::<Test>::get(&WORKER1.1), Some(31));
+		assert_eq!(ExitDeadline::<Test>::get(31).into_inner(), vec![WORKER1.1]);
+		assert!(TeeWorker::get_controller_list().is_empty());
+
+		MockVerifyMission::set_pending(WORKER1.1, 1);
+		assert_ok!(TeeWorker::complete_exit(RuntimeOrigin::signed(WORKER1.1)));
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(!Exiting::<Test>::contains_key(&WORKER1.1));
+		assert_eq!(MockVerifyMission::handed_off(), vec![WORKER1.1]);
+		System::assert_last_event(TeeWorkerEvent::<Test>::Exit { acc: WORKER1.1 }.into());
+
+		// The deadline of a completed exit passes without effect.
+		run_to_block(31);
+		assert!(!ExitDeadline::<Test>::contains_key(31));
+		System::assert_last_event(TeeWorkerEvent::<Test>::Exit { acc: WORKER1.1 }.into());
+	});
+}
 
 #[test]
-fn cryptos_are_compatible() {
-	use sp_core::crypto::Pair;
+fn timed_out_exit_without_missions_is_not_slashed() {
+	new_test_ext().execute_with(|| {
+		pallet_cess_staking::MinValidatorBond::<Test>::put(1_000);
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		assert_ok!(TeeWorker::exit(RuntimeOrigin::signed(WORKER1.1)));
 
-	let libp2p_secret = libp2p::identity::Keypair::generate_ed25519();
-	let libp2p_public = libp2p_secret.public();
+		run_to_block(30);
+		assert!(TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
 
-	let sp_core_secret = {
-		let libp2p::identity::Keypair::Ed25519(libp2p_ed_secret) = libp2p_secret.clone();
-		sp_core::ed25519::Pair::from_seed_slice(&libp2p_ed_secret.secret().as_ref()).unwrap()
-	};
+		run_to_block(31);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert!(!Exiting::<Test>::contains_key(&WORKER1.1));
+		assert!(MockVerifyMission::handed_off().is_empty());
+		System::assert_last_event(TeeWorkerEvent::<Test>::ExitForced { acc: WORKER1.1, slashed: false }.into());
+		assert_eq!(Balances::free_balance(WORKER1.0), 1_000_000);
+		assert_eq!(SchedulerCredit::current_scheduler_credits(WORKER1.0).punishment_count, 0);
+		assert_eq!(TeeWorker::get_controller_list(), vec![WORKER2.1]);
+	});
+}
+
+#[test]
+fn timed_out_exit_with_missions_is_slashed() {
+	new_test_ext().execute_with(|| {
+		// Schedulers are slashed 5% of the minimum validator bond.
+		pallet_cess_staking::MinValidatorBond::<Test>::put(1_000);
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		assert_ok!(TeeWorker::exit(RuntimeOrigin::signed(WORKER1.1)));
+		MockVerifyMission::set_pending(WORKER1.1, 2);
+
+		run_to_block(31);
+		assert!(!TeeWorkerMap::<Test>::contains_key(&WORKER1.1));
+		assert_eq!(MockVerifyMission::handed_off(), vec![WORKER1.1]);
+		assert_eq!(MockVerifyMission::pending_missions(&WORKER1.1), 0);
+		System::assert_last_event(TeeWorkerEvent::<Test>::ExitForced { acc: WORKER1.1, slashed: true }.into());
+		assert_eq!(Balances::free_balance(WORKER1.0), 1_000_000 - 50);
+		assert_eq!(Staking::ledger(WORKER1.1).unwrap().active, 100_000 - 50);
+		assert_eq!(SchedulerCredit::current_scheduler_credits(WORKER1.0).punishment_count, 1);
+	});
+}
+
+#[test]
+fn propose_podr2_key_requires_an_attested_worker() {
+	new_test_ext().execute_with(|| {
+		let key = [1u8; 270];
+		assert_noop!(
+			TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), key, 20),
+			Error::<Test>::NonTeeWorker
+		);
+
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		assert_noop!(
+			TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), key, 10),
+			Error::<Test>::ActivationTooEarly
+		);
+
+		run_to_block(101);
+		assert_noop!(
+			TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), key, 120),
+			Error::<Test>::AttestationLapsed
+		);
+	});
+}
+
+#[test]
+fn podr2_key_is_scheduled_once_the_threshold_approves() {
+	new_test_ext().execute_with(|| {
+		let key = [1u8; 270];
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		register_attested(WORKER3);
+
+		// 67% of three workers rounds up to all of them.
+		assert_ok!(TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), key, 20));
+		assert_eq!(PendingPodr2Key::<Test>::get().unwrap().approvals.into_inner(), vec![WORKER1.1]);
+		assert_noop!(
+			TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER2.1), [2u8; 270], 20),
+			Error::<Test>::RotationInProgress
+		);
+		assert_noop!(TeeWorker::approve_podr2_key(RuntimeOrigin::signed(WORKER1.1)), Error::<Test>::AlreadyApproved);
+
+		assert_ok!(TeeWorker::approve_podr2_key(RuntimeOrigin::signed(WORKER2.1)));
+		assert!(PendingPodr2Key::<Test>::exists());
+		assert!(!ScheduledPodr2Key::<Test>::exists());
 
-	let sp_core_public = sp_core_secret.public();
+		assert_ok!(TeeWorker::approve_podr2_key(RuntimeOrigin::signed(WORKER3.1)));
+		assert!(!PendingPodr2Key::<Test>::exists());
+		assert_eq!(ScheduledPodr2Key::<Test>::get(), Some((key, 20)));
+		System::assert_last_event(TeeWorkerEvent::<Test>::Podr2KeyScheduled { activation: 20 }.into());
+		assert_noop!(
+			TeeWorker::approve_podr2_key(RuntimeOrigin::signed(WORKER2.1)),
+			Error::<Test>::NoPendingRotation
+		);
+	});
+}
+
+#[test]
+fn podr2_key_threshold_counts_only_healthy_workers() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		assert_ok!(TeeWorker::exit(RuntimeOrigin::signed(WORKER2.1)));
+
+		// The exiting worker is not counted, so the proposer alone reaches the threshold.
+		assert_ok!(TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), [1u8; 270], 20));
+		assert_eq!(ScheduledPodr2Key::<Test>::get(), Some(([1u8; 270], 20)));
+	});
+}
+
+#[test]
+fn force_podr2_key_replaces_a_pending_proposal() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		assert_ok!(TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), [1u8; 270], 20));
+		assert!(PendingPodr2Key::<Test>::exists());
 
-	println!("libp2p_public: {:?}, sp_core public: {:?}", libp2p_public, sp_core_public);
+		assert_noop!(
+			TeeWorker::force_podr2_key(RuntimeOrigin::signed(WORKER1.1), [2u8; 270], 5),
+			sp_runtime::DispatchError::BadOrigin
+		);
+		assert_noop!(
+			TeeWorker::force_podr2_key(RuntimeOrigin::root(), [2u8; 270], 1),
+			Error::<Test>::ActivationTooEarly
+		);
 
-	let message = b"we are more powerful than not to be better";
+		// Governance is not held to the activation delay.
+		assert_ok!(TeeWorker::force_podr2_key(RuntimeOrigin::root(), [2u8; 270], 5));
+		assert!(!PendingPodr2Key::<Test>::exists());
+		assert_eq!(ScheduledPodr2Key::<Test>::get(), Some(([2u8; 270], 5)));
+	});
+}
+
+#[test]
+fn podr2_key_is_activated_in_on_initialize() {
+	new_test_ext().execute_with(|| {
+		assert_ok!(TeeWorker::force_podr2_key(RuntimeOrigin::root(), [1u8; 270], 5));
+		run_to_block(4);
+		assert_eq!(TeeWorker::tee_podr2_pk(), None);
+
+		run_to_block(5);
+		assert_eq!(TeeWorker::tee_podr2_pk(), Some([1u8; 270]));
+		assert_eq!(TeeWorker::previous_podr2_pk(), None);
+		assert!(!ScheduledPodr2Key::<Test>::exists());
+		System::assert_last_event(TeeWorkerEvent::<Test>::Podr2KeyActivated.into());
 
-	let libp2p_signature = libp2p_secret.sign(message).unwrap();
-	let sp_core_signature = sp_core_secret.sign(message); // no error expected...
+		// A rotation keeps the replaced key so existing tags can still be checked.
+		assert_ok!(TeeWorker::force_podr2_key(RuntimeOrigin::root(), [2u8; 270], 10));
+		run_to_block(10);
+		assert_eq!(TeeWorker::tee_podr2_pk(), Some([2u8; 270]));
+		assert_eq!(TeeWorker::previous_podr2_pk(), Some([1u8; 270]));
+	});
+}
+
+#[test]
+fn unapproved_podr2_key_proposal_expires() {
+	new_test_ext().execute_with(|| {
+		whitelist_mr_enclave(None);
+		register_attested(WORKER1);
+		register_attested(WORKER2);
+		assert_ok!(TeeWorker::propose_podr2_key(RuntimeOrigin::signed(WORKER1.1), [1u8; 270], 20));
 
-	assert!(sp_core::ed25519::Pair::verify(
-		&sp_core::ed25519::Signature::from_slice(&libp2p_signature).unwrap(),
-		message,
-		&sp_core_public
-	));
-	assert!(libp2p_public.verify(message, sp_core_signature.as_ref()));
+		run_to_block(20);
+		assert!(!PendingPodr2Key::<Test>::exists());
+		assert_eq!(TeeWorker::tee_podr2_pk(), None);
+		System::assert_last_event(TeeWorkerEvent::<Test>::Podr2KeyProposalExpired.into());
+	});
 }
@@ -6,8 +6,49 @@ use super::*;
 pub struct TeeWorkerInfo<T: pallet::Config> {
     pub controller_account: AccountOf<T>,
     pub peer_id: PeerId,
+    pub endpoint: IpAddress,
+    // Binary encoded libp2p multiaddr the worker listens on.
+    pub multiaddr: Multiaddr,
+    pub capabilities: TeeCapabilities,
     pub node_key: NodePublicKey,
     pub stash_account: AccountOf<T>,
+    // Block of the last successful attestation.
+    pub attested_at: BlockNumberOf<T>,
+    // MRENCLAVE of the last successful attestation. `None` for workers migrated from before
+    // attestations were stored, they count as attested until their reattest deadline.
+    pub mr_enclave: Option<[u8; 32]>,
+}
+
+// Services a worker offers to miners.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, Copy, RuntimeDebug, Default, MaxEncodedLen, TypeInfo)]
+pub struct TeeCapabilities {
+    pub tag_calculation: bool,
+    pub verification: bool,
+    pub filler_generation: bool,
+}
+
+impl TeeCapabilities {
+    pub fn is_empty(&self) -> bool {
+        !(self.tag_calculation || self.verification || self.filler_generation)
+    }
+}
+
+// How miners reach a healthy worker, as returned by the runtime API.
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, TypeInfo)]
+pub struct TeeWorkerEndpoint<AccountId> {
+    pub controller_account: AccountId,
+    pub peer_id: PeerId,
+    pub endpoint: IpAddress,
+    pub multiaddr: Multiaddr,
+    pub capabilities: TeeCapabilities,
+}
+
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct MrEnclaveEntry<Block> {
+    // Hex encoded MRENCLAVE.
+    pub mr_enclave: [u8; 64],
+    // Block from which the measurement is no longer accepted, if any.
+    pub expiry: Option<Block>,
 }
 
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, Default, MaxEncodedLen, TypeInfo)]
@@ -15,4 +56,32 @@ pub struct SgxAttestationReport {
     pub report_json_raw: Report,
     pub sign: ReportSign,
     pub cert_der: Cert,
-}
\ No newline at end of file
+}
+
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub struct DcapAttestationReport {
+    // SGX ECDSA quote, including the PCK certificate chain.
+    pub quote: DcapQuote,
+    // Raw `tcbInfo` JSON object of the platform FMSPC, exactly as signed by Intel.
+    pub tcb_info: TcbInfo,
+    pub tcb_info_signature: [u8; 64],
+    pub tcb_signing_cert: CertDer,
+}
+
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+pub enum AttestationReport {
+    // EPID attestation report signed by Intel IAS.
+    Ias(SgxAttestationReport),
+    // ECDSA quote verified with DCAP collateral.
+    Dcap(DcapAttestationReport),
+}
+
+#[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+#[codec(mel_bound())]
+pub struct Podr2KeyProposal<T: pallet::Config> {
+    pub key: Podr2Key,
+    // Block from which the new key replaces the current one.
+    pub activation: BlockNumberOf<T>,
+    pub approvals: BoundedVec<AccountOf<T>, T::SchedulerMaximum>,
+}
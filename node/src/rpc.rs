@@ -166,6 +166,7 @@ where
 	C::Api: pallet_mmr_rpc::MmrRuntimeApi<Block, <Block as sp_runtime::traits::Block>::Hash, BlockNumber>,
 	C::Api: pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>,
 	C::Api: RRSCApi<Block>,
+	C::Api: pallet_audit_rpc::AuditRuntimeApi<Block, AccountId, BlockNumber>,
 	C::Api: BlockBuilder<Block>,
 	C::Api: fp_rpc::ConvertTransactionRuntimeApi<Block>,
 	C::Api: fp_rpc::EthereumRuntimeRPCApi<Block>,
@@ -181,6 +182,7 @@ where
 		EthPubSubApiServer, EthSigner, Net, NetApiServer, Web3,	Web3ApiServer,
 	};
 	use pallet_mmr_rpc::{Mmr, MmrApiServer};
+	use pallet_audit_rpc::{Audit, AuditApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use cessc_consensus_rrsc_rpc::{ RRSC, RRSCApiServer };
 	use sc_finality_grandpa_rpc::{Grandpa, GrandpaApiServer};
@@ -234,6 +236,7 @@ where
 	// io.merge(Contracts::new(client.clone()).into_rpc())?;
 	io.merge(Mmr::new(client.clone()).into_rpc())?;
 	io.merge(TransactionPayment::new(client.clone()).into_rpc())?;
+	io.merge(Audit::new(client.clone()).into_rpc())?;
 	io.merge(
 		RRSC::new(
 			client.clone(),
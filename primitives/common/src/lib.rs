@@ -8,6 +8,7 @@ use frame_support::{
 use frame_support::{
 	BoundedVec,
 	pallet_prelude::ConstU32,
+	traits::Randomness,
 };
 use codec::{MaxEncodedLen};
 use scale_info::TypeInfo;
@@ -68,6 +69,14 @@ pub type ReportSign = BoundedVec<u8, ConstU32<344>>;
 pub type Report =  BoundedVec<u8, ConstU32<1354>>;
 pub type Cert = BoundedVec<u8, ConstU32<1588>> ;
 
+pub type DcapQuote = BoundedVec<u8, ConstU32<8192>>;
+pub type TcbInfo = BoundedVec<u8, ConstU32<8192>>;
+pub type CertDer = BoundedVec<u8, ConstU32<2048>>;
+pub type CrlDer = BoundedVec<u8, ConstU32<8192>>;
+pub type Multiaddr = BoundedVec<u8, ConstU32<256>>;
+// ISO 3166-1 alpha-2 country code, e.g. `*b"DE"`.
+pub type RegionCode = [u8; 2];
+
 #[derive(PartialEq, Eq, Encode, Decode, Clone, RuntimeDebug, MaxEncodedLen, TypeInfo)]
 pub enum DataType {
 	File,
@@ -80,3 +89,83 @@ pub enum IpAddress {
 	IPV6([u16; 8], u16),
 }
 
+/// Randomness helper built on top of the VRF output provided by rrsc.
+///
+/// Every value is derived from the chain randomness and a caller supplied `subject`
+/// (for example a challenge id and a miner account), so results used for different
+/// purposes within the same block are independent of each other.
+pub trait VrfRandomness<Output, BlockNumber>: Randomness<Option<Output>, BlockNumber>
+where
+	Output: AsRef<[u8]>,
+{
+	/// 32 random bytes bound to `subject`.
+	fn random_bytes(subject: &[u8]) -> [u8; 32] {
+		let (seed, _) = Self::random(subject);
+		let seed: sp_std::vec::Vec<u8> = seed.map(|v| v.as_ref().to_vec()).unwrap_or_default();
+		sp_core::hashing::blake2_256(&(seed, subject).encode())
+	}
+
+	/// A random `u64` bound to `subject`.
+	fn random_u64(subject: &[u8]) -> u64 {
+		let bytes = Self::random_bytes(subject);
+		let mut buf = [0u8; 8];
+		buf.copy_from_slice(&bytes[..8]);
+		u64::from_le_bytes(buf)
+	}
+
+	/// A random index in `0..len` bound to `subject`. Returns 0 when `len` is 0.
+	fn random_index(subject: &[u8], len: u32) -> u32 {
+		if len == 0 {
+			return 0;
+		}
+		(Self::random_u64(subject) % len as u64) as u32
+	}
+}
+
+impl<T, Output, BlockNumber> VrfRandomness<Output, BlockNumber> for T
+where
+	T: Randomness<Option<Output>, BlockNumber>,
+	Output: AsRef<[u8]>,
+{}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Randomness source before the first VRF output is available.
+	struct NoVrf;
+
+	impl Randomness<Option<[u8; 32]>, u64> for NoVrf {
+		fn random(_subject: &[u8]) -> (Option<[u8; 32]>, u64) {
+			(None, 0)
+		}
+	}
+
+	struct WithVrf;
+
+	impl Randomness<Option<[u8; 32]>, u64> for WithVrf {
+		fn random(_subject: &[u8]) -> (Option<[u8; 32]>, u64) {
+			(Some([7u8; 32]), 0)
+		}
+	}
+
+	#[test]
+	fn missing_vrf_output_falls_back_to_an_empty_seed() {
+		let subject: &[u8] = b"challenge";
+		let expected = sp_core::hashing::blake2_256(&(sp_std::vec::Vec::<u8>::new(), subject).encode());
+		assert_eq!(expected, NoVrf::random_bytes(subject));
+
+		// Without a seed the values are still bound to the subject.
+		assert_ne!(NoVrf::random_bytes(b"challenge"), NoVrf::random_bytes(b"other"));
+		assert_ne!(NoVrf::random_bytes(subject), WithVrf::random_bytes(subject));
+	}
+
+	#[test]
+	fn random_index_stays_in_range() {
+		assert_eq!(0, NoVrf::random_index(b"empty", 0));
+		for len in 1 .. 20u32 {
+			assert!(NoVrf::random_index(b"index", len) < len);
+			assert!(WithVrf::random_index(b"index", len) < len);
+		}
+	}
+}
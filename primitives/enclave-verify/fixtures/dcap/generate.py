@@ -0,0 +1,218 @@
+#!/usr/bin/env python3
+"""Generates the DCAP test fixtures used by `src/dcap.rs`.
+
+The fixtures mimic the layout of Intel's PCK hierarchy and collateral but are
+signed by a throwaway test root, so they carry no real attestation value.
+Requires the `cryptography` package. Run from this directory.
+"""
+import datetime
+import hashlib
+import json
+import struct
+
+from cryptography import x509
+from cryptography.hazmat.primitives import hashes, serialization
+from cryptography.hazmat.primitives.asymmetric import ec
+from cryptography.hazmat.primitives.asymmetric.utils import decode_dss_signature
+from cryptography.x509.oid import NameOID
+
+NOT_BEFORE = datetime.datetime(2023, 1, 1)
+NOT_AFTER = datetime.datetime(2049, 12, 31)
+FMSPC = bytes.fromhex("00906ea10000")
+TCB_COMPONENTS = [5, 5, 2, 4, 1, 128, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+PCE_SVN = 13
+MR_ENCLAVE = b"\x11" * 32
+MR_SIGNER = b"\x33" * 32
+REPORT_DATA = b"\x22" * 32 + b"\x00" * 32
+# INIT | MODE64BIT, debug enclaves additionally have bit 1 set.
+ATTRIBUTES = 0x05
+DEBUG = 0x02
+# Identity of Intel's quoting enclave, as pinned in `src/dcap.rs`.
+QE_MR_SIGNER = bytes.fromhex("8c4f5775d796503e96137f77c68a829a0056ac8ded70140b081b094490c57bff")
+QE_ISV_PROD_ID = 1
+QE_ISV_SVN = 8
+SGX_OID = "1.2.840.113741.1.13.1"
+
+
+def der(tag, content):
+    assert len(content) < 0x80 or tag == 0x30
+    if len(content) < 0x80:
+        return bytes([tag, len(content)]) + content
+    length = len(content).to_bytes((len(content).bit_length() + 7) // 8, "big")
+    return bytes([tag, 0x80 | len(length)]) + length + content
+
+
+def oid(dotted):
+    parts = [int(p) for p in dotted.split(".")]
+    body = bytes([parts[0] * 40 + parts[1]])
+    for part in parts[2:]:
+        chunk = [part & 0x7F]
+        part >>= 7
+        while part:
+            chunk.insert(0, 0x80 | (part & 0x7F))
+            part >>= 7
+        body += bytes(chunk)
+    return der(0x06, body)
+
+
+def integer(value):
+    raw = value.to_bytes(max(1, (value.bit_length() + 8) // 8), "big")
+    return der(0x02, raw)
+
+
+def sgx_extension():
+    tcb = b"".join(
+        der(0x30, oid(f"{SGX_OID}.2.{i + 1}") + integer(svn)) for i, svn in enumerate(TCB_COMPONENTS)
+    )
+    tcb += der(0x30, oid(f"{SGX_OID}.2.17") + integer(PCE_SVN))
+    tcb += der(0x30, oid(f"{SGX_OID}.2.18") + der(0x04, bytes(TCB_COMPONENTS)))
+    return der(
+        0x30,
+        der(0x30, oid(f"{SGX_OID}.1") + der(0x04, b"\x01" * 16))
+        + der(0x30, oid(f"{SGX_OID}.2") + der(0x30, tcb))
+        + der(0x30, oid(f"{SGX_OID}.3") + der(0x04, b"\x00\x00"))
+        + der(0x30, oid(f"{SGX_OID}.4") + der(0x04, FMSPC))
+        + der(0x30, oid(f"{SGX_OID}.5") + der(0x0A, b"\x00")),
+    )
+
+
+def name(common_name):
+    return x509.Name([
+        x509.NameAttribute(NameOID.COMMON_NAME, common_name),
+        x509.NameAttribute(NameOID.ORGANIZATION_NAME, "CESS Test"),
+    ])
+
+
+def certificate(subject, subject_key, issuer, issuer_key, ca, extensions=()):
+    builder = (
+        x509.CertificateBuilder()
+        .subject_name(name(subject))
+        .issuer_name(name(issuer))
+        .public_key(subject_key.public_key())
+        .serial_number(x509.random_serial_number())
+        .not_valid_before(NOT_BEFORE)
+        .not_valid_after(NOT_AFTER)
+        .add_extension(x509.BasicConstraints(ca=ca, path_length=None), critical=True)
+    )
+    for extension in extensions:
+        builder = builder.add_extension(extension, critical=False)
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def revocation_list(issuer, issuer_key, revoked):
+    builder = (
+        x509.CertificateRevocationListBuilder()
+        .issuer_name(name(issuer))
+        .last_update(NOT_BEFORE)
+        .next_update(NOT_AFTER)
+    )
+    for cert in revoked:
+        builder = builder.add_revoked_certificate(
+            x509.RevokedCertificateBuilder()
+            .serial_number(cert.serial_number)
+            .revocation_date(NOT_BEFORE)
+            .build()
+        )
+    return builder.sign(issuer_key, hashes.SHA256())
+
+
+def raw_signature(key, message):
+    r, s = decode_dss_signature(key.sign(message, ec.ECDSA(hashes.SHA256())))
+    return r.to_bytes(32, "big") + s.to_bytes(32, "big")
+
+
+def report_body(report_data, mr_enclave=b"\x00" * 32, mr_signer=b"\x00" * 32, attributes=ATTRIBUTES, isv_prod_id=0, isv_svn=0):
+    body = (
+        b"\x00" * 16 + b"\x00" * 4 + b"\x00" * 12 + b"\x00" * 16 + struct.pack("<Q", attributes) + b"\x00" * 8
+        + mr_enclave + b"\x00" * 32 + mr_signer + b"\x00" * 32 + b"\x00" * 64
+        + struct.pack("<HH", isv_prod_id, isv_svn) + b"\x00" * 2 + b"\x00" * 42 + b"\x00" * 16 + report_data
+    )
+    assert len(body) == 384
+    return body
+
+
+def main():
+    root_key = ec.generate_private_key(ec.SECP256R1())
+    platform_key = ec.generate_private_key(ec.SECP256R1())
+    pck_key = ec.generate_private_key(ec.SECP256R1())
+    tcb_key = ec.generate_private_key(ec.SECP256R1())
+    att_key = ec.generate_private_key(ec.SECP256R1())
+    other_root_key = ec.generate_private_key(ec.SECP256R1())
+
+    root = certificate("CESS Test SGX Root CA", root_key, "CESS Test SGX Root CA", root_key, True)
+    platform = certificate("CESS Test SGX PCK Platform CA", platform_key, "CESS Test SGX Root CA", root_key, True)
+    pck = certificate(
+        "CESS Test SGX PCK Certificate", pck_key, "CESS Test SGX PCK Platform CA", platform_key, False,
+        [x509.UnrecognizedExtension(x509.ObjectIdentifier(SGX_OID), sgx_extension())],
+    )
+    tcb_signing = certificate("CESS Test SGX TCB Signing", tcb_key, "CESS Test SGX Root CA", root_key, False)
+    other_root = certificate("CESS Test SGX Root CA", other_root_key, "CESS Test SGX Root CA", other_root_key, True)
+
+    att_public = att_key.public_key().public_bytes(
+        serialization.Encoding.X962, serialization.PublicFormat.UncompressedPoint
+    )[1:]
+    qe_auth = bytes(range(32))
+    qe_report_data = hashlib.sha256(att_public + qe_auth).digest() + b"\x00" * 32
+
+    header = struct.pack("<HHIHH", 3, 2, 0, 8, PCE_SVN) + bytes.fromhex("939a7233f79c4ca9940a0db3957f0607") + b"\x00" * 20
+    chain = b"".join(c.public_bytes(serialization.Encoding.PEM) for c in (pck, platform, root)) + b"\x00"
+
+    def quote(attributes=ATTRIBUTES, qe_mr_signer=QE_MR_SIGNER, qe_isv_prod_id=QE_ISV_PROD_ID, qe_isv_svn=QE_ISV_SVN):
+        signed = header + report_body(REPORT_DATA, MR_ENCLAVE, MR_SIGNER, attributes)
+        qe_report = report_body(qe_report_data, mr_signer=qe_mr_signer, isv_prod_id=qe_isv_prod_id, isv_svn=qe_isv_svn)
+        signature_data = (
+            raw_signature(att_key, signed)
+            + att_public
+            + qe_report
+            + raw_signature(pck_key, qe_report)
+            + struct.pack("<H", len(qe_auth)) + qe_auth
+            + struct.pack("<HI", 5, len(chain)) + chain
+        )
+        return signed + struct.pack("<I", len(signature_data)) + signature_data
+
+    tcb_info = json.dumps({
+        "id": "SGX",
+        "version": 3,
+        "issueDate": "2023-06-01T00:00:00Z",
+        "nextUpdate": "2030-01-01T00:00:00Z",
+        "fmspc": FMSPC.hex(),
+        "pceId": "0000",
+        "tcbType": 0,
+        "tcbEvaluationDataNumber": 15,
+        "tcbLevels": [
+            {
+                "tcb": {"sgxtcbcomponents": [{"svn": svn} for svn in TCB_COMPONENTS], "pcesvn": PCE_SVN},
+                "tcbDate": "2023-02-15T00:00:00Z",
+                "tcbStatus": "UpToDate",
+            },
+            {
+                "tcb": {"sgxtcbcomponents": [{"svn": 0} for _ in TCB_COMPONENTS], "pcesvn": 0},
+                "tcbDate": "2018-01-04T00:00:00Z",
+                "tcbStatus": "OutOfDate",
+            },
+        ],
+    }, separators=(",", ":")).encode()
+
+    files = {
+        "quote.bin": quote(),
+        "debug_quote.bin": quote(attributes=ATTRIBUTES | DEBUG),
+        "foreign_qe_quote.bin": quote(qe_mr_signer=b"\x44" * 32),
+        "foreign_qe_product_quote.bin": quote(qe_isv_prod_id=2),
+        "outdated_qe_quote.bin": quote(qe_isv_svn=QE_ISV_SVN - 1),
+        "tcb_info.json": tcb_info,
+        "tcb_info.sig": raw_signature(tcb_key, tcb_info),
+        "tcb_signing.der": tcb_signing.public_bytes(serialization.Encoding.DER),
+        "root.der": root.public_bytes(serialization.Encoding.DER),
+        "other_root.der": other_root.public_bytes(serialization.Encoding.DER),
+        "pck_crl.der": revocation_list("CESS Test SGX PCK Platform CA", platform_key, [pck])
+        .public_bytes(serialization.Encoding.DER),
+        "forged_crl.der": revocation_list("CESS Test SGX PCK Platform CA", other_root_key, [pck])
+        .public_bytes(serialization.Encoding.DER),
+    }
+    for file_name, content in files.items():
+        with open(file_name, "wb") as f:
+            f.write(content)
+
+
+if __name__ == "__main__":
+    main()
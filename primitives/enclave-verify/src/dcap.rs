@@ -0,0 +1,474 @@
+// Verification of SGX ECDSA (DCAP) quotes.
+//
+// A quote is accepted when:
+// * the PCK certificate chain embedded in the quote leads to the configured root CA,
+// * the QE report is signed by the PCK key, binds the attestation key and comes
+//   from Intel's quoting enclave at an acceptable ISVSVN,
+// * the enclave report is signed by the attestation key and is not of a debug enclave,
+// * the TCB info collateral is signed by a certificate issued by the same root,
+//   is not past its `nextUpdate`, matches the platform FMSPC and rates the
+//   platform TCB as acceptable.
+use super::*;
+use serde_json::Value;
+
+// Only quote version 3 with an ECDSA-256-with-P-256 attestation key for SGX is supported.
+const QUOTE_VERSION: u16 = 3;
+const ATT_KEY_TYPE_ECDSA_P256: u16 = 2;
+const TEE_TYPE_SGX: u32 = 0;
+// Certification data type carrying the concatenated PCK certificate chain in PEM.
+const CERT_DATA_PCK_CHAIN: u16 = 5;
+const TCB_INFO_VERSION: u64 = 3;
+
+const HEADER_LEN: usize = 48;
+const REPORT_BODY_LEN: usize = 384;
+const SIGNED_LEN: usize = HEADER_LEN + REPORT_BODY_LEN;
+const ECDSA_SIG_LEN: usize = 64;
+const ATT_KEY_LEN: usize = 64;
+// Offsets inside an enclave report body.
+const BODY_ATTRIBUTES: usize = 48;
+const BODY_MR_ENCLAVE: usize = 64;
+const BODY_MR_SIGNER: usize = 128;
+const BODY_ISV_PROD_ID: usize = 256;
+const BODY_ISV_SVN: usize = 258;
+const BODY_REPORT_DATA: usize = 320;
+
+// Identity of Intel's quoting enclave, see the QE identity collateral of the PCS.
+// Quotes produced by any other enclave, or by a QE older than the minimum ISVSVN, are rejected.
+const QE_MR_SIGNER: [u8; 32] = [
+    0x8c, 0x4f, 0x57, 0x75, 0xd7, 0x96, 0x50, 0x3e, 0x96, 0x13, 0x7f, 0x77, 0xc6, 0x8a, 0x82, 0x9a,
+    0x00, 0x56, 0xac, 0x8d, 0xed, 0x70, 0x14, 0x0b, 0x08, 0x1b, 0x09, 0x44, 0x90, 0xc5, 0x7b, 0xff,
+];
+const QE_ISV_PROD_ID: u16 = 1;
+const QE_MIN_ISV_SVN: u16 = 8;
+
+// TCB levels accepted for registration, mirroring `ACCEPTED_QUOTE_STATUS` of the IAS path.
+pub const ACCEPTED_TCB_STATUS: &[&str] = &["UpToDate", "SWHardeningNeeded"];
+
+// DER encoded OID prefix 1.2.840.113741.1.13.1, the Intel SGX PCK certificate extension.
+const SGX_EXTENSION_OID: [u8; 9] = [0x2A, 0x86, 0x48, 0x86, 0xF8, 0x4D, 0x01, 0x0D, 0x01];
+const SGX_FMSPC: u8 = 4;
+const SGX_TCB: u8 = 2;
+const SGX_TCB_PCESVN: u8 = 17;
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+static SUPPORTED_ECDSA_ALGS: SignatureAlgorithms = &[&webpki::ECDSA_P256_SHA256];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DcapReport {
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub report_data: [u8; 64],
+}
+
+// Platform properties read from the SGX extension of the PCK certificate.
+struct PckPlatform {
+    fmspc: [u8; 6],
+    tcb_components: [u8; 16],
+    pce_svn: u16,
+}
+
+/// Verifies a DCAP quote and its TCB info collateral against `root_cert_der`.
+///
+/// `tcb_info` is the raw `tcbInfo` JSON object exactly as signed by Intel,
+/// `tcb_info_signature` its raw `r || s` signature and `tcb_signing_cert` the DER
+/// encoded certificate that produced it. Certificates of either chain that are listed
+/// in one of the DER encoded `crls` are rejected. `now` is in seconds since the unix epoch.
+pub fn verify_dcap_quote(
+    quote: &[u8],
+    tcb_info: &[u8],
+    tcb_info_signature: &[u8],
+    tcb_signing_cert: &[u8],
+    root_cert_der: &[u8],
+    crls: &[&[u8]],
+    now: u64,
+) -> Result<DcapReport, VerifyError> {
+    let crls = parse_crls(crls)?;
+    verify_quote(quote, tcb_info, tcb_info_signature, tcb_signing_cert, root_cert_der, &crls, now)
+        .ok_or(VerifyError::QuoteRejected)
+}
+
+fn verify_quote(
+    quote: &[u8],
+    tcb_info: &[u8],
+    tcb_info_signature: &[u8],
+    tcb_signing_cert: &[u8],
+    root_cert_der: &[u8],
+    crls: &[webpki::CertRevocationList],
+    now: u64,
+) -> Option<DcapReport> {
+    if quote.len() < SIGNED_LEN + 4 {
+        return None;
+    }
+    if read_u16(quote, 0)? != QUOTE_VERSION
+        || read_u16(quote, 2)? != ATT_KEY_TYPE_ECDSA_P256
+        || read_u32(quote, 4)? != TEE_TYPE_SGX
+    {
+        return None;
+    }
+
+    let sig_data_len = read_u32(quote, SIGNED_LEN)? as usize;
+    let sig_data = quote.get(SIGNED_LEN + 4..(SIGNED_LEN + 4).checked_add(sig_data_len)?)?;
+
+    let isv_signature = sig_data.get(0..ECDSA_SIG_LEN)?;
+    let att_key = sig_data.get(ECDSA_SIG_LEN..ECDSA_SIG_LEN + ATT_KEY_LEN)?;
+    let mut offset = ECDSA_SIG_LEN + ATT_KEY_LEN;
+    let qe_report = sig_data.get(offset..offset + REPORT_BODY_LEN)?;
+    offset += REPORT_BODY_LEN;
+    let qe_signature = sig_data.get(offset..offset + ECDSA_SIG_LEN)?;
+    offset += ECDSA_SIG_LEN;
+    let qe_auth_len = read_u16(sig_data, offset)? as usize;
+    offset += 2;
+    let qe_auth = sig_data.get(offset..offset + qe_auth_len)?;
+    offset += qe_auth_len;
+    if read_u16(sig_data, offset)? != CERT_DATA_PCK_CHAIN {
+        return None;
+    }
+    let cert_len = read_u32(sig_data, offset + 2)? as usize;
+    offset += 6;
+    let cert_data = sig_data.get(offset..offset.checked_add(cert_len)?)?;
+
+    let root = webpki::trust_anchor_util::cert_der_as_trust_anchor(root_cert_der).ok()?;
+    let anchors = [root];
+    let trust_anchors = webpki::TLSClientTrustAnchors(&anchors);
+    let time = webpki::Time::from_seconds_since_unix_epoch(now);
+
+    // PCK certificate chain.
+    let chain = pem_to_der_list(cert_data)?;
+    let (pck_der, intermediates) = chain.split_first()?;
+    let intermediates: Vec<&[u8]> = intermediates.iter().map(|cert| cert.as_slice()).collect();
+    let pck_cert = webpki::EndEntityCert::from(pck_der.as_slice()).ok()?;
+    pck_cert
+        .verify_is_valid_tls_client_cert_with_crls(SUPPORTED_ECDSA_ALGS, &trust_anchors, &intermediates, crls, time)
+        .ok()?;
+
+    // The QE report is signed by the PCK key and commits to the attestation key.
+    pck_cert.verify_signature(&webpki::ECDSA_P256_SHA256, qe_report, &ecdsa_raw_to_der(qe_signature)?).ok()?;
+    let expected = sp_core::hashing::sha2_256(&[att_key, qe_auth].concat());
+    let qe_report_data = &qe_report[BODY_REPORT_DATA..BODY_REPORT_DATA + 64];
+    if qe_report_data[..32] != expected || qe_report_data[32..].iter().any(|b| *b != 0) {
+        return None;
+    }
+    if qe_report[BODY_MR_SIGNER..BODY_MR_SIGNER + 32] != QE_MR_SIGNER
+        || read_u16(qe_report, BODY_ISV_PROD_ID)? != QE_ISV_PROD_ID
+        || read_u16(qe_report, BODY_ISV_SVN)? < QE_MIN_ISV_SVN
+    {
+        return None;
+    }
+
+    // The enclave report is signed by the attestation key.
+    let mut public_key = [0u8; ATT_KEY_LEN + 1];
+    public_key[0] = 0x04;
+    public_key[1..].copy_from_slice(att_key);
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_FIXED, &public_key[..])
+        .verify(&quote[..SIGNED_LEN], isv_signature)
+        .ok()?;
+
+    // TCB info collateral.
+    let tcb_cert = webpki::EndEntityCert::from(tcb_signing_cert).ok()?;
+    tcb_cert
+        .verify_is_valid_tls_client_cert_with_crls(SUPPORTED_ECDSA_ALGS, &trust_anchors, &intermediates, crls, time)
+        .ok()?;
+    tcb_cert.verify_signature(&webpki::ECDSA_P256_SHA256, tcb_info, &ecdsa_raw_to_der(tcb_info_signature)?).ok()?;
+
+    let platform = parse_pck_platform(pck_der)?;
+    check_tcb_info(tcb_info, &platform, now)?;
+
+    let body = &quote[HEADER_LEN..SIGNED_LEN];
+    let flags = u64::from_le_bytes(body[BODY_ATTRIBUTES..BODY_ATTRIBUTES + 8].try_into().ok()?);
+    if flags & SGX_FLAGS_DEBUG != 0 {
+        return None;
+    }
+
+    Some(DcapReport {
+        mr_enclave: body[BODY_MR_ENCLAVE..BODY_MR_ENCLAVE + 32].try_into().ok()?,
+        mr_signer: body[BODY_MR_SIGNER..BODY_MR_SIGNER + 32].try_into().ok()?,
+        report_data: body[BODY_REPORT_DATA..BODY_REPORT_DATA + 64].try_into().ok()?,
+    })
+}
+
+// Finds the TCB level the platform satisfies and checks that its status is accepted.
+fn check_tcb_info(tcb_info: &[u8], platform: &PckPlatform, now: u64) -> Option<()> {
+    let info: Value = serde_json::from_slice(tcb_info).ok()?;
+
+    if info["id"].as_str()? != "SGX" || info["version"].as_u64()? != TCB_INFO_VERSION {
+        return None;
+    }
+    if now >= parse_ias_timestamp(info["nextUpdate"].as_str()?)? {
+        return None;
+    }
+
+    let mut fmspc = [0u8; 6];
+    decode_hex(info["fmspc"].as_str()?, &mut fmspc)?;
+    if fmspc != platform.fmspc {
+        return None;
+    }
+
+    // Levels are ordered from the newest to the oldest, the first one satisfied applies.
+    for level in info["tcbLevels"].as_array()? {
+        let tcb = &level["tcb"];
+        let components = tcb["sgxtcbcomponents"].as_array()?;
+        if components.len() != platform.tcb_components.len() {
+            return None;
+        }
+
+        let mut satisfied = platform.pce_svn as u64 >= tcb["pcesvn"].as_u64()?;
+        for (component, svn) in components.iter().zip(platform.tcb_components.iter()) {
+            if (*svn as u64) < component["svn"].as_u64()? {
+                satisfied = false;
+            }
+        }
+
+        if satisfied {
+            let status = level["tcbStatus"].as_str()?;
+            return if ACCEPTED_TCB_STATUS.contains(&status) { Some(()) } else { None };
+        }
+    }
+
+    None
+}
+
+// Reads FMSPC, the TCB components and PCESVN from the SGX extension of a PCK certificate.
+fn parse_pck_platform(pck_der: &[u8]) -> Option<PckPlatform> {
+    let mut fmspc = [0u8; 6];
+    let value = find_sgx_value(pck_der, &[SGX_FMSPC])?;
+    if value.0 != 0x04 || value.1.len() != fmspc.len() {
+        return None;
+    }
+    fmspc.copy_from_slice(value.1);
+
+    let mut tcb_components = [0u8; 16];
+    for (index, component) in tcb_components.iter_mut().enumerate() {
+        let value = find_sgx_value(pck_der, &[SGX_TCB, index as u8 + 1])?;
+        *component = der_uint(value)?.try_into().ok()?;
+    }
+
+    let pce_svn = der_uint(find_sgx_value(pck_der, &[SGX_TCB, SGX_TCB_PCESVN])?)?.try_into().ok()?;
+
+    Some(PckPlatform { fmspc, tcb_components, pce_svn })
+}
+
+// Returns the tag and content of the value following the SGX extension OID with the given suffix.
+fn find_sgx_value<'a>(der: &'a [u8], suffix: &[u8]) -> Option<(u8, &'a [u8])> {
+    let mut oid: Vec<u8> = Vec::with_capacity(2 + SGX_EXTENSION_OID.len() + suffix.len());
+    oid.push(0x06);
+    oid.push((SGX_EXTENSION_OID.len() + suffix.len()) as u8);
+    oid.extend_from_slice(&SGX_EXTENSION_OID);
+    oid.extend_from_slice(suffix);
+
+    let start = der.windows(oid.len()).position(|window| window == oid.as_slice())? + oid.len();
+    let tag = *der.get(start)?;
+    let len = *der.get(start + 1)? as usize;
+    // Every value we read is short, so only the short length form is supported.
+    if len > 0x7f {
+        return None;
+    }
+    Some((tag, der.get(start + 2..start + 2 + len)?))
+}
+
+fn der_uint(value: (u8, &[u8])) -> Option<u32> {
+    if value.0 != 0x02 || value.1.is_empty() || value.1.len() > 4 {
+        return None;
+    }
+    Some(value.1.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32))
+}
+
+// Converts a raw `r || s` P-256 signature into the ASN.1 DER form expected by webpki.
+fn ecdsa_raw_to_der(raw: &[u8]) -> Option<Vec<u8>> {
+    if raw.len() != ECDSA_SIG_LEN {
+        return None;
+    }
+
+    let integer = |value: &[u8]| -> Vec<u8> {
+        let first = value.iter().position(|b| *b != 0).unwrap_or(value.len() - 1);
+        let value = &value[first..];
+        let mut out = Vec::with_capacity(value.len() + 3);
+        out.push(0x02);
+        if value[0] & 0x80 != 0 {
+            out.push(value.len() as u8 + 1);
+            out.push(0);
+        } else {
+            out.push(value.len() as u8);
+        }
+        out.extend_from_slice(value);
+        out
+    };
+
+    let r = integer(&raw[..32]);
+    let s = integer(&raw[32..]);
+    let mut der = Vec::with_capacity(r.len() + s.len() + 2);
+    der.push(0x30);
+    der.push((r.len() + s.len()) as u8);
+    der.extend(r);
+    der.extend(s);
+    Some(der)
+}
+
+fn pem_to_der_list(pem: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let mut rest = sp_std::str::from_utf8(pem).ok()?;
+    let mut certs = Vec::new();
+
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        let body_start = start + PEM_BEGIN.len();
+        let body_end = body_start + rest[body_start..].find(PEM_END)?;
+        let body: Vec<u8> = rest[body_start..body_end].bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+        certs.push(base64::decode(&body).ok()?);
+        rest = &rest[body_end + PEM_END.len()..];
+    }
+
+    if certs.is_empty() {
+        return None;
+    }
+    Some(certs)
+}
+
+fn decode_hex(s: &str, out: &mut [u8]) -> Option<()> {
+    let bytes = s.as_bytes();
+    if bytes.len() != out.len() * 2 {
+        return None;
+    }
+    for (i, pair) in bytes.chunks(2).enumerate() {
+        let high = (pair[0] as char).to_digit(16)?;
+        let low = (pair[1] as char).to_digit(16)?;
+        out[i] = (high << 4 | low) as u8;
+    }
+    Some(())
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUOTE: &[u8] = include_bytes!("../fixtures/dcap/quote.bin");
+    const DEBUG_QUOTE: &[u8] = include_bytes!("../fixtures/dcap/debug_quote.bin");
+    const FOREIGN_QE_QUOTE: &[u8] = include_bytes!("../fixtures/dcap/foreign_qe_quote.bin");
+    const FOREIGN_QE_PRODUCT_QUOTE: &[u8] = include_bytes!("../fixtures/dcap/foreign_qe_product_quote.bin");
+    const OUTDATED_QE_QUOTE: &[u8] = include_bytes!("../fixtures/dcap/outdated_qe_quote.bin");
+    const TCB_INFO: &[u8] = include_bytes!("../fixtures/dcap/tcb_info.json");
+    const TCB_INFO_SIG: &[u8] = include_bytes!("../fixtures/dcap/tcb_info.sig");
+    const TCB_SIGNING_CERT: &[u8] = include_bytes!("../fixtures/dcap/tcb_signing.der");
+    const ROOT_CERT: &[u8] = include_bytes!("../fixtures/dcap/root.der");
+    const OTHER_ROOT_CERT: &[u8] = include_bytes!("../fixtures/dcap/other_root.der");
+    const PCK_CRL: &[u8] = include_bytes!("../fixtures/dcap/pck_crl.der");
+    const FORGED_CRL: &[u8] = include_bytes!("../fixtures/dcap/forged_crl.der");
+    // 2024-01-01T00:00:00Z
+    const NOW: u64 = 1704067200;
+
+    fn verify(quote: &[u8], root: &[u8], now: u64) -> Option<DcapReport> {
+        verify_dcap_quote(quote, TCB_INFO, TCB_INFO_SIG, TCB_SIGNING_CERT, root, &[], now).ok()
+    }
+
+    #[test]
+    fn fixture_quote_verifies() {
+        let report = verify(QUOTE, ROOT_CERT, NOW).expect("fixture quote is valid");
+        assert_eq!([0x11u8; 32], report.mr_enclave);
+        assert_eq!([0x33u8; 32], report.mr_signer);
+        assert_eq!([0x22u8; 32], report.report_data[..32]);
+    }
+
+    #[test]
+    fn tampered_report_is_rejected() {
+        let mut quote = QUOTE.to_vec();
+        quote[HEADER_LEN + BODY_MR_ENCLAVE] ^= 0xff;
+        assert_eq!(None, verify(&quote, ROOT_CERT, NOW));
+    }
+
+    #[test]
+    fn debug_enclave_is_rejected() {
+        assert_eq!(None, verify(DEBUG_QUOTE, ROOT_CERT, NOW));
+    }
+
+    #[test]
+    fn untrusted_quoting_enclave_is_rejected() {
+        assert_eq!(None, verify(FOREIGN_QE_QUOTE, ROOT_CERT, NOW));
+        assert_eq!(None, verify(FOREIGN_QE_PRODUCT_QUOTE, ROOT_CERT, NOW));
+        assert_eq!(None, verify(OUTDATED_QE_QUOTE, ROOT_CERT, NOW));
+    }
+
+    #[test]
+    fn unknown_root_is_rejected() {
+        assert_eq!(None, verify(QUOTE, OTHER_ROOT_CERT, NOW));
+    }
+
+    #[test]
+    fn outdated_tcb_info_is_rejected() {
+        // 2030-01-01T00:00:00Z, the `nextUpdate` of the fixture TCB info.
+        assert_eq!(None, verify(QUOTE, ROOT_CERT, 1893456000));
+    }
+
+    #[test]
+    fn tampered_tcb_info_is_rejected() {
+        let tcb_info = sp_std::str::from_utf8(TCB_INFO).unwrap().replace("UpToDate", "Revoked!");
+        assert_eq!(
+            Err(VerifyError::QuoteRejected),
+            verify_dcap_quote(QUOTE, tcb_info.as_bytes(), TCB_INFO_SIG, TCB_SIGNING_CERT, ROOT_CERT, &[], NOW)
+        );
+    }
+
+    #[test]
+    fn revoked_pck_cert_is_rejected() {
+        assert_eq!(
+            Err(VerifyError::QuoteRejected),
+            verify_dcap_quote(QUOTE, TCB_INFO, TCB_INFO_SIG, TCB_SIGNING_CERT, ROOT_CERT, &[PCK_CRL], NOW)
+        );
+    }
+
+    #[test]
+    fn crl_not_signed_by_issuer_is_ignored() {
+        assert!(
+            verify_dcap_quote(QUOTE, TCB_INFO, TCB_INFO_SIG, TCB_SIGNING_CERT, ROOT_CERT, &[FORGED_CRL], NOW)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn malformed_crl_is_rejected() {
+        assert_eq!(
+            Err(VerifyError::InvalidCrl),
+            verify_dcap_quote(QUOTE, TCB_INFO, TCB_INFO_SIG, TCB_SIGNING_CERT, ROOT_CERT, &[&PCK_CRL[1..]], NOW)
+        );
+    }
+
+    #[test]
+    fn truncated_quote_is_rejected() {
+        assert_eq!(None, verify(&QUOTE[..QUOTE.len() - 1], ROOT_CERT, NOW));
+        assert_eq!(None, verify(&QUOTE[..SIGNED_LEN], ROOT_CERT, NOW));
+    }
+
+    // Truncated and bit flipped copies of a valid quote and its collateral must be
+    // rejected without panicking.
+    #[test]
+    fn mutated_quotes_do_not_panic() {
+        use rand::{Rng, RngCore};
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..300 {
+            let mut quote = QUOTE.to_vec();
+            quote.truncate(rng.gen_range(0..=QUOTE.len()));
+            for _ in 0..rng.gen_range(0..8) {
+                if quote.is_empty() {
+                    break;
+                }
+                let index = rng.gen_range(0..quote.len());
+                quote[index] ^= 1 << rng.gen_range(0..8);
+            }
+            let _ = verify(&quote, ROOT_CERT, NOW);
+
+            let mut tcb_info = TCB_INFO.to_vec();
+            let index = rng.gen_range(0..tcb_info.len());
+            tcb_info[index] = rng.gen();
+            let mut garbage = vec![0u8; rng.gen_range(0..512)];
+            rng.fill_bytes(&mut garbage);
+            let _ = verify_dcap_quote(QUOTE, &tcb_info, &garbage, &garbage, &garbage, &[], NOW);
+        }
+    }
+}
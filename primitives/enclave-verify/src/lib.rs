@@ -5,8 +5,14 @@ use sp_std::vec::Vec;
 // 	ecdsa::{Signature, Public},
 // };
 // use sp_io::hashing::sha2_256;
-// use serde_json::Value;
+use serde_json::Value;
 use cp_cess_common::*;
+
+#[cfg(target_arch = "wasm32")]
+extern crate ring_wasmable as ring;
+
+mod dcap;
+pub use dcap::{verify_dcap_quote, DcapReport, ACCEPTED_TCB_STATUS};
 // #[cfg(feature = "std")]
 // use sp_externalities::{Externalities, ExternalitiesExt};
 // use sp_runtime_interface::{
@@ -23,13 +29,13 @@ use ic_verify_bls_signature::{
     PublicKey as BLSPubilc,
 };
 
-#[test]
+#[cfg(test)]
 use rand::RngCore;
 
-#[test]
+#[cfg(test)]
 use rsa::{
-    RsaPublicKey, RsaPrivateKey, 
-    EncodePublicKey,
+    RsaPublicKey, RsaPrivateKey,
+    pkcs8::EncodePublicKey,
 };
 
 // #[cfg(feature = "std")]
@@ -92,6 +98,32 @@ static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
     &webpki::RSA_PKCS1_3072_8192_SHA384,
 ];
 
+/// Why a signature, certificate or attestation was rejected.
+///
+/// Every input checked here comes from an extrinsic, so malformed data is reported
+/// through this error rather than by panicking.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VerifyError {
+    // A hex string contains a character that is not a hex digit.
+    InvalidHex,
+    // A public key could not be decoded.
+    InvalidPublicKey,
+    // A signature could not be decoded.
+    InvalidSignature,
+    // A well formed signature does not match the message and key.
+    SignatureMismatch,
+    // A certificate could not be decoded.
+    InvalidCertificate,
+    // A certificate does not chain to a trusted root, is expired or is revoked.
+    CertificateRejected,
+    // A certificate revocation list is not valid DER.
+    InvalidCrl,
+    // An IAS report is malformed, stale or has an untrusted quote status.
+    InvalidReport,
+    // A DCAP quote or its collateral failed verification.
+    QuoteRejected,
+}
+
 // pub fn u8v_to_hexstr(x: &[u8]) -> String {
 //     // produce a hexnum string from a byte vector
 //     let mut s = String::new();
@@ -101,26 +133,23 @@ static SUPPORTED_SIG_ALGS: SignatureAlgorithms = &[
 //     s
 // }
 
-pub fn hexstr_to_u8v(s: &str, x: &mut [u8]) {
+/// Decodes the hex string `s` into `x`, zero filling `x` when `s` is shorter.
+pub fn hexstr_to_u8v(s: &str, x: &mut [u8]) -> Result<(), VerifyError> {
     let nx = x.len();
     let mut pos = 0;
     let mut val: u8 = 0;
     let mut cct = 0;
     for c in s.chars() {
         if pos < nx {
-            match c.to_digit(16) {
-                Some(d) => {
-                    val += d as u8;
-                    cct += 1;
-                    if (cct & 1) == 0 {
-                        x[pos] = val;
-                        pos += 1;
-                        val = 0;
-                    } else {
-                        val <<= 4;
-                    }
-                }
-                None => panic!("Invalid hex digit"),
+            let d = c.to_digit(16).ok_or(VerifyError::InvalidHex)?;
+            val += d as u8;
+            cct += 1;
+            if (cct & 1) == 0 {
+                x[pos] = val;
+                pos += 1;
+                val = 0;
+            } else {
+                val <<= 4;
             }
         } else {
             break;
@@ -130,108 +159,175 @@ pub fn hexstr_to_u8v(s: &str, x: &mut [u8]) {
         x[ix] = val;
         val = 0;
     }
+    Ok(())
 }
 
+// Quote statuses accepted from IAS. Any other status means the platform is not trusted.
+pub const ACCEPTED_QUOTE_STATUS: &[&str] = &["OK", "SW_HARDENING_NEEDED"];
+// Reports dated this many seconds after the chain time are still accepted, to tolerate clock drift.
+pub const REPORT_CLOCK_DRIFT: u64 = 60;
+
+const QUOTE_BODY_LEN: usize = 432;
+// The report body starts at 48, its attributes at 48 within the body, flags first.
+const ATTRIBUTES_OFFSET: usize = 96;
+const MR_ENCLAVE_OFFSET: usize = 112;
+const MR_SIGNER_OFFSET: usize = 176;
+const REPORT_DATA_OFFSET: usize = 368;
+// Attribute flag of enclaves launched in debug mode, their memory can be read by the host.
+pub const SGX_FLAGS_DEBUG: u64 = 0x02;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct IasReport {
+    // Seconds since the unix epoch at which IAS produced the report.
+    pub timestamp: u64,
+    pub mr_enclave: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub report_data: [u8; 64],
+}
+
+/// Parses DER encoded certificate revocation lists, failing if any of them is malformed.
+pub fn parse_crls<'a>(crls: &[&'a [u8]]) -> Result<Vec<webpki::CertRevocationList<'a>>, VerifyError> {
+    crls.iter()
+        .map(|crl| webpki::CertRevocationList::from(crl).map_err(|_| VerifyError::InvalidCrl))
+        .collect()
+}
+
+/// Verifies the IAS certificate chain and report signature, then parses the report.
+///
+/// `now` is the current time in seconds since the unix epoch. It is used both to
+/// check the validity period of the IAS certificate and, together with `max_age`,
+/// to reject stale reports. Certificates listed in `crls` are rejected.
 pub fn verify_miner_cert(
     ias_sig: &ReportSign,
     ias_cert: &Cert,
     report_json_raw: &Report,
-) -> Option<u8> {
-    let ias_cert_dec = match base64::decode_config(ias_cert, base64::STANDARD) {
-        Ok(c) => c,
-        Err(_) => return Option::None,
-    };
-    let sig_cert: webpki::EndEntityCert = match webpki::EndEntityCert::from(ias_cert_dec.as_slice()) {
-        Ok(c) => c,
-        Err(_) => return Option::None,
-    };
+    crls: &[&[u8]],
+    now: u64,
+    max_age: u64,
+) -> Result<IasReport, VerifyError> {
+    let ias_cert_dec = base64::decode_config(ias_cert, base64::STANDARD)
+        .map_err(|_| VerifyError::InvalidCertificate)?;
+    let sig_cert = webpki::EndEntityCert::from(ias_cert_dec.as_slice())
+        .map_err(|_| VerifyError::InvalidCertificate)?;
 
     let intermediate_report: Vec<&[u8]> = Vec::new();
-    //2022-12-09 00:00:00
-    let now_func = webpki::Time::from_seconds_since_unix_epoch(1670515200); 
-
-    if let Err(_e) = sig_cert.verify_is_valid_tls_server_cert(
-        SUPPORTED_SIG_ALGS,
-        &IAS_SERVER_ROOTS,
-        &intermediate_report,
-        now_func
-    ) {return Option::None;}
-
-    let ias_sig_dec: Vec<u8> = match base64::decode(ias_sig) {
-        Ok(value) => value,
-        Err(_) => return Option::None,
-    };
-
-    if let Err(_e) = sig_cert.verify_signature(
-        &webpki::RSA_PKCS1_2048_8192_SHA256,
-        &report_json_raw,
-        &ias_sig_dec,
-    ) {return Option::None;}
-
-
-    // let some_quote_body: Value = match serde_json::from_slice(report_json_raw) {
-    //     Ok(body) => body,
-    //     Err(_) => return Option::None,
-    // };
+    let crls = parse_crls(crls)?;
+    let now_func = webpki::Time::from_seconds_since_unix_epoch(now);
+
+    sig_cert
+        .verify_is_valid_tls_server_cert_with_crls(
+            SUPPORTED_SIG_ALGS,
+            &IAS_SERVER_ROOTS,
+            &intermediate_report,
+            &crls,
+            now_func,
+        )
+        .map_err(|_| VerifyError::CertificateRejected)?;
+
+    let ias_sig_dec: Vec<u8> = base64::decode(ias_sig).map_err(|_| VerifyError::InvalidSignature)?;
+
+    sig_cert
+        .verify_signature(&webpki::RSA_PKCS1_2048_8192_SHA256, &report_json_raw, &ias_sig_dec)
+        .map_err(|_| VerifyError::SignatureMismatch)?;
+
+    parse_ias_report(report_json_raw, now, max_age).ok_or(VerifyError::InvalidReport)
+}
 
-    // if let Value::String(maybe_isv_quote_body) = &some_quote_body["isvEnclaveQuoteBody"] {
-    //     let decoded_quote_body = match base64::decode(&maybe_isv_quote_body) {
-    //         Ok(decoded_qb) => decoded_qb,
-    //         Err(_) => return Option::None,
-    //     };
+/// Parses an IAS attestation report whose signature has already been checked.
+///
+/// Rejects reports with an untrusted `isvEnclaveQuoteStatus`, reports of debug enclaves
+/// and reports that are older than `max_age` seconds or dated in the future.
+pub fn parse_ias_report(report_json_raw: &[u8], now: u64, max_age: u64) -> Option<IasReport> {
+    let report: Value = serde_json::from_slice(report_json_raw).ok()?;
 
-    //     let id_code: [u8; 32] =  match decoded_quote_body[112..144].try_into() {
-    //         Ok(code) => code,
-    //         Err(_) => return Option::None,
-    //     };
+    let status = report["isvEnclaveQuoteStatus"].as_str()?;
+    if !ACCEPTED_QUOTE_STATUS.contains(&status) {
+        return None;
+    }
 
-    //     if !mrenclave_codes.contains(&id_code) {
-    //         return Option::None;
-    //     }
+    let timestamp = parse_ias_timestamp(report["timestamp"].as_str()?)?;
+    if timestamp > now.saturating_add(REPORT_CLOCK_DRIFT) || now.saturating_sub(timestamp) > max_age {
+        return None;
+    }
 
-    //     let quote_mr_signer: [u8; 32] =  match decoded_quote_body[176..208].try_into() {
-    //         Ok(mr_signer) => mr_signer,
-    //         Err(_) => return Option::None,
-    //     };
+    let quote_body = base64::decode(report["isvEnclaveQuoteBody"].as_str()?).ok()?;
+    if quote_body.len() < QUOTE_BODY_LEN {
+        return None;
+    }
+    let flags = u64::from_le_bytes(quote_body[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 8].try_into().ok()?);
+    if flags & SGX_FLAGS_DEBUG != 0 {
+        return None;
+    }
 
-    //     if mr_signer != &quote_mr_signer {
-    //         return Option::None;
-    //     }
+    Some(IasReport {
+        timestamp,
+        mr_enclave: quote_body[MR_ENCLAVE_OFFSET..MR_ENCLAVE_OFFSET + 32].try_into().ok()?,
+        mr_signer: quote_body[MR_SIGNER_OFFSET..MR_SIGNER_OFFSET + 32].try_into().ok()?,
+        report_data: quote_body[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + 64].try_into().ok()?,
+    })
+}
 
-    //     let quote_pk: [u8; 33] = match decoded_quote_body[368..401].try_into() {
-    //         Ok(pk) => pk,
-    //         Err(_) => return Option::None,
-    //     };
+/// Converts an IAS timestamp such as `2023-03-01T08:45:21.427452` (UTC) to unix seconds.
+pub fn parse_ias_timestamp(timestamp: &str) -> Option<u64> {
+    let bytes = timestamp.as_bytes();
+    if bytes.len() < 19 || bytes[4] != b'-' || bytes[7] != b'-' || bytes[10] != b'T' || bytes[13] != b':' || bytes[16] != b':' {
+        return None;
+    }
 
-    //     let pk = Public::from_raw(quote_pk);
+    let number = |from: usize, to: usize| -> Option<u64> {
+        let mut value: u64 = 0;
+        for c in &bytes[from..to] {
+            if !c.is_ascii_digit() {
+                return None;
+            }
+            value = value * 10 + (c - b'0') as u64;
+        }
+        Some(value)
+    };
 
-    //     let data: Vec<u8> = [&quote_body[..], &ias_sig[..], &ias_cert[..]].concat();
-    //     let result = sp_io::crypto::ecdsa_verify_prehashed(quote_sig, &sha2_256(&data), &pk);
+    let year = number(0, 4)?;
+    let month = number(5, 7)?;
+    let day = number(8, 10)?;
+    let hour = number(11, 13)?;
+    let minute = number(14, 16)?;
+    let second = number(17, 19)?;
+    if year < 1970 || month == 0 || month > 12 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
 
-    //     if !result {
-    //         return Option::None;
-    //     }
+    // Days since the unix epoch, see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = (era * 146097 + doe).checked_sub(719468)?;
 
-    //     return Option::Some(pk);
-    // };
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
 
-    Option::Some(1)
+/// Encodes bytes as lowercase hex, the format used by the MRENCLAVE whitelist.
+pub fn u8v_to_hex(x: &[u8; 32]) -> [u8; 64] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = [0u8; 64];
+    for (i, byte) in x.iter().enumerate() {
+        out[i * 2] = HEX[(byte >> 4) as usize];
+        out[i * 2 + 1] = HEX[(byte & 0x0f) as usize];
+    }
+    out
 }
 
-pub fn verify_rsa(key: &[u8], msg: &[u8], sig: &[u8]) -> bool {
-    let pk = rsa::RsaPublicKey::from_public_key_der(key).unwrap();
+pub fn verify_rsa(key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), VerifyError> {
+    let pk = rsa::RsaPublicKey::from_public_key_der(key).map_err(|_| VerifyError::InvalidPublicKey)?;
 
-    match pk.verify(Pkcs1v15Sign::new_raw(), msg, sig) {
-        Ok(()) => return true,
-        Err(_) => return false,
-    };
+    pk.verify(Pkcs1v15Sign::new_raw(), msg, sig).map_err(|_| VerifyError::SignatureMismatch)
 }
 
-pub fn verify_bls(key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), ()> {
-    let puk = BLSPubilc::deserialize(key).unwrap();
+pub fn verify_bls(key: &[u8], msg: &[u8], sig: &[u8]) -> Result<(), VerifyError> {
+    let puk = BLSPubilc::deserialize(key).map_err(|_| VerifyError::InvalidPublicKey)?;
     log::info!("bls puk: {:?}", puk);
-    let sig = BLSSignature::deserialize(sig).unwrap();
-    puk.verify(&msg, &sig)
+    let sig = BLSSignature::deserialize(sig).map_err(|_| VerifyError::InvalidSignature)?;
+    puk.verify(&msg, &sig).map_err(|_| VerifyError::SignatureMismatch)
 }
 
 // pub fn sig_rsa(key: &[u8], msg: &[u8]) -> &[u8] {
@@ -253,3 +349,96 @@ fn cryptos_rsa() {
 	let result = verify_rsa(&doc.as_bytes(), &msg, &sig);
 	println!("result: {:?}", result);
 }
+
+// Random inputs for the checks that run on extrinsic data. None of them may panic.
+#[test]
+fn malformed_inputs_do_not_panic() {
+    let mut rng = rand::thread_rng();
+    let mut random_bytes = |max: usize| -> Vec<u8> {
+        let mut bytes = vec![0u8; rng.next_u32() as usize % max];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    };
+
+    for _ in 0..500 {
+        let (key, msg, sig) = (random_bytes(600), random_bytes(64), random_bytes(600));
+        assert!(verify_rsa(&key, &msg, &sig).is_err());
+        assert!(verify_bls(&key, &msg, &sig).is_err());
+        let _ = parse_crls(&[&key]);
+
+        let text = String::from_utf8_lossy(&key);
+        let mut out = [0u8; 32];
+        let _ = hexstr_to_u8v(&text, &mut out);
+        let _ = parse_ias_timestamp(&text);
+        let _ = parse_ias_report(&key, 1670515200, 3600);
+
+        let cert = Cert::truncate_from(key);
+        let sign = ReportSign::truncate_from(sig);
+        let report = Report::truncate_from(msg);
+        assert!(verify_miner_cert(&sign, &cert, &report, &[], 1670515200, 3600).is_err());
+        let cert = Cert::truncate_from(base64::encode(&random_bytes(1000)).into_bytes());
+        assert!(verify_miner_cert(&sign, &cert, &report, &[], 1670515200, 3600).is_err());
+    }
+}
+
+#[test]
+fn malformed_keys_are_reported() {
+    assert_eq!(Err(VerifyError::InvalidPublicKey), verify_rsa(&[], b"msg", &[]));
+    assert_eq!(Err(VerifyError::InvalidPublicKey), verify_bls(&[0u8; 96], b"msg", &[0u8; 48]));
+    assert_eq!(Err(VerifyError::InvalidCrl), parse_crls(&[&[0x30, 0x00]]).map(|_| ()));
+
+    let mut out = [0u8; 4];
+    assert_eq!(Err(VerifyError::InvalidHex), hexstr_to_u8v("12zz", &mut out));
+    assert_eq!(Ok(()), hexstr_to_u8v("0a0B", &mut out));
+    assert_eq!([0x0a, 0x0b, 0, 0], out);
+}
+
+#[test]
+fn ias_timestamp_parses() {
+    assert_eq!(Some(0), parse_ias_timestamp("1970-01-01T00:00:00"));
+    assert_eq!(Some(1670515200), parse_ias_timestamp("2022-12-08T16:00:00.000000"));
+    assert_eq!(Some(951782400), parse_ias_timestamp("2000-02-29T00:00:00"));
+    assert_eq!(None, parse_ias_timestamp("2022-13-08T16:00:00"));
+    assert_eq!(None, parse_ias_timestamp("2022/12/08 16:00:00"));
+}
+
+#[test]
+fn ias_report_checks_status_and_freshness() {
+    let mut body = [0u8; QUOTE_BODY_LEN];
+    body[MR_ENCLAVE_OFFSET..MR_ENCLAVE_OFFSET + 32].copy_from_slice(&[7u8; 32]);
+    body[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + 32].copy_from_slice(&[9u8; 32]);
+    let report = |status: &str| format!(
+        "{{\"timestamp\":\"2022-12-08T16:00:00.000000\",\"isvEnclaveQuoteStatus\":\"{}\",\"isvEnclaveQuoteBody\":\"{}\"}}",
+        status,
+        base64::encode(&body[..]),
+    );
+    let now = 1670515200;
+
+    let parsed = parse_ias_report(report("OK").as_bytes(), now + 10, 3600).expect("valid report");
+    assert_eq!([7u8; 32], parsed.mr_enclave);
+    assert_eq!([9u8; 32], parsed.report_data[..32]);
+    assert_eq!(now, parsed.timestamp);
+
+    assert_eq!(None, parse_ias_report(report("GROUP_REVOKED").as_bytes(), now, 3600));
+    assert_eq!(None, parse_ias_report(report("OK").as_bytes(), now + 3601, 3600));
+    assert_eq!(None, parse_ias_report(report("OK").as_bytes(), now - REPORT_CLOCK_DRIFT - 1, 3600));
+    assert_eq!(None, parse_ias_report(b"not json", now, 3600));
+}
+
+#[test]
+fn ias_report_rejects_debug_enclaves() {
+    let report = |flags: u8| {
+        let mut body = [0u8; QUOTE_BODY_LEN];
+        body[ATTRIBUTES_OFFSET] = flags;
+        format!(
+            "{{\"timestamp\":\"2022-12-08T16:00:00.000000\",\"isvEnclaveQuoteStatus\":\"OK\",\"isvEnclaveQuoteBody\":\"{}\"}}",
+            base64::encode(&body[..]),
+        )
+    };
+    let now = 1670515200;
+
+    // INIT | MODE64BIT, as set for production enclaves.
+    assert!(parse_ias_report(report(0x05).as_bytes(), now, 3600).is_some());
+    assert_eq!(None, parse_ias_report(report(0x05 | SGX_FLAGS_DEBUG as u8).as_bytes(), now, 3600));
+}
+
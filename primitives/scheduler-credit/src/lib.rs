@@ -12,6 +12,9 @@ pub trait SchedulerCreditCounter<SchedulerCtrlAccountId> {
   /// Record the number of times the scheduler has been punished
 	fn record_punishment(scheduler_id: &SchedulerCtrlAccountId) -> DispatchResult;
 
+  /// Credit score of the scheduler over the recent periods, 0 for unknown schedulers
+	fn credit_score(scheduler_id: &SchedulerCtrlAccountId) -> u32;
+
 }
 
 /// Stash account finder, used to find the corresponding Stash account according to the Controller account
@@ -81,7 +81,6 @@ pub mod impls;
 use impls::{Author, CreditToBlockAuthor, SchedulerStashAccountFinder};
 // use frame_support::traits::OnRuntimeUpgrade;
 // pub use pallet_file_bank::migrations::TestMigrationFileBank;
-// pub use pallet_audit::migrations::MigrationSegmentBook;
 
 pub mod constants;
 use fp_rpc::TransactionStatus;
@@ -939,6 +938,16 @@ parameter_types! {
 	pub const DepositBufferPeriod: u32 = 3;
 	pub const MaxAward: u128 = 1_306_849_000_000_000_000;
 	pub const LockInPeriod: u8 = 2;
+	pub const PayoutBatchSize: u32 = 100;
+	pub const RewardImmediateShare: Perbill = Perbill::from_percent(20);
+	pub const RewardReleaseNumber: u32 = 180;
+	pub const RewardReleaseInterval: BlockNumber = DAYS;
+	pub const MaxRewardOrders: u32 = 200;
+	pub const MinerUnbondingPeriod: BlockNumber = 7 * DAYS;
+	pub const MinerMaxUnlockingChunks: u32 = 32;
+	pub const MaxDelegators: u32 = 64;
+	pub const MaxCommission: Perbill = Perbill::from_percent(50);
+	pub const MaxMultiaddrs: u32 = 8;
 }
 
 impl pallet_sminer::Config for Runtime {
@@ -958,6 +967,16 @@ impl pallet_sminer::Config for Runtime {
 	type MaxAward = MaxAward;
 	type LockInPeriod = LockInPeriod;
 	type ChallengeMinerMax = ChallengeMinerMax;
+	type PayoutBatchSize = PayoutBatchSize;
+	type RewardImmediateShare = RewardImmediateShare;
+	type RewardReleaseNumber = RewardReleaseNumber;
+	type RewardReleaseInterval = RewardReleaseInterval;
+	type MaxRewardOrders = MaxRewardOrders;
+	type UnbondingPeriod = MinerUnbondingPeriod;
+	type MaxUnlockingChunks = MinerMaxUnlockingChunks;
+	type MaxDelegators = MaxDelegators;
+	type MaxCommission = MaxCommission;
+	type MaxMultiaddrs = MaxMultiaddrs;
 }
 
 parameter_types! {
@@ -995,6 +1014,13 @@ parameter_types! {
 	pub const OneHours: BlockNumber = HOURS;
 	pub const SegUnsignedPriority: TransactionPriority = TransactionPriority::max_value();
 	pub const LockTime: BlockNumber = HOURS / 60;
+	pub const ProposalValidity: BlockNumber = HOURS / 60;
+	#[derive(Clone, PartialEq, Eq)]
+	pub const AuditHistoryLimit: u32 = 30;
+	pub const RewardSettleLimit: u32 = 100;
+	pub const ChallengeBaseLife: BlockNumber = 30 * MINUTES;
+	pub const IdleLifePerTiB: BlockNumber = 5 * MINUTES;
+	pub const ServiceLifePerTiB: BlockNumber = 10 * MINUTES;
 }
 
 impl pallet_audit::Config for Runtime {
@@ -1018,9 +1044,15 @@ impl pallet_audit::Config for Runtime {
 	type NextSessionRotation = Babe;
 	type UnsignedPriority = SegUnsignedPriority;
 	type LockTime = LockTime;
+	type ProposalValidity = ProposalValidity;
 	type SubmitValidationLimit = SubmitValidationLimit;
 	type ChallengeMinerMax = ChallengeMinerMax;
 	type SigmaMax = SigmaMax;
+	type AuditHistoryLimit = AuditHistoryLimit;
+	type RewardSettleLimit = RewardSettleLimit;
+	type ChallengeBaseLife = ChallengeBaseLife;
+	type IdleLifePerTiB = IdleLifePerTiB;
+	type ServiceLifePerTiB = ServiceLifePerTiB;
 }
 
 pub const SEGMENT_COUNT: u32 = 1000;
@@ -1096,6 +1128,13 @@ parameter_types! {
 	pub const ParamsLimit: u32 = 359;
 	#[derive(Clone, Eq, PartialEq)]
 	pub const MaxWhitelist: u32 = 200;
+	pub const MaxReportAge: u64 = 60 * 60 * 24;
+	pub const MaxCrls: u32 = 10;
+	pub const AttestationValidity: BlockNumber = 7 * DAYS;
+	pub const AttestationGracePeriod: BlockNumber = DAYS;
+	pub const Podr2KeyThreshold: Perbill = Perbill::from_percent(67);
+	pub const Podr2KeyActivationDelay: BlockNumber = DAYS;
+	pub const ExitTimeout: BlockNumber = 6 * HOURS;
 	// #[derive(Clone, Eq, PartialEq)]
 	// pub const ReportLength: u32 = 1354;
 	// #[derive(Clone, Eq, PartialEq)]
@@ -1113,6 +1152,14 @@ impl pallet_tee_worker::Config for Runtime {
 	type CreditCounter = SchedulerCredit;
 	type ParamsLimit = ParamsLimit;
 	type MaxWhitelist = MaxWhitelist;
+	type MaxReportAge = MaxReportAge;
+	type MaxCrls = MaxCrls;
+	type AttestationValidity = AttestationValidity;
+	type AttestationGracePeriod = AttestationGracePeriod;
+	type Podr2KeyThreshold = Podr2KeyThreshold;
+	type Podr2KeyActivationDelay = Podr2KeyActivationDelay;
+	type VerifyMission = Audit;
+	type ExitTimeout = ExitTimeout;
 	// type AuthorityId = pallet_tee_worker::ed25519::AuthorityId;
 }
 
@@ -1586,10 +1633,19 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
-	// TestMigrationFileBank<Runtime>,
-	// MigrationSegmentBook<Runtime>,
+	Migrations,
 >;
 
+// Storage migrations applied on the next runtime upgrade.
+type Migrations = (
+	pallet_sminer::migrations::v1::MigrateToV1<Runtime>,
+	pallet_sminer::migrations::v2::MigrateToV2<Runtime>,
+	pallet_sminer::migrations::v3::MigrateToV3<Runtime>,
+	pallet_tee_worker::migrations::v1::MigrateToV1<Runtime>,
+	pallet_audit::migrations::v2::MigrateToV2<Runtime>,
+	// TestMigrationFileBank<Runtime>,
+);
+
 #[cfg(feature = "runtime-benchmarks")]
 #[macro_use]
 extern crate frame_benchmarking;
@@ -1767,6 +1823,24 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_audit_runtime_api::AuditApi<Block, AccountId, BlockNumber> for Runtime {
+		fn audit_history(miner: AccountId) -> Vec<pallet_audit_runtime_api::AuditRecord<AccountId, BlockNumber>> {
+			Audit::miner_audit_history(miner)
+		}
+	}
+
+	impl pallet_tee_worker_runtime_api::TeeWorkerApi<Block, AccountId> for Runtime {
+		fn healthy_tee_workers() -> Vec<pallet_tee_worker_runtime_api::TeeWorkerEndpoint<AccountId>> {
+			TeeWorker::healthy_workers()
+		}
+	}
+
+	impl pallet_sminer_runtime_api::SminerApi<Block, AccountId, Balance, BlockNumber> for Runtime {
+		fn reward_unlocks(miner: AccountId) -> Vec<(BlockNumber, Balance)> {
+			Sminer::reward_unlocks(&miner)
+		}
+	}
+
 	impl pallet_contracts::ContractsApi<Block, AccountId, Balance, BlockNumber, Hash> for Runtime {
 		fn call(
 			origin: AccountId,
@@ -23,6 +23,7 @@ pub struct Cert<'a> {
     pub ee_or_ca: EndEntityOrCA<'a>,
 
     pub signed_data: signed_data::SignedData<'a>,
+    pub serial: untrusted::Input<'a>,
     pub issuer: untrusted::Input<'a>,
     pub validity: untrusted::Input<'a>,
     pub subject: untrusted::Input<'a>,
@@ -45,7 +46,7 @@ pub fn parse_cert<'a>(
 /// certificates.
 pub(crate) fn parse_cert_internal<'a>(
     cert_der: untrusted::Input<'a>, ee_or_ca: EndEntityOrCA<'a>,
-    serial_number: fn(input: &mut untrusted::Reader<'_>) -> Result<(), Error>,
+    serial_number: fn(input: &mut untrusted::Reader<'a>) -> Result<untrusted::Input<'a>, Error>,
 ) -> Result<Cert<'a>, Error> {
     let (tbs, signed_data) = cert_der.read_all(Error::BadDER, |cert_der| {
         der::nested(
@@ -58,7 +59,7 @@ pub(crate) fn parse_cert_internal<'a>(
 
     tbs.read_all(Error::BadDER, |tbs| {
         version3(tbs)?;
-        serial_number(tbs)?;
+        let serial = serial_number(tbs)?;
 
         let signature = der::expect_tag_and_get_value(tbs, der::Tag::Sequence)?;
         // TODO: In mozilla::pkix, the comparison is done based on the
@@ -82,6 +83,7 @@ pub(crate) fn parse_cert_internal<'a>(
             ee_or_ca,
 
             signed_data,
+            serial,
             issuer,
             validity,
             subject,
@@ -145,16 +147,19 @@ fn version3(input: &mut untrusted::Reader) -> Result<(), Error> {
     )
 }
 
-pub fn certificate_serial_number(input: &mut untrusted::Reader) -> Result<(), Error> {
+pub fn certificate_serial_number<'a>(
+    input: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, Error> {
     // https://tools.ietf.org/html/rfc5280#section-4.1.2.2:
     // * Conforming CAs MUST NOT use serialNumber values longer than 20 octets."
     // * "The serial number MUST be a positive integer [...]"
 
     let value = der::positive_integer(input)?;
-    if value.big_endian_without_leading_zero().len() > 20 {
+    let serial = value.big_endian_without_leading_zero();
+    if serial.len() > 20 {
         return Err(Error::BadDER);
     }
-    Ok(())
+    Ok(untrusted::Input::from(serial))
 }
 
 enum Understood {
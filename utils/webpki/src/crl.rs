@@ -0,0 +1,146 @@
+// Copyright 2015 Brian Smith.
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHORS DISCLAIM ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+// OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use crate::{cert::Cert, der, signed_data, Error, SignatureAlgorithm};
+
+/// A parsed X.509 certificate revocation list.
+///
+/// The list is not trusted on its own: it is only applied to a certificate
+/// when its issuer matches the certificate's issuer and its signature verifies
+/// with the issuer's public key while the chain is being built.
+pub struct CertRevocationList<'a> {
+    signed_data: signed_data::SignedData<'a>,
+    issuer: untrusted::Input<'a>,
+    revoked_certs: Option<untrusted::Input<'a>>,
+}
+
+impl<'a> CertRevocationList<'a> {
+    /// Parse the ASN.1 DER-encoded X.509 encoding of the CRL `crl_der`.
+    pub fn from(crl_der: &'a [u8]) -> Result<Self, Error> {
+        let crl_der = untrusted::Input::from(crl_der);
+        let (tbs, signed_data) = crl_der.read_all(Error::BadDER, |crl_der| {
+            der::nested(
+                crl_der,
+                der::Tag::Sequence,
+                Error::BadDER,
+                signed_data::parse_signed_data,
+            )
+        })?;
+
+        // https://tools.ietf.org/html/rfc5280#section-5.1.2
+        tbs.read_all(Error::BadDER, |tbs| {
+            // The version is omitted for v1 lists; only v1 and v2 exist.
+            if tbs.peek(der::Tag::Integer as u8) {
+                let version = der::small_nonnegative_integer(tbs)?;
+                if version != 1 {
+                    return Err(Error::UnsupportedCrlVersion);
+                }
+            }
+
+            let signature = der::expect_tag_and_get_value(tbs, der::Tag::Sequence)?;
+            if signature != signed_data.algorithm {
+                return Err(Error::SignatureAlgorithmMismatch);
+            }
+
+            let issuer = der::expect_tag_and_get_value(tbs, der::Tag::Sequence)?;
+            let _this_update = der::time_choice(tbs)?;
+            if tbs.peek(der::Tag::UTCTime as u8) || tbs.peek(der::Tag::GeneralizedTime as u8) {
+                let _next_update = der::time_choice(tbs)?;
+            }
+
+            let revoked_certs = if tbs.peek(der::Tag::Sequence as u8) {
+                let revoked_certs = der::expect_tag_and_get_value(tbs, der::Tag::Sequence)?;
+                // Validate the entries up front so that lookups can't fail later.
+                revoked_certs.read_all(Error::BadDER, |entries| {
+                    while !entries.at_end() {
+                        revoked_cert_serial(entries)?;
+                    }
+                    Ok(())
+                })?;
+                Some(revoked_certs)
+            } else {
+                None
+            };
+
+            // crlExtensions. None of them change which serials are revoked, so
+            // they are skipped.
+            if !tbs.at_end() {
+                der::expect_tag_and_get_value(tbs, der::Tag::ContextSpecificConstructed0)?;
+            }
+
+            Ok(Self {
+                signed_data,
+                issuer,
+                revoked_certs,
+            })
+        })
+    }
+
+    fn is_revoked(&self, serial: untrusted::Input) -> Result<bool, Error> {
+        let revoked_certs = match self.revoked_certs {
+            Some(revoked_certs) => revoked_certs,
+            None => return Ok(false),
+        };
+        revoked_certs.read_all(Error::BadDER, |entries| {
+            let mut revoked = false;
+            while !entries.at_end() {
+                if revoked_cert_serial(entries)? == serial {
+                    revoked = true;
+                }
+            }
+            Ok(revoked)
+        })
+    }
+}
+
+// revokedCertificates entry: SEQUENCE { userCertificate, revocationDate,
+// crlEntryExtensions OPTIONAL }.
+fn revoked_cert_serial<'a>(
+    entries: &mut untrusted::Reader<'a>,
+) -> Result<untrusted::Input<'a>, Error> {
+    der::nested(entries, der::Tag::Sequence, Error::BadDER, |entry| {
+        let serial = der::positive_integer(entry)?;
+        let _revocation_date = der::time_choice(entry)?;
+        if !entry.at_end() {
+            der::expect_tag_and_get_value(entry, der::Tag::Sequence)?;
+        }
+        Ok(untrusted::Input::from(
+            serial.big_endian_without_leading_zero(),
+        ))
+    })
+}
+
+/// Fails with `Error::CertRevoked` if `cert` is listed by any of `crls` that
+/// was issued by the certificate's issuer, whose public key is `issuer_spki`.
+///
+/// Lists from other issuers, or whose signature doesn't verify, are ignored.
+pub(crate) fn check_revocation(
+    supported_sig_algs: &[&SignatureAlgorithm], cert: &Cert, issuer_spki: untrusted::Input,
+    crls: &[CertRevocationList],
+) -> Result<(), Error> {
+    for crl in crls {
+        if crl.issuer != cert.issuer {
+            continue;
+        }
+        if signed_data::verify_signed_data(supported_sig_algs, issuer_spki, &crl.signed_data)
+            .is_err()
+        {
+            continue;
+        }
+        if crl.is_revoked(cert.serial)? {
+            return Err(Error::CertRevoked);
+        }
+    }
+    Ok(())
+}
@@ -30,6 +30,10 @@ pub enum Error {
     /// later than the certificate's notAfter time.
     CertExpired,
 
+    /// The certificate has been revoked by a certificate revocation list of
+    /// its issuer.
+    CertRevoked,
+
     /// The certificate is not valid for the name it is being validated for.
     CertNotValidForName,
 
@@ -70,6 +74,9 @@ pub enum Error {
     /// The certificate is not a v3 X.509 certificate.
     UnsupportedCertVersion,
 
+    /// The certificate revocation list is not a v1 or v2 X.509 CRL.
+    UnsupportedCrlVersion,
+
     /// The certificate contains an unsupported critical extension.
     UnsupportedCriticalExtension,
 
@@ -49,19 +49,22 @@ pub fn cert_der_as_trust_anchor(cert_der: &[u8]) -> Result<TrustAnchor, Error> {
 
 fn possibly_invalid_certificate_serial_number<'a>(
     input: &mut untrusted::Reader<'a>,
-) -> Result<(), Error> {
+) -> Result<untrusted::Input<'a>, Error> {
     // https://tools.ietf.org/html/rfc5280#section-4.1.2.2:
     // * Conforming CAs MUST NOT use serialNumber values longer than 20 octets."
     // * "The serial number MUST be a positive integer [...]"
     //
     // However, we don't enforce these constraints on trust anchors, as there
     // are widely-deployed trust anchors that violate these constraints.
-    skip(input, der::Tag::Integer)
+    der::expect_tag_and_get_value(input, der::Tag::Integer)
 }
 
 /// Generates code for hard-coding the given trust anchors into a program. This
 /// is designed to be used in a build script. `name` is the name of the public
 /// static variable that will contain the TrustAnchor array.
+///
+/// Requires the `std` feature.
+#[cfg(feature = "std")]
 pub fn generate_code_for_trust_anchors(name: &str, trust_anchors: &[TrustAnchor]) -> String {
     let decl = format!(
         "static {}: [TrustAnchor<'static>; {}] = ",
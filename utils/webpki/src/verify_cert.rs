@@ -14,13 +14,14 @@
 
 use crate::{
     cert::{self, Cert, EndEntityOrCA},
+    crl::{self, CertRevocationList},
     der, name, signed_data, time, Error, SignatureAlgorithm, TrustAnchor,
 };
 
 pub fn build_chain(
     required_eku_if_present: KeyPurposeId, supported_sig_algs: &[&SignatureAlgorithm],
-    trust_anchors: &[TrustAnchor], intermediate_certs: &[&[u8]], cert: &Cert, time: time::Time,
-    sub_ca_count: usize,
+    trust_anchors: &[TrustAnchor], intermediate_certs: &[&[u8]], crls: &[CertRevocationList],
+    cert: &Cert, time: time::Time, sub_ca_count: usize,
 ) -> Result<(), Error> {
     let used_as_ca = used_as_ca(&cert.ee_or_ca);
 
@@ -47,8 +48,6 @@ pub fn build_chain(
         },
     }
 
-    // TODO: revocation.
-
     match loop_while_non_fatal_error(trust_anchors, |trust_anchor: &TrustAnchor| {
         let trust_anchor_subject = untrusted::Input::from(trust_anchor.subject);
         if cert.issuer != trust_anchor_subject {
@@ -65,13 +64,16 @@ pub fn build_chain(
 
         // TODO: check_distrust(trust_anchor_subject, trust_anchor_spki)?;
 
-        check_signatures(supported_sig_algs, cert, trust_anchor_spki)?;
+        check_signatures(supported_sig_algs, cert, trust_anchor_spki, crls)?;
 
         Ok(())
     }) {
         Ok(()) => {
             return Ok(());
         },
+        Err(Error::CertRevoked) => {
+            return Err(Error::CertRevoked);
+        },
         Err(..) => {
             // If the error is not fatal, then keep going.
         },
@@ -115,6 +117,7 @@ pub fn build_chain(
             supported_sig_algs,
             trust_anchors,
             intermediate_certs,
+            crls,
             &potential_issuer,
             time,
             next_sub_ca_count,
@@ -124,14 +127,14 @@ pub fn build_chain(
 
 fn check_signatures(
     supported_sig_algs: &[&SignatureAlgorithm], cert_chain: &Cert,
-    trust_anchor_key: untrusted::Input,
+    trust_anchor_key: untrusted::Input, crls: &[CertRevocationList],
 ) -> Result<(), Error> {
     let mut spki_value = trust_anchor_key;
     let mut cert = cert_chain;
     loop {
         signed_data::verify_signed_data(supported_sig_algs, spki_value, &cert.signed_data)?;
 
-        // TODO: check revocation
+        crl::check_revocation(supported_sig_algs, cert, spki_value, crls)?;
 
         match &cert.ee_or_ca {
             &EndEntityOrCA::CA(child_cert) => {
@@ -324,6 +327,10 @@ where
             Ok(()) => {
                 return Ok(());
             },
+            // A revoked certificate can't be rescued by another path.
+            Err(Error::CertRevoked) => {
+                return Err(Error::CertRevoked);
+            },
             Err(..) => {
                 // If the error is not fatal, then keep going.
             },
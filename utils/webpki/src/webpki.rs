@@ -37,6 +37,7 @@ mod der;
 
 mod calendar;
 mod cert;
+mod crl;
 mod error;
 mod name;
 mod signed_data;
@@ -47,6 +48,7 @@ pub mod trust_anchor_util;
 
 mod verify_cert;
 
+pub use crl::CertRevocationList;
 pub use error::Error;
 pub use name::{DNSNameRef, InvalidDNSNameError};
 
@@ -127,12 +129,33 @@ impl<'a> EndEntityCert<'a> {
         &self, supported_sig_algs: &[&SignatureAlgorithm],
         &TLSServerTrustAnchors(trust_anchors): &TLSServerTrustAnchors,
         intermediate_certs: &[&[u8]], time: Time,
+    ) -> Result<(), Error> {
+        self.verify_is_valid_tls_server_cert_with_crls(
+            supported_sig_algs,
+            &TLSServerTrustAnchors(trust_anchors),
+            intermediate_certs,
+            &[],
+            time,
+        )
+    }
+
+    /// Like `verify_is_valid_tls_server_cert`, but additionally fails with
+    /// `Error::CertRevoked` if any certificate in the chain is listed by one
+    /// of `crls`.
+    ///
+    /// A revocation list is only applied to the certificates of its issuer,
+    /// and only if its signature verifies with that issuer's public key.
+    pub fn verify_is_valid_tls_server_cert_with_crls(
+        &self, supported_sig_algs: &[&SignatureAlgorithm],
+        &TLSServerTrustAnchors(trust_anchors): &TLSServerTrustAnchors,
+        intermediate_certs: &[&[u8]], crls: &[CertRevocationList], time: Time,
     ) -> Result<(), Error> {
         verify_cert::build_chain(
             verify_cert::EKU_SERVER_AUTH,
             supported_sig_algs,
             trust_anchors,
             intermediate_certs,
+            crls,
             &self.inner,
             time,
             0,
@@ -157,12 +180,33 @@ impl<'a> EndEntityCert<'a> {
         &self, supported_sig_algs: &[&SignatureAlgorithm],
         &TLSClientTrustAnchors(trust_anchors): &TLSClientTrustAnchors,
         intermediate_certs: &[&[u8]], time: Time,
+    ) -> Result<(), Error> {
+        self.verify_is_valid_tls_client_cert_with_crls(
+            supported_sig_algs,
+            &TLSClientTrustAnchors(trust_anchors),
+            intermediate_certs,
+            &[],
+            time,
+        )
+    }
+
+    /// Like `verify_is_valid_tls_client_cert`, but additionally fails with
+    /// `Error::CertRevoked` if any certificate in the chain is listed by one
+    /// of `crls`.
+    ///
+    /// A revocation list is only applied to the certificates of its issuer,
+    /// and only if its signature verifies with that issuer's public key.
+    pub fn verify_is_valid_tls_client_cert_with_crls(
+        &self, supported_sig_algs: &[&SignatureAlgorithm],
+        &TLSClientTrustAnchors(trust_anchors): &TLSClientTrustAnchors,
+        intermediate_certs: &[&[u8]], crls: &[CertRevocationList], time: Time,
     ) -> Result<(), Error> {
         verify_cert::build_chain(
             verify_cert::EKU_CLIENT_AUTH,
             supported_sig_algs,
             trust_anchors,
             intermediate_certs,
+            crls,
             &self.inner,
             time,
             0,